@@ -0,0 +1,486 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Serialize, Deserialize};
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+use web3::types::{H256, U256};
+
+use crate::blockchain_interface::BlockchainInterface;
+use crate::cost_accounting::wei_to_eth;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AlertKind {
+    LowBalance,
+    ShortRunway,
+    SyncLag,
+    StaleCityData,
+    NewDispute,
+    RpcError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertSeverity {
+    Resolved,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: AlertSeverity,
+    pub subject: String,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+pub trait AlertSink: Send + Sync {
+    fn send(&self, alert: &Alert);
+}
+
+pub struct LogAlertSink;
+
+impl AlertSink for LogAlertSink {
+    fn send(&self, alert: &Alert) {
+        eprintln!("[{:?}] {:?} {}: {}", alert.severity, alert.kind, alert.subject, alert.message);
+    }
+}
+
+pub struct ChannelAlertSink {
+    tx: mpsc::Sender<Alert>,
+    dropped: AtomicU64,
+}
+
+impl ChannelAlertSink {
+    pub fn new(buffer: usize) -> (Self, mpsc::Receiver<Alert>) {
+        let (tx, rx) = mpsc::channel(buffer);
+        (Self { tx, dropped: AtomicU64::new(0) }, rx)
+    }
+
+    // Alerts that could not be queued because the receiver was full or gone.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl AlertSink for ChannelAlertSink {
+    fn send(&self, alert: &Alert) {
+        if self.tx.try_send(alert.clone()).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    pub poll_interval: Duration,
+    pub cities: Vec<String>,
+    pub min_balance_wei: U256,
+    pub min_runway_hours: f64,
+    pub burn_rate_window: Duration,
+    pub max_sync_lag_secs: i64,
+    pub max_data_age_secs: i64,
+    pub city_max_data_age_secs: HashMap<String, i64>,
+    // Submissions watched for disputes; the oldest are dropped beyond this.
+    pub max_tracked_hashes: usize,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(60),
+            cities: Vec::new(),
+            min_balance_wei: U256::exp10(16),
+            min_runway_hours: 72.0,
+            burn_rate_window: Duration::from_secs(24 * 3600),
+            max_sync_lag_secs: 120,
+            max_data_age_secs: 3600,
+            city_max_data_age_secs: HashMap::new(),
+            max_tracked_hashes: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    pub timestamp: i64,
+    pub block_number: U256,
+    pub gas_price: U256,
+    pub balance_wei: U256,
+    pub burn_rate_eth_per_hour: Option<f64>,
+    pub runway_hours: Option<f64>,
+    pub sync_lag_secs: Option<i64>,
+    pub city_data_age_secs: HashMap<String, i64>,
+    pub dispute_counts: HashMap<String, u64>,
+}
+
+#[derive(Default)]
+struct MonitorState {
+    balance_samples: VecDeque<(i64, U256)>,
+    tracked_hashes: HashMap<[u8; 32], u64>,
+    tracked_order: VecDeque<[u8; 32]>,
+    active_alerts: HashSet<(AlertKind, String)>,
+    last_snapshot: Option<HealthSnapshot>,
+}
+
+pub struct AccountMonitor {
+    interface: Arc<BlockchainInterface>,
+    config: MonitorConfig,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    state: Arc<RwLock<MonitorState>>,
+}
+
+impl AccountMonitor {
+    pub fn new(interface: Arc<BlockchainInterface>, config: MonitorConfig) -> Self {
+        Self {
+            interface,
+            config,
+            sinks: Vec::new(),
+            state: Arc::new(RwLock::new(MonitorState::default())),
+        }
+    }
+
+    pub fn add_sink(&mut self, sink: Arc<dyn AlertSink>) {
+        self.sinks.push(sink);
+    }
+
+    // Submits through the monitored interface and watches the reading's hash for disputes.
+    pub async fn submit_weather_data(
+        &self,
+        city: String,
+        temperature: f64,
+        humidity: f64,
+        timestamp: u64,
+    ) -> Result<H256, Box<dyn std::error::Error>> {
+        let data_hash = BlockchainInterface::submission_hash(&city, temperature, humidity, timestamp);
+        let tx_hash = self.interface.submit_weather_data(city, temperature, humidity, timestamp).await?;
+        self.track_submission(data_hash).await;
+        Ok(tx_hash)
+    }
+
+    pub async fn track_submission(&self, data_hash: [u8; 32]) {
+        let mut state = self.state.write().await;
+        if state.tracked_hashes.contains_key(&data_hash) {
+            return;
+        }
+
+        state.tracked_hashes.insert(data_hash, 0);
+        state.tracked_order.push_back(data_hash);
+        while state.tracked_order.len() > self.config.max_tracked_hashes.max(1) {
+            if let Some(oldest) = state.tracked_order.pop_front() {
+                state.tracked_hashes.remove(&oldest);
+            }
+        }
+    }
+
+    pub async fn latest_snapshot(&self) -> Option<HealthSnapshot> {
+        self.state.read().await.last_snapshot.clone()
+    }
+
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = interval(self.config.poll_interval);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.check_once().await {
+                    self.raise(AlertKind::RpcError, "rpc", AlertSeverity::Warning, e).await;
+                } else {
+                    self.clear(AlertKind::RpcError, "rpc").await;
+                }
+            }
+        })
+    }
+
+    pub async fn check_once(&self) -> Result<HealthSnapshot, String> {
+        let now = unix_now();
+
+        let stats = self.interface.get_network_stats().await.map_err(|e| e.to_string())?;
+        let block_timestamp = self.interface.get_latest_block_timestamp().await.map_err(|e| e.to_string())?;
+
+        let (burn_rate, runway) = self.update_burn_rate(now, stats.account_balance).await;
+        self.check_balance(stats.account_balance, runway).await;
+
+        let sync_lag = now - block_timestamp as i64;
+        if sync_lag > self.config.max_sync_lag_secs {
+            let message = format!("latest block is {}s behind wall clock (limit {}s)", sync_lag, self.config.max_sync_lag_secs);
+            self.raise(AlertKind::SyncLag, "node", AlertSeverity::Warning, message).await;
+        } else {
+            self.clear(AlertKind::SyncLag, "node").await;
+        }
+
+        let mut city_data_age_secs = HashMap::new();
+        for city in &self.config.cities {
+            let data = match self.interface.get_weather_data(city).await.map_err(|e| e.to_string()) {
+                Ok(data) => data,
+                Err(e) => {
+                    let message = format!("no on-chain data could be read: {}", e);
+                    self.raise(AlertKind::StaleCityData, city, AlertSeverity::Warning, message).await;
+                    continue;
+                }
+            };
+
+            let age = block_timestamp as i64 - data.timestamp as i64;
+            city_data_age_secs.insert(city.clone(), age);
+
+            let limit = self.config.city_max_data_age_secs.get(city).copied().unwrap_or(self.config.max_data_age_secs);
+            if age > limit {
+                let message = format!("latest on-chain reading is {}s old (limit {}s)", age, limit);
+                self.raise(AlertKind::StaleCityData, city, AlertSeverity::Warning, message).await;
+            } else {
+                self.clear(AlertKind::StaleCityData, city).await;
+            }
+        }
+
+        let dispute_counts = self.check_disputes().await;
+
+        let snapshot = HealthSnapshot {
+            timestamp: now,
+            block_number: stats.block_number,
+            gas_price: stats.gas_price,
+            balance_wei: stats.account_balance,
+            burn_rate_eth_per_hour: burn_rate,
+            runway_hours: runway,
+            sync_lag_secs: Some(sync_lag),
+            city_data_age_secs,
+            dispute_counts,
+        };
+
+        self.state.write().await.last_snapshot = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    async fn update_burn_rate(&self, now: i64, balance: U256) -> (Option<f64>, Option<f64>) {
+        let mut state = self.state.write().await;
+        let window = self.config.burn_rate_window.as_secs() as i64;
+
+        state.balance_samples.push_back((now, balance));
+        while let Some(&(ts, _)) = state.balance_samples.front() {
+            if now - ts > window {
+                state.balance_samples.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let (first_ts, _) = *state.balance_samples.front().unwrap();
+        let elapsed_hours = (now - first_ts) as f64 / 3600.0;
+        if elapsed_hours <= 0.0 {
+            return (None, None);
+        }
+
+        // Only decreases count towards burn; top-ups would otherwise hide spending.
+        let spent: f64 = state.balance_samples
+            .iter()
+            .zip(state.balance_samples.iter().skip(1))
+            .filter(|((_, before), (_, after))| after < before)
+            .map(|((_, before), (_, after))| wei_to_eth(*before - *after))
+            .sum();
+
+        let burn_rate = spent / elapsed_hours;
+        let runway = if burn_rate > 0.0 { Some(wei_to_eth(balance) / burn_rate) } else { None };
+
+        (Some(burn_rate), runway)
+    }
+
+    async fn check_balance(&self, balance: U256, runway: Option<f64>) {
+        if balance < self.config.min_balance_wei {
+            let message = format!(
+                "reporter balance {:.6} ETH is below minimum {:.6} ETH",
+                wei_to_eth(balance),
+                wei_to_eth(self.config.min_balance_wei)
+            );
+            self.raise(AlertKind::LowBalance, "reporter", AlertSeverity::Critical, message).await;
+        } else {
+            self.clear(AlertKind::LowBalance, "reporter").await;
+        }
+
+        match runway {
+            Some(hours) if hours < self.config.min_runway_hours => {
+                let message = format!("balance lasts {:.1}h at current burn rate (minimum {:.1}h)", hours, self.config.min_runway_hours);
+                self.raise(AlertKind::ShortRunway, "reporter", AlertSeverity::Warning, message).await;
+            }
+            _ => self.clear(AlertKind::ShortRunway, "reporter").await,
+        }
+    }
+
+    async fn check_disputes(&self) -> HashMap<String, u64> {
+        let hashes: Vec<([u8; 32], u64)> = {
+            let state = self.state.read().await;
+            state.tracked_hashes.iter().map(|(hash, count)| (*hash, *count)).collect()
+        };
+
+        let mut counts = HashMap::new();
+        for (hash, previous) in hashes {
+            let count = match self.interface.get_dispute_count(hash).await {
+                Ok(count) => count.low_u64(),
+                Err(_) => continue,
+            };
+
+            let key = hash_hex(&hash);
+            if count > previous {
+                let message = format!("{} new dispute(s), {} total", count - previous, count);
+                self.emit(Alert {
+                    kind: AlertKind::NewDispute,
+                    severity: AlertSeverity::Critical,
+                    subject: key.clone(),
+                    message,
+                    timestamp: unix_now(),
+                });
+                self.record_dispute_count(hash, count).await;
+            }
+
+            counts.insert(key, count);
+        }
+
+        counts
+    }
+
+    // The hash may have been evicted while its count was fetched; re-inserting it would leave
+    // it outside tracked_order and never evicted again.
+    async fn record_dispute_count(&self, hash: [u8; 32], count: u64) {
+        if let Some(seen) = self.state.write().await.tracked_hashes.get_mut(&hash) {
+            *seen = (*seen).max(count);
+        }
+    }
+
+    async fn raise(&self, kind: AlertKind, subject: &str, severity: AlertSeverity, message: String) {
+        let newly_active = self.state.write().await.active_alerts.insert((kind, subject.to_string()));
+        if newly_active {
+            self.emit(Alert {
+                kind,
+                severity,
+                subject: subject.to_string(),
+                message,
+                timestamp: unix_now(),
+            });
+        }
+    }
+
+    async fn clear(&self, kind: AlertKind, subject: &str) {
+        let was_active = self.state.write().await.active_alerts.remove(&(kind, subject.to_string()));
+        if was_active {
+            self.emit(Alert {
+                kind,
+                severity: AlertSeverity::Resolved,
+                subject: subject.to_string(),
+                message: "condition cleared".to_string(),
+                timestamp: unix_now(),
+            });
+        }
+    }
+
+    fn emit(&self, alert: Alert) {
+        for sink in &self.sinks {
+            sink.send(&alert);
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn hash_hex(hash: &[u8; 32]) -> String {
+    let mut hex = String::from("0x");
+    for byte in hash {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn monitor(config: MonitorConfig) -> AccountMonitor {
+        // Nothing here reaches the node; the transport only has to parse.
+        let interface = BlockchainInterface::new(
+            "http://127.0.0.1:9",
+            "0x0000000000000000000000000000000000000001",
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".to_string(),
+        )
+        .await
+        .unwrap();
+        AccountMonitor::new(Arc::new(interface), config)
+    }
+
+    #[tokio::test]
+    async fn tracking_evicts_the_oldest_hashes_and_ignores_repeats() {
+        let monitor = monitor(MonitorConfig { max_tracked_hashes: 2, ..MonitorConfig::default() }).await;
+        monitor.track_submission([1; 32]).await;
+        monitor.track_submission([2; 32]).await;
+        monitor.track_submission([1; 32]).await;
+        monitor.track_submission([3; 32]).await;
+
+        let state = monitor.state.read().await;
+        assert_eq!(state.tracked_order, VecDeque::from(vec![[2; 32], [3; 32]]));
+        assert_eq!(state.tracked_hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn dispute_count_for_an_evicted_hash_is_not_tracked_again() {
+        let monitor = monitor(MonitorConfig { max_tracked_hashes: 1, ..MonitorConfig::default() }).await;
+        monitor.track_submission([1; 32]).await;
+        monitor.record_dispute_count([1; 32], 2).await;
+        assert_eq!(monitor.state.read().await.tracked_hashes.get(&[1; 32]), Some(&2));
+
+        // [1; 32] is evicted while its dispute count is in flight.
+        monitor.track_submission([2; 32]).await;
+        monitor.record_dispute_count([1; 32], 3).await;
+
+        let state = monitor.state.read().await;
+        assert!(!state.tracked_hashes.contains_key(&[1; 32]));
+        assert_eq!(state.tracked_hashes.len(), state.tracked_order.len());
+    }
+
+    #[tokio::test]
+    async fn alerts_fire_once_until_cleared() {
+        let mut monitor = monitor(MonitorConfig::default()).await;
+        let (sink, mut rx) = ChannelAlertSink::new(8);
+        monitor.add_sink(Arc::new(sink));
+
+        monitor.raise(AlertKind::SyncLag, "node", AlertSeverity::Warning, "behind".to_string()).await;
+        monitor.raise(AlertKind::SyncLag, "node", AlertSeverity::Warning, "still behind".to_string()).await;
+        monitor.clear(AlertKind::SyncLag, "node").await;
+        monitor.clear(AlertKind::SyncLag, "node").await;
+
+        assert_eq!(rx.recv().await.unwrap().severity, AlertSeverity::Warning);
+        assert_eq!(rx.recv().await.unwrap().severity, AlertSeverity::Resolved);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn full_alert_channel_counts_dropped_alerts() {
+        let (sink, _rx) = ChannelAlertSink::new(1);
+        let alert = Alert {
+            kind: AlertKind::LowBalance,
+            severity: AlertSeverity::Critical,
+            subject: "reporter".to_string(),
+            message: "low".to_string(),
+            timestamp: 0,
+        };
+        for _ in 0..3 {
+            sink.send(&alert);
+        }
+        assert_eq!(sink.dropped(), 2);
+    }
+
+    #[tokio::test]
+    async fn burn_rate_ignores_top_ups() {
+        let monitor = monitor(MonitorConfig::default()).await;
+        let eth = U256::exp10(18);
+        assert_eq!(monitor.update_burn_rate(0, eth * 10).await, (None, None));
+        monitor.update_burn_rate(1800, eth * 9).await;
+        monitor.update_burn_rate(2700, eth * 20).await;
+        let (burn_rate, runway) = monitor.update_burn_rate(3600, eth * 19).await;
+
+        assert_eq!(burn_rate, Some(2.0));
+        assert_eq!(runway, Some(9.5));
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use chrono::{Datelike, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+
+const MAD_TO_SIGMA: f64 = 1.4826;
+const SEASONAL_BINS: usize = 52;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JointMode {
+    Independent,
+    Combined,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    pub variables: Vec<WeatherVariable>,
+    pub threshold: f64,
+    pub timezone: Tz,
+    pub remove_seasonal: bool,
+    pub remove_diurnal: bool,
+    pub min_samples_per_bin: usize,
+    pub joint_mode: JointMode,
+    pub max_gap_secs: i64,
+    pub min_event_points: usize,
+}
+
+impl AnomalyConfig {
+    pub fn temperature(timezone: Tz) -> Self {
+        Self {
+            variables: vec![WeatherVariable::Temperature],
+            threshold: 3.5,
+            timezone,
+            remove_seasonal: true,
+            remove_diurnal: true,
+            min_samples_per_bin: 5,
+            joint_mode: JointMode::Independent,
+            max_gap_secs: 3 * 3600,
+            min_event_points: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnomalyDirection {
+    High,
+    Low,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyEvent {
+    pub location: String,
+    pub start: i64,
+    pub end: i64,
+    pub peak_timestamp: i64,
+    pub peak_score: f64,
+    pub mean_score: f64,
+    pub direction: AnomalyDirection,
+    pub variables: Vec<WeatherVariable>,
+    pub points: usize,
+}
+
+struct ScoredPoint {
+    timestamp: i64,
+    score: f64,
+    signed_score: f64,
+    variables: Vec<WeatherVariable>,
+}
+
+// Scores `points` against a baseline fitted on `history`, which should not include the tested points:
+// otherwise a long anomaly shifts its own baseline and hides itself.
+pub fn detect_anomalies(points: &[WeatherDataPoint], history: &[WeatherDataPoint], config: &AnomalyConfig) -> Vec<AnomalyEvent> {
+    if points.is_empty() || history.len() < 10 || config.variables.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted: Vec<&WeatherDataPoint> = points.iter().collect();
+    sorted.sort_by_key(|p| p.timestamp);
+
+    let baselines: Vec<(WeatherVariable, Baseline)> = config.variables
+        .iter()
+        .filter_map(|variable| Baseline::fit(history, *variable, config).map(|baseline| (*variable, baseline)))
+        .collect();
+
+    let mut scored = Vec::new();
+    for point in &sorted {
+        if point.qc_flags.failed() {
+            continue;
+        }
+        let scores: Vec<(WeatherVariable, f64)> = baselines
+            .iter()
+            .filter_map(|(variable, baseline)| baseline.score(point, config).map(|z| (*variable, z)))
+            .collect();
+        if scores.is_empty() {
+            continue;
+        }
+
+        let candidate = match config.joint_mode {
+            JointMode::Independent => {
+                let flagged: Vec<&(WeatherVariable, f64)> = scores.iter().filter(|(_, z)| z.abs() > config.threshold).collect();
+                flagged
+                    .iter()
+                    .max_by(|a, b| a.1.abs().partial_cmp(&b.1.abs()).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(_, peak)| ScoredPoint {
+                        timestamp: point.timestamp,
+                        score: peak.abs(),
+                        signed_score: *peak,
+                        variables: flagged.iter().map(|(v, _)| *v).collect(),
+                    })
+            }
+            JointMode::Combined => {
+                let combined = (scores.iter().map(|(_, z)| z * z).sum::<f64>() / scores.len() as f64).sqrt();
+                let net: f64 = scores.iter().map(|(_, z)| z).sum();
+                (combined > config.threshold).then(|| ScoredPoint {
+                    timestamp: point.timestamp,
+                    score: combined,
+                    signed_score: net.signum() * combined,
+                    variables: scores.iter().map(|(v, _)| *v).collect(),
+                })
+            }
+        };
+
+        if let Some(candidate) = candidate {
+            scored.push(candidate);
+        }
+    }
+
+    group_events(&sorted[0].location, scored, config)
+}
+
+// Seasonal and diurnal bin centres, then a median/MAD scale of what remains.
+struct Baseline {
+    variable: WeatherVariable,
+    seasonal: Option<BinBaseline>,
+    diurnal: Option<BinBaseline>,
+    center: f64,
+    scale: f64,
+}
+
+impl Baseline {
+    fn fit(history: &[WeatherDataPoint], variable: WeatherVariable, config: &AnomalyConfig) -> Option<Self> {
+        let circular = variable == WeatherVariable::WindDirection;
+        let samples: Vec<((usize, usize), f64)> = history
+            .iter()
+            .filter(|p| !p.qc_flags.failed() && variable.value(p).is_finite())
+            .filter_map(|p| Some((local_bins(p.timestamp, config)?, variable.value(p))))
+            .collect();
+
+        let mut residuals: Vec<f64> = samples.iter().map(|(_, value)| *value).collect();
+
+        let seasonal = config.remove_seasonal.then(|| {
+            let baseline = BinBaseline::fit(&residuals, |i| samples[i].0 .0, config.min_samples_per_bin, circular);
+            for (i, residual) in residuals.iter_mut().enumerate() {
+                *residual = difference(*residual, baseline.get(samples[i].0 .0), circular);
+            }
+            baseline
+        });
+
+        let diurnal = config.remove_diurnal.then(|| {
+            let baseline = BinBaseline::fit(&residuals, |i| samples[i].0 .1, config.min_samples_per_bin, circular);
+            for (i, residual) in residuals.iter_mut().enumerate() {
+                *residual = difference(*residual, baseline.get(samples[i].0 .1), circular);
+            }
+            baseline
+        });
+
+        let center = median(&residuals)?;
+        let deviations: Vec<f64> = residuals.iter().map(|r| (r - center).abs()).collect();
+        let scale = median(&deviations).unwrap_or(0.0) * MAD_TO_SIGMA;
+        if scale <= f64::EPSILON {
+            return None;
+        }
+
+        Some(Self { variable, seasonal, diurnal, center, scale })
+    }
+
+    fn score(&self, point: &WeatherDataPoint, config: &AnomalyConfig) -> Option<f64> {
+        let circular = self.variable == WeatherVariable::WindDirection;
+        let value = self.variable.value(point);
+        if !value.is_finite() {
+            return None;
+        }
+
+        let (season, hour) = local_bins(point.timestamp, config)?;
+        let mut residual = value;
+        if let Some(seasonal) = &self.seasonal {
+            residual = difference(residual, seasonal.get(season), circular);
+        }
+        if let Some(diurnal) = &self.diurnal {
+            residual = difference(residual, diurnal.get(hour), circular);
+        }
+        Some((residual - self.center) / self.scale)
+    }
+}
+
+fn local_bins(timestamp: i64, config: &AnomalyConfig) -> Option<(usize, usize)> {
+    let dt = config.timezone.timestamp_opt(timestamp, 0).single()?;
+    let bin = ((dt.ordinal0() as usize * SEASONAL_BINS) / 366).min(SEASONAL_BINS - 1);
+    Some((bin, dt.hour() as usize))
+}
+
+struct BinBaseline {
+    bins: HashMap<usize, f64>,
+    fallback: f64,
+}
+
+impl BinBaseline {
+    fn fit<F>(values: &[f64], bin_of: F, min_samples: usize, circular: bool) -> Self
+    where
+        F: Fn(usize) -> usize,
+    {
+        let mut bins: HashMap<usize, Vec<f64>> = HashMap::new();
+        for (i, value) in values.iter().enumerate() {
+            bins.entry(bin_of(i)).or_default().push(*value);
+        }
+
+        let center = |samples: &[f64]| if circular { circular_mean(samples) } else { median(samples) };
+        Self {
+            fallback: center(values).unwrap_or(0.0),
+            bins: bins
+                .into_iter()
+                .filter(|(_, samples)| samples.len() >= min_samples)
+                .filter_map(|(bin, samples)| center(&samples).map(|c| (bin, c)))
+                .collect(),
+        }
+    }
+
+    fn get(&self, bin: usize) -> f64 {
+        self.bins.get(&bin).copied().unwrap_or(self.fallback)
+    }
+}
+
+fn difference(value: f64, baseline: f64, circular: bool) -> f64 {
+    if circular {
+        (value - baseline + 540.0).rem_euclid(360.0) - 180.0
+    } else {
+        value - baseline
+    }
+}
+
+fn circular_mean(degrees: &[f64]) -> Option<f64> {
+    if degrees.is_empty() {
+        return None;
+    }
+    let (sin, cos) = degrees
+        .iter()
+        .fold((0.0, 0.0), |(s, c), d| (s + d.to_radians().sin(), c + d.to_radians().cos()));
+    Some(sin.atan2(cos).to_degrees().rem_euclid(360.0))
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let n = sorted.len();
+    Some(if n.is_multiple_of(2) { (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0 } else { sorted[n / 2] })
+}
+
+fn group_events(location: &str, scored: Vec<ScoredPoint>, config: &AnomalyConfig) -> Vec<AnomalyEvent> {
+    let mut events: Vec<AnomalyEvent> = Vec::new();
+    let mut current: Vec<ScoredPoint> = Vec::new();
+
+    for point in scored {
+        let continues = current.last().is_some_and(|last| point.timestamp - last.timestamp <= config.max_gap_secs);
+        if !continues && !current.is_empty() {
+            events.extend(finish_event(location, std::mem::take(&mut current), config));
+        }
+        current.push(point);
+    }
+    events.extend(finish_event(location, current, config));
+
+    events
+}
+
+fn finish_event(location: &str, points: Vec<ScoredPoint>, config: &AnomalyConfig) -> Option<AnomalyEvent> {
+    if points.is_empty() || points.len() < config.min_event_points {
+        return None;
+    }
+
+    let peak = points
+        .iter()
+        .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap_or(std::cmp::Ordering::Equal))?;
+
+    let highs = points.iter().filter(|p| p.signed_score > 0.0).count();
+    let direction = if highs == points.len() {
+        AnomalyDirection::High
+    } else if highs == 0 {
+        AnomalyDirection::Low
+    } else {
+        AnomalyDirection::Mixed
+    };
+
+    let mut variables: Vec<WeatherVariable> = points.iter().flat_map(|p| p.variables.iter().copied()).collect();
+    variables.sort();
+    variables.dedup();
+
+    Some(AnomalyEvent {
+        location: location.to_string(),
+        start: points[0].timestamp,
+        end: points[points.len() - 1].timestamp,
+        peak_timestamp: peak.timestamp,
+        peak_score: peak.score,
+        mean_score: points.iter().map(|p| p.score).sum::<f64>() / points.len() as f64,
+        direction,
+        variables,
+        points: points.len(),
+    })
+}
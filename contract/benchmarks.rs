@@ -0,0 +1,190 @@
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::{DataProcessor, WeatherDataPoint};
+use crate::data_query::TimeWindow;
+use crate::ingestion::ConflictPolicy;
+use crate::quality_control::QcFlags;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkConfig {
+    pub location: String,
+    pub years: u32,
+    pub interval_secs: i64,
+    pub window_hours: i64,
+    pub iterations: u32,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            location: "BENCH".to_string(),
+            years: 5,
+            interval_secs: 3600,
+            window_hours: 24 * 7,
+            iterations: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub iterations: u32,
+    pub mean: Duration,
+    pub min: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub points: usize,
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkReport {
+    pub fn speedup(&self, baseline: &str, candidate: &str) -> Option<f64> {
+        let mean = |name: &str| self.results.iter().find(|r| r.name == name).map(|r| r.mean.as_secs_f64());
+        Some(mean(baseline)? / mean(candidate)?.max(f64::EPSILON))
+    }
+}
+
+// Compares the columnar summaries against the previous clone-and-collect implementation on one synthetic history.
+// Timings depend on the machine; run the ignored test below for current numbers. Correctness of the
+// summaries is covered by the location_series tests.
+pub async fn run_processing_benchmark(config: BenchmarkConfig) -> std::io::Result<BenchmarkReport> {
+    let processor = DataProcessor::new();
+    let points = synthetic_history(&config);
+    let count = points.len();
+    let end = points.last().map_or(0, |p| p.timestamp + 1);
+    processor.add_batch_data(points).await?;
+
+    let window = TimeWindow::last_seconds(config.window_hours * 3600, end);
+    let full_window = TimeWindow::new(i64::MIN, i64::MAX);
+    let mut results = Vec::new();
+
+    results.push(time_async("legacy_full_history", config.iterations, || async {
+        let data = processor.query_points(&config.location, full_window).await;
+        legacy_summary(data)
+    }).await);
+    results.push(time_async("columnar_full_history", config.iterations, || async {
+        processor.process_location_data(&config.location).await.map_or(0.0, |p| p.average_temperature)
+    }).await);
+    results.push(time_async("legacy_window", config.iterations, || async {
+        let data = processor.query_points(&config.location, window).await;
+        legacy_summary(data)
+    }).await);
+    results.push(time_async("columnar_window", config.iterations, || async {
+        processor.process_window(&config.location, window).await.map_or(0.0, |p| p.average_temperature)
+    }).await);
+
+    // A KeepLast correction of a point in the middle of the history, which used to rebuild the summary.
+    let corrections = DataProcessor::new().with_conflict_policy(ConflictPolicy::KeepLast);
+    let mut history = synthetic_history(&config);
+    let middle = history[history.len() / 2].clone();
+    corrections.add_batch_data(std::mem::take(&mut history)).await?;
+    let mut offset = 0.0;
+    results.push(time_async("keep_last_correction", config.iterations, || {
+        offset += 0.1;
+        let correction = WeatherDataPoint { temperature: middle.temperature + offset, ..middle.clone() };
+        let corrections = &corrections;
+        async move { corrections.add_data_point(correction).await.map_or(0.0, |report| report.updated as f64) }
+    }).await);
+
+    Ok(BenchmarkReport { points: count, results })
+}
+
+async fn time_async<F, Fut>(name: &str, iterations: u32, mut run: F) -> BenchmarkResult
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = f64>,
+{
+    let iterations = iterations.max(1);
+    let mut total = Duration::ZERO;
+    let mut min = Duration::MAX;
+
+    for _ in 0..iterations {
+        let started = Instant::now();
+        std::hint::black_box(run().await);
+        let elapsed = started.elapsed();
+        total += elapsed;
+        min = min.min(elapsed);
+    }
+
+    BenchmarkResult {
+        name: name.to_string(),
+        iterations,
+        mean: total / iterations,
+        min,
+    }
+}
+
+// The per-call work process_location_data did before the columnar store.
+fn legacy_summary(data: Vec<WeatherDataPoint>) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let n = data.len() as f64;
+    let temperatures: Vec<f64> = data.par_iter().map(|d| d.temperature).collect();
+    let humidities: Vec<f64> = data.par_iter().map(|d| d.humidity).collect();
+    let pressures: Vec<f64> = data.par_iter().map(|d| d.pressure).collect();
+    let wind_speeds: Vec<f64> = data.par_iter().map(|d| d.wind_speed).collect();
+    let precipitations: Vec<f64> = data.par_iter().map(|d| d.precipitation).collect();
+
+    let average = temperatures.par_iter().sum::<f64>() / n;
+    let max = temperatures.par_iter().cloned().reduce(|| f64::NEG_INFINITY, f64::max);
+    let min = temperatures.par_iter().cloned().reduce(|| f64::INFINITY, f64::min);
+    let xy_sum: f64 = temperatures.iter().enumerate().map(|(i, &y)| i as f64 * y).sum();
+
+    average + max + min + xy_sum
+        + humidities.par_iter().sum::<f64>() / n
+        + pressures.par_iter().sum::<f64>() / n
+        + wind_speeds.par_iter().sum::<f64>() / n
+        + precipitations.par_iter().sum::<f64>()
+}
+
+fn synthetic_history(config: &BenchmarkConfig) -> Vec<WeatherDataPoint> {
+    let start = 1_600_000_000;
+    let count = (config.years as i64 * 365 * 86_400 / config.interval_secs.max(1)) as usize;
+
+    (0..count)
+        .map(|i| {
+            let timestamp = start + i as i64 * config.interval_secs;
+            let day = (timestamp % 86_400) as f64 / 86_400.0 * std::f64::consts::TAU;
+            let year = (timestamp % 31_536_000) as f64 / 31_536_000.0 * std::f64::consts::TAU;
+
+            WeatherDataPoint {
+                timestamp,
+                location: config.location.clone(),
+                temperature: 12.0 + 10.0 * year.sin() + 4.0 * day.sin(),
+                humidity: 65.0 + 20.0 * day.cos(),
+                pressure: 1013.0 + 5.0 * year.cos(),
+                wind_speed: 4.0 + 2.0 * day.sin().abs(),
+                wind_direction: (i as f64 * 7.0) % 360.0,
+                precipitation: if i % 17 == 0 { 1.2 } else { 0.0 },
+                qc_flags: QcFlags::default(),
+                source: "synthetic".to_string(),
+                provenance: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Not a bench target because the crate has no manifest to declare one in; run it explicitly with
+    // cargo test --release benchmarks -- --ignored --nocapture
+    #[tokio::test]
+    #[ignore = "timing benchmark; run in release mode"]
+    async fn processing_benchmark() {
+        let report = run_processing_benchmark(BenchmarkConfig::default()).await.unwrap();
+        for result in &report.results {
+            println!("{:<24} mean {:>12?} min {:>12?}", result.name, result.mean, result.min);
+        }
+        assert!(report.speedup("legacy_full_history", "columnar_full_history").unwrap() > 1.0);
+        assert!(report.speedup("legacy_window", "columnar_window").unwrap() > 1.0);
+    }
+}
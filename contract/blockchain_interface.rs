@@ -1,13 +1,15 @@
 use web3::Web3;
 use web3::transports::Http;
 use web3::contract::{Contract, Options};
-use web3::types::{Address, U256, H256, TransactionParameters, TransactionReceipt};
+use web3::contract::tokens::Tokenize;
+use web3::signing::{Key, SecretKey, SecretKeyRef};
+use web3::types::{Address, U256, H256, BlockId, BlockNumber, TransactionReceipt};
 use std::str::FromStr;
 use serde::{Serialize, Deserialize};
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use ethabi::Token;
 
+use crate::units::to_oracle_fixed;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OracleData {
     pub city: String,
@@ -22,7 +24,7 @@ pub struct BlockchainInterface {
     web3: Web3<Http>,
     contract: Contract<Http>,
     account_address: Address,
-    private_key: String,
+    private_key: SecretKey,
 }
 
 impl BlockchainInterface {
@@ -41,7 +43,8 @@ impl BlockchainInterface {
             include_bytes!("../abi/WeatherOracle.json"),
         )?;
 
-        let account_address = web3::signing::Key::from_secret(private_key.as_bytes())?.address();
+        let private_key = SecretKey::from_str(private_key.trim_start_matches("0x"))?;
+        let account_address = SecretKeyRef::new(&private_key).address();
 
         Ok(Self {
             web3,
@@ -51,6 +54,14 @@ impl BlockchainInterface {
         })
     }
 
+    pub fn account_address(&self) -> Address {
+        self.account_address
+    }
+
+    pub fn contract_address(&self) -> Address {
+        self.contract.address()
+    }
+
     pub async fn submit_weather_data(
         &self,
         city: String,
@@ -58,38 +69,18 @@ impl BlockchainInterface {
         humidity: f64,
         timestamp: u64,
     ) -> Result<H256, Box<dyn std::error::Error>> {
-        let temp_scaled = (temperature * 100.0) as i64;
-        let humidity_scaled = (humidity * 100.0) as i64;
-
-        let data_hash = self.calculate_data_hash(&city, temp_scaled, humidity_scaled, timestamp);
-
-        let function_call = self.contract
-            .call("submitData", (city, temp_scaled, humidity_scaled, timestamp, data_hash), self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
-        let gas_price = self.web3.eth().gas_price().await?;
+        // Rounded, not truncated: 21.7 * 100.0 is 2169.999... in floating point.
+        let temp_scaled = to_oracle_fixed(temperature);
+        let humidity_scaled = to_oracle_fixed(humidity);
 
-        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
-
-        let transaction = TransactionParameters {
-            to: Some(self.contract.address()),
-            data: function_call.tx.data.unwrap(),
-            gas: gas_estimate,
-            gas_price: Some(gas_price),
-            nonce: Some(nonce),
-            ..Default::default()
-        };
-
-        let signed_transaction = self.web3.accounts().sign_transaction(transaction, &self.private_key);
+        let data_hash = Self::submission_hash(&city, temperature, humidity, timestamp);
 
-        let tx_hash = self.web3.eth().send_raw_transaction(signed_transaction.raw_transaction).await?;
-
-        Ok(tx_hash)
+        self.send_transaction("submitData", (city, temp_scaled, humidity_scaled, timestamp, data_hash), U256::zero()).await
     }
 
     pub async fn get_weather_data(&self, city: &str) -> Result<OracleData, Box<dyn std::error::Error>> {
         let result: (String, i64, i64, u64, [u8; 32]) = self.contract
-            .query("getLatestData", (city,), self.account_address, Options::default(), None)
+            .query("getLatestData", (city.to_string(),), self.account_address, Options::default(), None)
             .await?;
 
         Ok(OracleData {
@@ -110,50 +101,11 @@ impl BlockchainInterface {
     }
 
     pub async fn stake_tokens(&self, amount: U256) -> Result<H256, Box<dyn std::error::Error>> {
-        let function_call = self.contract
-            .call("stake", (amount,), self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
-        let gas_price = self.web3.eth().gas_price().await?;
-        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
-
-        let transaction = TransactionParameters {
-            to: Some(self.contract.address()),
-            data: function_call.tx.data.unwrap(),
-            gas: gas_estimate,
-            gas_price: Some(gas_price),
-            nonce: Some(nonce),
-            value: amount,
-            ..Default::default()
-        };
-
-        let signed_transaction = self.web3.accounts().sign_transaction(transaction, &self.private_key);
-        let tx_hash = self.web3.eth().send_raw_transaction(signed_transaction.raw_transaction).await?;
-
-        Ok(tx_hash)
+        self.send_transaction("stake", (amount,), amount).await
     }
 
     pub async fn unstake_tokens(&self, amount: U256) -> Result<H256, Box<dyn std::error::Error>> {
-        let function_call = self.contract
-            .call("unstake", (amount,), self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
-        let gas_price = self.web3.eth().gas_price().await?;
-        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
-
-        let transaction = TransactionParameters {
-            to: Some(self.contract.address()),
-            data: function_call.tx.data.unwrap(),
-            gas: gas_estimate,
-            gas_price: Some(gas_price),
-            nonce: Some(nonce),
-            ..Default::default()
-        };
-
-        let signed_transaction = self.web3.accounts().sign_transaction(transaction, &self.private_key);
-        let tx_hash = self.web3.eth().send_raw_transaction(signed_transaction.raw_transaction).await?;
-
-        Ok(tx_hash)
+        self.send_transaction("unstake", (amount,), U256::zero()).await
     }
 
     pub async fn submit_dispute(
@@ -161,26 +113,7 @@ impl BlockchainInterface {
         data_hash: [u8; 32],
         reason: String,
     ) -> Result<H256, Box<dyn std::error::Error>> {
-        let function_call = self.contract
-            .call("submitDispute", (data_hash, reason), self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
-        let gas_price = self.web3.eth().gas_price().await?;
-        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
-
-        let transaction = TransactionParameters {
-            to: Some(self.contract.address()),
-            data: function_call.tx.data.unwrap(),
-            gas: gas_estimate,
-            gas_price: Some(gas_price),
-            nonce: Some(nonce),
-            ..Default::default()
-        };
-
-        let signed_transaction = self.web3.accounts().sign_transaction(transaction, &self.private_key);
-        let tx_hash = self.web3.eth().send_raw_transaction(signed_transaction.raw_transaction).await?;
-
-        Ok(tx_hash)
+        self.send_transaction("submitDispute", (data_hash, reason), U256::zero()).await
     }
 
     pub async fn get_dispute_count(&self, data_hash: [u8; 32]) -> Result<U256, Box<dyn std::error::Error>> {
@@ -192,26 +125,7 @@ impl BlockchainInterface {
     }
 
     pub async fn claim_rewards(&self) -> Result<H256, Box<dyn std::error::Error>> {
-        let function_call = self.contract
-            .call("claimRewards", (), self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
-        let gas_price = self.web3.eth().gas_price().await?;
-        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
-
-        let transaction = TransactionParameters {
-            to: Some(self.contract.address()),
-            data: function_call.tx.data.unwrap(),
-            gas: gas_estimate,
-            gas_price: Some(gas_price),
-            nonce: Some(nonce),
-            ..Default::default()
-        };
-
-        let signed_transaction = self.web3.accounts().sign_transaction(transaction, &self.private_key);
-        let tx_hash = self.web3.eth().send_raw_transaction(signed_transaction.raw_transaction).await?;
-
-        Ok(tx_hash)
+        self.send_transaction("claimRewards", (), U256::zero()).await
     }
 
     pub async fn get_reward_balance(&self) -> Result<U256, Box<dyn std::error::Error>> {
@@ -222,6 +136,14 @@ impl BlockchainInterface {
         Ok(result)
     }
 
+    pub async fn get_reward_pool(&self) -> Result<U256, Box<dyn std::error::Error>> {
+        let result: U256 = self.contract
+            .query("rewardPool", (), self.account_address, Options::default(), None)
+            .await?;
+
+        Ok(result)
+    }
+
     pub async fn get_network_stats(&self) -> Result<NetworkStats, Box<dyn std::error::Error>> {
         let block_number = self.web3.eth().block_number().await?;
         let gas_price = self.web3.eth().gas_price().await?;
@@ -229,13 +151,22 @@ impl BlockchainInterface {
         let balance = self.web3.eth().balance(self.account_address, None).await?;
 
         Ok(NetworkStats {
-            block_number,
+            block_number: U256::from(block_number.as_u64()),
             gas_price,
             chain_id,
             account_balance: balance,
         })
     }
 
+    pub async fn get_latest_block_timestamp(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        let block = self.web3.eth().block(BlockId::Number(BlockNumber::Latest)).await?;
+
+        match block {
+            Some(block) => Ok(block.timestamp.as_u64()),
+            None => Err("Latest block not found".into()),
+        }
+    }
+
     pub async fn wait_for_transaction(&self, tx_hash: H256) -> Result<TransactionReceipt, Box<dyn std::error::Error>> {
         let receipt = self.web3.eth().transaction_receipt(tx_hash).await?;
 
@@ -245,19 +176,37 @@ impl BlockchainInterface {
         }
     }
 
+    // Signed locally with the reporter key, so the node does not need to manage the account.
+    async fn send_transaction<P: Tokenize + Clone>(
+        &self,
+        function: &str,
+        params: P,
+        value: U256,
+    ) -> Result<H256, Box<dyn std::error::Error>> {
+        let gas_estimate = self.contract
+            .estimate_gas(function, params.clone(), self.account_address, Options::with(|o| o.value = Some(value)))
+            .await?;
+        let gas_price = self.web3.eth().gas_price().await?;
+        let nonce = self.web3.eth().transaction_count(self.account_address, None).await?;
+
+        let options = Options::with(|o| {
+            o.gas = Some(gas_estimate);
+            o.gas_price = Some(gas_price);
+            o.nonce = Some(nonce);
+            o.value = Some(value);
+        });
+        let tx_hash = self.contract.signed_call(function, params, options, &self.private_key).await?;
+
+        Ok(tx_hash)
+    }
+
+    // Hash submit_weather_data puts on chain for these values.
+    pub fn submission_hash(city: &str, temperature: f64, humidity: f64, timestamp: u64) -> [u8; 32] {
+        compute_data_hash(city, to_oracle_fixed(temperature), to_oracle_fixed(humidity), timestamp)
+    }
+
     fn calculate_data_hash(&self, city: &str, temperature: i64, humidity: i64, timestamp: u64) -> [u8; 32] {
-        use sha3::{Digest, Sha3_256};
-
-        let mut hasher = Sha3_256::new();
-        hasher.update(city.as_bytes());
-        hasher.update(&temperature.to_be_bytes());
-        hasher.update(&humidity.to_be_bytes());
-        hasher.update(&timestamp.to_be_bytes());
-
-        let result = hasher.finalize();
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&result);
-        hash
+        compute_data_hash(city, temperature, humidity, timestamp)
     }
 
     pub async fn validate_data_integrity(&self, data: &OracleData) -> Result<bool, Box<dyn std::error::Error>> {
@@ -277,10 +226,9 @@ impl BlockchainInterface {
     }
 
     pub async fn estimate_transaction_cost(&self, function_name: &str, params: Vec<Token>) -> Result<U256, Box<dyn std::error::Error>> {
-        let function_call = self.contract
-            .call(function_name, params, self.account_address, Options::default());
-
-        let gas_estimate = function_call.estimate_gas().await?;
+        let gas_estimate = self.contract
+            .estimate_gas(function_name, params, self.account_address, Options::default())
+            .await?;
         let gas_price = self.web3.eth().gas_price().await?;
 
         Ok(gas_estimate * gas_price)
@@ -293,4 +241,19 @@ pub struct NetworkStats {
     pub gas_price: U256,
     pub chain_id: U256,
     pub account_balance: U256,
-}
\ No newline at end of file
+}
+
+pub fn compute_data_hash(city: &str, temperature: i64, humidity: i64, timestamp: u64) -> [u8; 32] {
+    use sha3::{Digest, Sha3_256};
+
+    let mut hasher = Sha3_256::new();
+    hasher.update(city.as_bytes());
+    hasher.update(temperature.to_be_bytes());
+    hasher.update(humidity.to_be_bytes());
+    hasher.update(timestamp.to_be_bytes());
+
+    let result = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&result);
+    hash
+}
@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use chrono::{Datelike, NaiveDate, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+use crate::station_registry::solve_linear;
+
+const DAYS_PER_YEAR: f64 = 365.25;
+const QUANTILES: usize = 101;
+// Fewer same-month windows than this and multi-day windows are ranked against every month.
+const MIN_WINDOWS_PER_MONTH: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClimatologyConfig {
+    pub timezone: Tz,
+    pub annual_harmonics: usize,
+    pub diurnal_harmonics: usize,
+    pub min_samples: usize,
+    pub min_samples_per_month: usize,
+}
+
+impl ClimatologyConfig {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            timezone,
+            annual_harmonics: 3,
+            diurnal_harmonics: 2,
+            min_samples: 24 * 365,
+            min_samples_per_month: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClimateDeparture {
+    pub observed: f64,
+    pub normal: f64,
+    pub departure: f64,
+    pub percentile: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClimateNormals {
+    pub location: String,
+    pub variable: WeatherVariable,
+    pub timezone: Tz,
+    pub annual_harmonics: usize,
+    pub diurnal_harmonics: usize,
+    pub coefficients: Vec<f64>,
+    pub samples: usize,
+    pub first_timestamp: i64,
+    pub last_timestamp: i64,
+    // Residual quantiles (0th..100th percentile) per calendar month, for single observations and daily means.
+    pub point_quantiles: Vec<Vec<f64>>,
+    pub daily_quantiles: Vec<Vec<f64>>,
+    // Daily-mean residuals by date, from which multi-day windows get a distribution of their own length.
+    #[serde(default)]
+    pub daily_residuals: Vec<(NaiveDate, f64)>,
+}
+
+impl ClimateNormals {
+    pub fn normal_at(&self, timestamp: i64) -> f64 {
+        let (year_fraction, day_fraction, _, _) = local_position(self.timezone, timestamp);
+        self.normal_for(year_fraction, day_fraction)
+    }
+
+    // `year_fraction` and `day_fraction` are in [0, 1): position within the year and within the local day.
+    pub fn normal_for(&self, year_fraction: f64, day_fraction: f64) -> f64 {
+        design_row(year_fraction, day_fraction, self.annual_harmonics, self.diurnal_harmonics)
+            .iter()
+            .zip(self.coefficients.iter())
+            .map(|(x, c)| x * c)
+            .sum()
+    }
+
+    pub fn normal_on(&self, date: NaiveDate, hour: u32) -> f64 {
+        self.normal_for(date.ordinal0() as f64 / DAYS_PER_YEAR, hour as f64 / 24.0)
+    }
+
+    pub fn departure(&self, timestamp: i64, value: f64) -> ClimateDeparture {
+        let normal = self.normal_at(timestamp);
+        let (_, _, month, _) = local_position(self.timezone, timestamp);
+        ClimateDeparture {
+            observed: value,
+            normal,
+            departure: value - normal,
+            percentile: percentile_of(&self.point_quantiles[month], value - normal),
+        }
+    }
+
+    // Windows shorter than half a day are ranked against single observations, about a day against daily means
+    // and longer ones against the means of every run of as many consecutive days in the record.
+    // Missing samples are skipped; None when none remain.
+    pub fn window_departure(&self, samples: &[(i64, f64)]) -> Option<ClimateDeparture> {
+        let samples: Vec<(i64, f64)> = samples.iter().copied().filter(|(_, v)| v.is_finite()).collect();
+        if samples.is_empty() {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+        let observed = samples.iter().map(|(_, v)| v).sum::<f64>() / n;
+        let normal = samples.iter().map(|(ts, _)| self.normal_at(*ts)).sum::<f64>() / n;
+        let departure = observed - normal;
+
+        let first = samples.iter().map(|(ts, _)| *ts).min()?;
+        let last = samples.iter().map(|(ts, _)| *ts).max()?;
+        let (_, _, month, _) = local_position(self.timezone, first + (last - first) / 2);
+        let days = ((last - first) as f64 / 86_400.0).round().max(1.0) as usize;
+        let percentile = if last - first < 12 * 3600 {
+            percentile_of(&self.point_quantiles[month], departure)
+        } else {
+            match self.window_quantiles(days, month) {
+                Some(quantiles) => percentile_of(&quantiles, departure),
+                None => percentile_of(&self.daily_quantiles[month], departure),
+            }
+        };
+
+        Some(ClimateDeparture {
+            observed,
+            normal,
+            departure,
+            percentile,
+        })
+    }
+
+    fn window_quantiles(&self, days: usize, month: usize) -> Option<Vec<f64>> {
+        if days < 2 {
+            return None;
+        }
+
+        let mut same_month = Vec::new();
+        let mut pooled = Vec::new();
+        for run in self.daily_residuals.windows(days) {
+            if (run[days - 1].0 - run[0].0).num_days() != days as i64 - 1 {
+                continue;
+            }
+            let mean = run.iter().map(|(_, residual)| residual).sum::<f64>() / days as f64;
+            if run[days / 2].0.month0() as usize == month {
+                same_month.push(mean);
+            }
+            pooled.push(mean);
+        }
+
+        if same_month.len() >= MIN_WINDOWS_PER_MONTH {
+            Some(quantiles(same_month))
+        } else if pooled.len() >= 2 {
+            Some(quantiles(pooled))
+        } else {
+            None
+        }
+    }
+}
+
+pub fn compute_normals(
+    location: &str,
+    points: &[WeatherDataPoint],
+    variable: WeatherVariable,
+    config: &ClimatologyConfig,
+) -> Option<ClimateNormals> {
+    // Directions need circular statistics; a harmonic fit of degrees is meaningless.
+    if variable == WeatherVariable::WindDirection {
+        return None;
+    }
+
+    let samples: Vec<(i64, f64)> = points
+        .iter()
+        .filter(|p| p.qc_flags.settlement_eligible())
+        .map(|p| (p.timestamp, variable.value(p)))
+        .filter(|(_, v)| v.is_finite())
+        .collect();
+    if samples.len() < config.min_samples.max(1) {
+        return None;
+    }
+
+    let positions: Vec<(f64, f64, usize, NaiveDate)> = samples
+        .iter()
+        .map(|(ts, _)| local_position(config.timezone, *ts))
+        .collect();
+
+    let width = 1 + 2 * config.annual_harmonics + 6 * config.diurnal_harmonics;
+    let mut normal_matrix = vec![vec![0.0; width + 1]; width];
+    for ((_, value), (year, day, _, _)) in samples.iter().zip(positions.iter()) {
+        let row = design_row(*year, *day, config.annual_harmonics, config.diurnal_harmonics);
+        for i in 0..width {
+            for j in 0..width {
+                normal_matrix[i][j] += row[i] * row[j];
+            }
+            normal_matrix[i][width] += row[i] * value;
+        }
+    }
+    let coefficients = solve_linear(normal_matrix)?;
+
+    let mut normals = ClimateNormals {
+        location: location.to_string(),
+        variable,
+        timezone: config.timezone,
+        annual_harmonics: config.annual_harmonics,
+        diurnal_harmonics: config.diurnal_harmonics,
+        coefficients,
+        samples: samples.len(),
+        first_timestamp: samples.iter().map(|(ts, _)| *ts).min()?,
+        last_timestamp: samples.iter().map(|(ts, _)| *ts).max()?,
+        point_quantiles: Vec::new(),
+        daily_quantiles: Vec::new(),
+        daily_residuals: Vec::new(),
+    };
+
+    let mut point_residuals: Vec<Vec<f64>> = vec![Vec::new(); 12];
+    let mut daily: BTreeMap<NaiveDate, (usize, f64)> = BTreeMap::new();
+    for ((_, value), (year, day, month, date)) in samples.iter().zip(positions.iter()) {
+        let residual = value - normals.normal_for(*year, *day);
+        point_residuals[*month].push(residual);
+        let entry = daily.entry(*date).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += residual;
+    }
+
+    let mut daily_residuals: Vec<Vec<f64>> = vec![Vec::new(); 12];
+    for (date, (count, sum)) in daily {
+        let mean = sum / count as f64;
+        daily_residuals[date.month0() as usize].push(mean);
+        normals.daily_residuals.push((date, mean));
+    }
+
+    normals.point_quantiles = monthly_quantiles(point_residuals, config.min_samples_per_month);
+    normals.daily_quantiles = monthly_quantiles(daily_residuals, config.min_samples_per_month / 24);
+    Some(normals)
+}
+
+// Annual and diurnal harmonics plus their interaction with the first annual harmonic,
+// so the daily cycle can change amplitude and phase through the year.
+fn design_row(year_fraction: f64, day_fraction: f64, annual: usize, diurnal: usize) -> Vec<f64> {
+    let mut row = Vec::with_capacity(1 + 2 * annual + 6 * diurnal);
+    row.push(1.0);
+
+    for k in 1..=annual {
+        let angle = TAU * k as f64 * year_fraction;
+        row.push(angle.cos());
+        row.push(angle.sin());
+    }
+
+    let (season_cos, season_sin) = ((TAU * year_fraction).cos(), (TAU * year_fraction).sin());
+    for m in 1..=diurnal {
+        let angle = TAU * m as f64 * day_fraction;
+        let (c, s) = (angle.cos(), angle.sin());
+        row.extend_from_slice(&[c, s, c * season_cos, c * season_sin, s * season_cos, s * season_sin]);
+    }
+
+    row
+}
+
+fn local_position(timezone: Tz, timestamp: i64) -> (f64, f64, usize, NaiveDate) {
+    let local = timezone.timestamp_opt(timestamp, 0).unwrap();
+    let day_fraction = (local.hour() as f64 + local.minute() as f64 / 60.0) / 24.0;
+    let year_fraction = (local.ordinal0() as f64 + day_fraction) / DAYS_PER_YEAR;
+    (year_fraction, day_fraction, local.month0() as usize, local.date_naive())
+}
+
+// Months with too few residuals borrow the pooled distribution.
+fn monthly_quantiles(residuals: Vec<Vec<f64>>, min_per_month: usize) -> Vec<Vec<f64>> {
+    let pooled: Vec<f64> = residuals.iter().flatten().copied().collect();
+    let pooled_quantiles = quantiles(pooled);
+
+    residuals
+        .into_iter()
+        .map(|month| {
+            if month.len() >= min_per_month.max(2) {
+                quantiles(month)
+            } else {
+                pooled_quantiles.clone()
+            }
+        })
+        .collect()
+}
+
+fn quantiles(mut values: Vec<f64>) -> Vec<f64> {
+    if values.is_empty() {
+        return vec![0.0; QUANTILES];
+    }
+
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let last = (values.len() - 1) as f64;
+    (0..QUANTILES)
+        .map(|q| {
+            let position = last * q as f64 / (QUANTILES - 1) as f64;
+            let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+            values[lower] + (values[upper] - values[lower]) * (position - lower as f64)
+        })
+        .collect()
+}
+
+// NaN for a missing residual, which has no rank.
+fn percentile_of(quantiles: &[f64], residual: f64) -> f64 {
+    if !residual.is_finite() {
+        return f64::NAN;
+    }
+    let last = quantiles.len() - 1;
+    if residual <= quantiles[0] {
+        return 0.0;
+    }
+    if residual >= quantiles[last] {
+        return 100.0;
+    }
+
+    let upper = quantiles.partition_point(|q| *q < residual);
+    let lower = upper - 1;
+    let span = quantiles[upper] - quantiles[lower];
+    let within = if span > 0.0 { (residual - quantiles[lower]) / span } else { 0.5 };
+    (lower as f64 + within) * 100.0 / last as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality_control::QcFlags;
+
+    const START: i64 = 1_577_836_800; // 2020-01-01T00:00:00Z
+
+    // Two years of hourly temperatures: an annual cycle, a diurnal cycle and deterministic noise.
+    fn normals() -> ClimateNormals {
+        let points: Vec<WeatherDataPoint> = (0..2 * 365 * 24)
+            .map(|hour| {
+                let timestamp = START + hour * 3600;
+                let year = hour as f64 / (DAYS_PER_YEAR * 24.0);
+                let day = (hour % 24) as f64 / 24.0;
+                let noise = ((hour * 7919) % 101) as f64 / 50.0 - 1.0;
+                WeatherDataPoint {
+                    timestamp,
+                    location: "KJFK".to_string(),
+                    temperature: 12.0 - 10.0 * (TAU * year).cos() - 4.0 * (TAU * day).cos() + noise,
+                    humidity: 60.0,
+                    pressure: 1013.0,
+                    wind_speed: 3.0,
+                    wind_direction: 180.0,
+                    precipitation: 0.0,
+                    qc_flags: QcFlags::CHECKED,
+                    source: "metar".to_string(),
+                    provenance: None,
+                }
+            })
+            .collect();
+        compute_normals("KJFK", &points, WeatherVariable::Temperature, &ClimatologyConfig::new(chrono_tz::UTC)).unwrap()
+    }
+
+    #[test]
+    fn fitted_normal_follows_the_cycles() {
+        let normals = normals();
+        assert!((normals.normal_on(NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(), 0) - -2.0).abs() < 0.5);
+        assert!((normals.normal_on(NaiveDate::from_ymd_opt(2021, 7, 2).unwrap(), 12) - 26.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn percentiles_rank_departures() {
+        let normals = normals();
+        let timestamp = START + 400 * 86_400;
+        let normal = normals.normal_at(timestamp);
+        let low = normals.departure(timestamp, normal - 0.5).percentile;
+        let high = normals.departure(timestamp, normal + 0.5).percentile;
+        assert!(low < 50.0 && 50.0 < high, "{} {}", low, high);
+        assert_eq!(normals.departure(timestamp, normal + 50.0).percentile, 100.0);
+    }
+
+    #[test]
+    fn missing_values_have_no_percentile() {
+        let normals = normals();
+        let timestamp = START + 400 * 86_400;
+        assert!(normals.departure(timestamp, f64::NAN).percentile.is_nan());
+
+        let normal = normals.normal_at(timestamp);
+        let departure = normals.window_departure(&[(timestamp, normal + 1.0), (timestamp + 3600, f64::NAN)]).unwrap();
+        assert_eq!(departure.observed, normal + 1.0);
+        assert!(departure.percentile.is_finite());
+        assert!(normals.window_departure(&[(timestamp, f64::NAN)]).is_none());
+    }
+}
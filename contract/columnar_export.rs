@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use arrow::array::{ArrayRef, DictionaryArray, Float64Array, Int32Array, StringArray, TimestampSecondArray, UInt32Array};
+use arrow::datatypes::{DataType, Field, Int32Type, Schema, SchemaRef, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, Datelike, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::ExportFormat;
+use crate::data_query::{TimeWindow, WeatherVariable};
+use crate::derived_variables::{DerivedValues, DerivedVariable};
+use crate::location_series::LocationSeries;
+use crate::units::{Unit, UnitSystem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Partitioning {
+    None,
+    Location,
+    Month,
+    LocationAndMonth,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnarExportOptions {
+    // Empty selects every location.
+    pub locations: Vec<String>,
+    pub window: Option<TimeWindow>,
+    pub batch_rows: usize,
+    #[serde(default)]
+    pub units: UnitSystem,
+}
+
+impl Default for ColumnarExportOptions {
+    fn default() -> Self {
+        Self {
+            locations: Vec::new(),
+            window: None,
+            batch_rows: 65_536,
+            units: UnitSystem::metric(),
+        }
+    }
+}
+
+// One output file: the locations it holds, its partition labels and the rows' time window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPartition {
+    pub locations: Vec<String>,
+    pub location: Option<String>,
+    pub month: Option<String>,
+    pub window: Option<TimeWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedFile {
+    pub path: PathBuf,
+    pub rows: usize,
+}
+
+// Missing measurements are written as nulls rather than NaN so dataframes see them as missing.
+// Each measurement field records its unit in the field metadata under "unit".
+pub fn schema(units: &UnitSystem) -> SchemaRef {
+    let measurement = |name: &str, unit: Unit| {
+        Field::new(name, DataType::Float64, true)
+            .with_metadata(HashMap::from([("unit".to_string(), unit.symbol().to_string())]))
+    };
+    let mut fields = vec![
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Second, Some("UTC".into())), false),
+        Field::new("location", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), false),
+    ];
+    fields.extend(WeatherVariable::ALL.iter().map(|v| measurement(v.name(), units.unit(*v))));
+    fields.extend(DerivedVariable::ALL.iter().map(|v| measurement(v.name(), units.derived_unit(*v))));
+    fields.push(Field::new("source", DataType::Utf8, false));
+    fields.push(Field::new("qc_flags", DataType::UInt32, false));
+    Arc::new(Schema::new(fields))
+}
+
+// `dictionary` must be the same array for every batch of a file: Arrow IPC files cannot replace dictionaries.
+pub fn build_batch(
+    schema: &SchemaRef,
+    dictionary: &Arc<StringArray>,
+    location_key: i32,
+    series: &LocationSeries,
+    range: Range<usize>,
+    units: &UnitSystem,
+) -> Result<RecordBatch, ArrowError> {
+    let rows = range.len();
+    let elevation = series.elevation();
+    let nullable = |values: Vec<f64>, unit: Unit| -> ArrayRef {
+        Arc::new(
+            values
+                .into_iter()
+                .map(|v| v.is_finite().then(|| unit.from_canonical(v)))
+                .collect::<Float64Array>(),
+        )
+    };
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(TimestampSecondArray::from(series.timestamps()[range.clone()].to_vec()).with_timezone("UTC")),
+        Arc::new(DictionaryArray::<Int32Type>::try_new(
+            Int32Array::from(vec![location_key; rows]),
+            dictionary.clone() as ArrayRef,
+        )?),
+    ];
+    columns.extend(
+        WeatherVariable::ALL
+            .iter()
+            .map(|v| nullable(series.column(*v)[range.clone()].to_vec(), units.unit(*v))),
+    );
+
+    let derived: Vec<DerivedValues> = range
+        .clone()
+        .map(|i| {
+            let value = |v: WeatherVariable| series.column(v)[i];
+            DerivedValues::from_values(
+                value(WeatherVariable::Temperature),
+                value(WeatherVariable::Humidity),
+                value(WeatherVariable::Pressure),
+                value(WeatherVariable::WindSpeed),
+                value(WeatherVariable::WindDirection),
+                elevation,
+            )
+        })
+        .collect();
+    columns.extend(
+        DerivedVariable::ALL
+            .iter()
+            .map(|v| nullable(derived.iter().map(|d| d.get(*v).unwrap_or(f64::NAN)).collect(), units.derived_unit(*v))),
+    );
+
+    columns.push(Arc::new(range.clone().map(|i| Some(series.source(i))).collect::<StringArray>()));
+    columns.push(Arc::new(series.qc_flags()[range].iter().map(|f| f.0).collect::<UInt32Array>()));
+
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+pub enum BatchWriter<W: Write + Send> {
+    Parquet(ArrowWriter<W>),
+    ArrowIpc(FileWriter<W>),
+}
+
+impl<W: Write + Send> BatchWriter<W> {
+    // Parquet row groups are flushed every `batch_rows` so the writer never buffers more than one batch.
+    pub fn new(format: ExportFormat, writer: W, schema: &SchemaRef, batch_rows: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match format {
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(batch_rows.max(1))
+                    .build();
+                BatchWriter::Parquet(ArrowWriter::try_new(writer, schema.clone(), Some(properties))?)
+            }
+            ExportFormat::ArrowIpc => BatchWriter::ArrowIpc(FileWriter::try_new(writer, schema)?),
+            ExportFormat::Json | ExportFormat::Csv => return Err(format!("{:?} is not a columnar format", format).into()),
+        })
+    }
+
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            BatchWriter::Parquet(writer) => writer.write(batch)?,
+            BatchWriter::ArrowIpc(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            BatchWriter::Parquet(writer) => {
+                writer.close()?;
+            }
+            BatchWriter::ArrowIpc(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+// Hive-style layout (`location=.../month=YYYY-MM/`), which pandas, polars and Spark read as partition columns.
+pub fn partition_path(root: &Path, location: Option<&str>, month: Option<&str>, format: &ExportFormat) -> PathBuf {
+    let mut path = root.to_path_buf();
+    if let Some(location) = location {
+        path.push(format!("location={}", encode_partition_value(location)));
+    }
+    if let Some(month) = month {
+        path.push(format!("month={}", month));
+    }
+    path.push(format!("part-0.{}", format.extension()));
+    path
+}
+
+fn encode_partition_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.') {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+// UTC calendar months overlapping [start, end], labelled YYYY-MM.
+pub fn month_windows(start: i64, end: i64) -> Vec<(String, TimeWindow)> {
+    let first = match DateTime::from_timestamp(start, 0) {
+        Some(first) => first.date_naive(),
+        None => return Vec::new(),
+    };
+    let mut month = NaiveDate::from_ymd_opt(first.year(), first.month(), 1).unwrap();
+    let mut windows = Vec::new();
+
+    loop {
+        let next = if month.month() == 12 {
+            NaiveDate::from_ymd_opt(month.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(month.year(), month.month() + 1, 1)
+        };
+        let next = match next {
+            Some(next) => next,
+            None => break,
+        };
+        let window = TimeWindow::new(
+            month.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp(),
+            next.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp(),
+        );
+        windows.push((format!("{:04}-{:02}", month.year(), month.month()), window));
+        if window.end > end {
+            break;
+        }
+        month = next;
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+    use arrow::array::Array;
+    use arrow::ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use crate::data_processor::{DataProcessor, WeatherDataPoint};
+    use crate::quality_control::QcFlags;
+
+    const NOV_14: i64 = 1_700_000_000;
+    const NOV_30: i64 = 1_701_302_400;
+    const DEC_1: i64 = 1_701_388_800;
+
+    fn point(location: &str, timestamp: i64, source: &str, humidity: f64) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: location.to_string(),
+            temperature: 10.0,
+            humidity,
+            pressure: 1010.0,
+            wind_speed: 3.0,
+            wind_direction: 180.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::CHECKED,
+            source: source.to_string(),
+            provenance: None,
+        }
+    }
+
+    // KJFK has two sources at its first timestamp and three at its second.
+    async fn processor() -> DataProcessor {
+        let processor = DataProcessor::new();
+        let points = vec![
+            point("KJFK", NOV_14, "a", 50.0),
+            point("KJFK", NOV_14, "b", f64::NAN),
+            point("KJFK", NOV_14 + 3600, "a", 51.0),
+            point("KJFK", NOV_14 + 3600, "b", 52.0),
+            point("KJFK", NOV_14 + 3600, "c", 53.0),
+            point("KJFK", NOV_14 + 7200, "a", 54.0),
+            point("Portland, OR", NOV_30, "a", 60.0),
+            point("Portland, OR", DEC_1, "a", 61.0),
+        ];
+        processor.add_batch_data(points).await.unwrap();
+        processor
+    }
+
+    fn options(batch_rows: usize) -> ColumnarExportOptions {
+        ColumnarExportOptions { batch_rows, ..ColumnarExportOptions::default() }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("weather-columnar-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    fn check_schema(schema: &Schema) {
+        let units = UnitSystem::metric();
+        assert_eq!(schema.field_with_name("timestamp").unwrap().data_type(), &DataType::Timestamp(TimeUnit::Second, Some("UTC".into())));
+        assert_eq!(
+            schema.field_with_name("location").unwrap().data_type(),
+            &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+        );
+        for variable in WeatherVariable::ALL {
+            let field = schema.field_with_name(variable.name()).unwrap();
+            assert!(field.is_nullable());
+            assert_eq!(field.metadata().get("unit").map(String::as_str), Some(units.unit(variable).symbol()));
+        }
+    }
+
+    #[tokio::test]
+    async fn ipc_export_reads_back_with_schema_nulls_and_whole_timestamps_per_batch() {
+        let mut bytes = Vec::new();
+        let rows = processor().await.export_columnar(&mut bytes, ExportFormat::ArrowIpc, &options(2)).await.unwrap();
+        assert_eq!(rows, 8);
+
+        let reader = FileReader::try_new(Cursor::new(bytes), None).unwrap();
+        check_schema(&reader.schema());
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+
+        // A batch of two rows grows to three rather than split the sources at KJFK's second timestamp.
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).collect::<Vec<_>>(), vec![2, 3, 1, 2]);
+
+        let humidity = batches[0].column_by_name("humidity").unwrap();
+        assert_eq!(humidity.null_count(), 1);
+        assert!(humidity.is_null(1));
+
+        let timestamps = batches[1].column_by_name("timestamp").unwrap().as_any().downcast_ref::<TimestampSecondArray>().unwrap();
+        assert!(timestamps.iter().all(|t| t == Some(NOV_14 + 3600)));
+
+        let locations = batches[3].column_by_name("location").unwrap().as_any().downcast_ref::<DictionaryArray<Int32Type>>().unwrap();
+        let names = locations.values().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(names.value(locations.keys().value(0) as usize), "Portland, OR");
+    }
+
+    #[tokio::test]
+    async fn parquet_export_reads_back_with_schema_and_nulls() {
+        let root = temp_root("parquet");
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("all.parquet");
+        let rows = processor().await.export_columnar(File::create(&path).unwrap(), ExportFormat::Parquet, &options(2)).await.unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        check_schema(builder.schema());
+        let batches: Vec<RecordBatch> = builder.build().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), rows);
+        assert_eq!(batches.iter().map(|b| b.column_by_name("humidity").unwrap().null_count()).sum::<usize>(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn partitioned_export_writes_hive_paths() {
+        let root = temp_root("partitioned");
+        let files = processor()
+            .await
+            .export_partitioned(&root, ExportFormat::Parquet, Partitioning::LocationAndMonth, &options(1024))
+            .await
+            .unwrap();
+
+        let layout: Vec<(String, usize)> = files
+            .iter()
+            .map(|file| (file.path.strip_prefix(&root).unwrap().to_string_lossy().into_owned(), file.rows))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                ("location=KJFK/month=2023-11/part-0.parquet".to_string(), 6),
+                ("location=Portland%2C%20OR/month=2023-11/part-0.parquet".to_string(), 1),
+                ("location=Portland%2C%20OR/month=2023-12/part-0.parquet".to_string(), 1),
+            ]
+        );
+
+        let december = ParquetRecordBatchReaderBuilder::try_new(File::open(&files[2].path).unwrap()).unwrap().build().unwrap();
+        let batch = december.into_iter().next().unwrap().unwrap();
+        let timestamps = batch.column_by_name("timestamp").unwrap().as_any().downcast_ref::<TimestampSecondArray>().unwrap();
+        assert_eq!(timestamps.value(0), DEC_1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
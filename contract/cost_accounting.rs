@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Serialize, Deserialize};
+use web3::types::{H256, TransactionReceipt, U256};
+
+use crate::data_import::csv_field;
+
+const UNATTRIBUTED: &str = "unattributed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OperationType {
+    SubmitData,
+    Stake,
+    Unstake,
+    SubmitDispute,
+    ClaimRewards,
+    Other,
+}
+
+impl OperationType {
+    pub fn from_function_name(name: &str) -> Self {
+        match name {
+            "submitData" => OperationType::SubmitData,
+            "stake" => OperationType::Stake,
+            "unstake" => OperationType::Unstake,
+            "submitDispute" => OperationType::SubmitDispute,
+            "claimRewards" => OperationType::ClaimRewards,
+            _ => OperationType::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEntry {
+    pub operation: OperationType,
+    pub city: Option<String>,
+    pub timestamp: i64,
+    pub tx_hash: H256,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+    pub cost_wei: U256,
+    pub succeeded: bool,
+}
+
+impl CostEntry {
+    pub fn new(
+        operation: OperationType,
+        city: Option<String>,
+        timestamp: i64,
+        tx_hash: H256,
+        gas_used: U256,
+        effective_gas_price: U256,
+        succeeded: bool,
+    ) -> Self {
+        Self {
+            operation,
+            city,
+            timestamp,
+            tx_hash,
+            gas_used,
+            effective_gas_price,
+            cost_wei: gas_used * effective_gas_price,
+            succeeded,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardEntry {
+    pub city: Option<String>,
+    pub timestamp: i64,
+    pub amount_wei: U256,
+    pub tx_hash: Option<H256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyCityCost {
+    pub date: NaiveDate,
+    pub city: String,
+    pub submissions: u64,
+    pub operations: u64,
+    pub failed_operations: u64,
+    pub gas_used: U256,
+    pub cost_wei: U256,
+    pub rewards_wei: U256,
+    pub net_eth: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationCost {
+    pub operation: OperationType,
+    pub count: u64,
+    pub gas_used: U256,
+    pub cost_wei: U256,
+    pub average_cost_eth: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CitySummary {
+    pub city: String,
+    pub submissions: u64,
+    pub cost_wei: U256,
+    pub rewards_wei: U256,
+    pub net_eth: f64,
+    pub cost_per_submission_eth: f64,
+    pub profitable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfitLossReport {
+    pub daily: Vec<DailyCityCost>,
+    pub by_operation: Vec<OperationCost>,
+    pub by_city: Vec<CitySummary>,
+    pub total_cost_wei: U256,
+    pub total_rewards_wei: U256,
+    pub net_eth: f64,
+}
+
+impl ProfitLossReport {
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("date,city,submissions,operations,failed_operations,gas_used,cost_wei,rewards_wei,net_eth\n");
+
+        for row in &self.daily {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                row.date,
+                csv_field(&row.city, ','),
+                row.submissions,
+                row.operations,
+                row.failed_operations,
+                row.gas_used,
+                row.cost_wei,
+                row.rewards_wei,
+                row.net_eth
+            ));
+        }
+
+        csv
+    }
+
+    pub fn city_summary_csv(&self) -> String {
+        let mut csv = String::from("city,submissions,cost_wei,rewards_wei,net_eth,cost_per_submission_eth,profitable\n");
+
+        for row in &self.by_city {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                csv_field(&row.city, ','),
+                row.submissions,
+                row.cost_wei,
+                row.rewards_wei,
+                row.net_eth,
+                row.cost_per_submission_eth,
+                row.profitable
+            ));
+        }
+
+        csv
+    }
+
+    pub fn operation_csv(&self) -> String {
+        let mut csv = String::from("operation,count,gas_used,cost_wei,average_cost_eth\n");
+
+        for row in &self.by_operation {
+            csv.push_str(&format!(
+                "{:?},{},{},{},{}\n",
+                row.operation,
+                row.count,
+                row.gas_used,
+                row.cost_wei,
+                row.average_cost_eth
+            ));
+        }
+
+        csv
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CostLedger {
+    entries: Vec<CostEntry>,
+    rewards: Vec<RewardEntry>,
+}
+
+impl CostLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_receipt(
+        &mut self,
+        operation: OperationType,
+        city: Option<String>,
+        timestamp: i64,
+        receipt: &TransactionReceipt,
+    ) -> Result<U256, Box<dyn std::error::Error>> {
+        let gas_used = receipt.gas_used.ok_or("Receipt has no gas_used")?;
+        let effective_gas_price = receipt.effective_gas_price.ok_or("Receipt has no effective_gas_price")?;
+        let succeeded = receipt.status.is_none_or(|status| status.as_u64() == 1);
+
+        Ok(self.record_cost(CostEntry::new(operation, city, timestamp, receipt.transaction_hash, gas_used, effective_gas_price, succeeded)))
+    }
+
+    pub fn record_cost(&mut self, entry: CostEntry) -> U256 {
+        let cost_wei = entry.cost_wei;
+        self.entries.push(entry);
+        cost_wei
+    }
+
+    pub fn record_reward(&mut self, city: Option<String>, timestamp: i64, amount_wei: U256, tx_hash: Option<H256>) {
+        self.rewards.push(RewardEntry {
+            city,
+            timestamp,
+            amount_wei,
+            tx_hash,
+        });
+    }
+
+    pub fn entries(&self) -> &[CostEntry] {
+        &self.entries
+    }
+
+    pub fn rewards(&self) -> &[RewardEntry] {
+        &self.rewards
+    }
+
+    pub fn build_report(&self, start: Option<i64>, end: Option<i64>) -> ProfitLossReport {
+        let in_range = |ts: i64| start.is_none_or(|s| ts >= s) && end.is_none_or(|e| ts < e);
+
+        let mut daily: BTreeMap<(NaiveDate, String), DailyCityCost> = BTreeMap::new();
+        let mut by_operation: BTreeMap<OperationType, OperationCost> = BTreeMap::new();
+
+        for entry in self.entries.iter().filter(|e| in_range(e.timestamp)) {
+            let date = day_of(entry.timestamp);
+            let city = entry.city.clone().unwrap_or_else(|| UNATTRIBUTED.to_string());
+
+            let row = daily.entry((date, city.clone())).or_insert_with(|| empty_day(date, city));
+            row.operations += 1;
+            if entry.operation == OperationType::SubmitData && entry.succeeded {
+                row.submissions += 1;
+            }
+            if !entry.succeeded {
+                row.failed_operations += 1;
+            }
+            row.gas_used += entry.gas_used;
+            row.cost_wei += entry.cost_wei;
+
+            let op = by_operation.entry(entry.operation).or_insert_with(|| OperationCost {
+                operation: entry.operation,
+                count: 0,
+                gas_used: U256::zero(),
+                cost_wei: U256::zero(),
+                average_cost_eth: 0.0,
+            });
+            op.count += 1;
+            op.gas_used += entry.gas_used;
+            op.cost_wei += entry.cost_wei;
+        }
+
+        for op in by_operation.values_mut() {
+            op.average_cost_eth = wei_to_eth(op.cost_wei) / op.count.max(1) as f64;
+        }
+
+        let submission_share = self.submission_share(&daily);
+
+        for reward in self.rewards.iter().filter(|r| in_range(r.timestamp)) {
+            let date = day_of(reward.timestamp);
+
+            match &reward.city {
+                Some(city) => {
+                    let row = daily.entry((date, city.clone())).or_insert_with(|| empty_day(date, city.clone()));
+                    row.rewards_wei += reward.amount_wei;
+                }
+                None if submission_share.is_empty() => {
+                    let row = daily
+                        .entry((date, UNATTRIBUTED.to_string()))
+                        .or_insert_with(|| empty_day(date, UNATTRIBUTED.to_string()));
+                    row.rewards_wei += reward.amount_wei;
+                }
+                None => {
+                    // Integer division drops wei; the last share takes the remainder so the shares add up to the reward.
+                    let mut remaining = reward.amount_wei;
+                    for (index, (key, numerator, denominator)) in submission_share.iter().enumerate() {
+                        let amount = if index + 1 == submission_share.len() {
+                            remaining
+                        } else {
+                            reward.amount_wei * U256::from(*numerator) / U256::from(*denominator)
+                        };
+                        remaining -= amount;
+                        if let Some(row) = daily.get_mut(key) {
+                            row.rewards_wei += amount;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut cities: BTreeMap<String, CitySummary> = BTreeMap::new();
+        for row in daily.values_mut() {
+            row.net_eth = wei_to_eth(row.rewards_wei) - wei_to_eth(row.cost_wei);
+
+            let summary = cities.entry(row.city.clone()).or_insert_with(|| CitySummary {
+                city: row.city.clone(),
+                submissions: 0,
+                cost_wei: U256::zero(),
+                rewards_wei: U256::zero(),
+                net_eth: 0.0,
+                cost_per_submission_eth: 0.0,
+                profitable: false,
+            });
+            summary.submissions += row.submissions;
+            summary.cost_wei += row.cost_wei;
+            summary.rewards_wei += row.rewards_wei;
+        }
+
+        for summary in cities.values_mut() {
+            summary.net_eth = wei_to_eth(summary.rewards_wei) - wei_to_eth(summary.cost_wei);
+            summary.cost_per_submission_eth = if summary.submissions > 0 {
+                wei_to_eth(summary.cost_wei) / summary.submissions as f64
+            } else {
+                0.0
+            };
+            summary.profitable = summary.rewards_wei > summary.cost_wei;
+        }
+
+        let total_cost_wei = daily.values().fold(U256::zero(), |acc, row| acc + row.cost_wei);
+        let total_rewards_wei = daily.values().fold(U256::zero(), |acc, row| acc + row.rewards_wei);
+
+        ProfitLossReport {
+            daily: daily.into_values().collect(),
+            by_operation: by_operation.into_values().collect(),
+            by_city: cities.into_values().collect(),
+            total_cost_wei,
+            total_rewards_wei,
+            net_eth: wei_to_eth(total_rewards_wei) - wei_to_eth(total_cost_wei),
+        }
+    }
+
+    // In (date, city) order, so the share that takes the rounding remainder is deterministic.
+    fn submission_share(&self, daily: &BTreeMap<(NaiveDate, String), DailyCityCost>) -> Vec<((NaiveDate, String), u64, u64)> {
+        let total: u64 = daily.values().map(|row| row.submissions).sum();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        daily
+            .iter()
+            .filter(|(_, row)| row.submissions > 0)
+            .map(|(key, row)| (key.clone(), row.submissions, total))
+            .collect()
+    }
+}
+
+fn empty_day(date: NaiveDate, city: String) -> DailyCityCost {
+    DailyCityCost {
+        date,
+        city,
+        submissions: 0,
+        operations: 0,
+        failed_operations: 0,
+        gas_used: U256::zero(),
+        cost_wei: U256::zero(),
+        rewards_wei: U256::zero(),
+        net_eth: 0.0,
+    }
+}
+
+fn day_of(timestamp: i64) -> NaiveDate {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|dt| dt.date_naive())
+        .unwrap_or_default()
+}
+
+pub fn wei_to_eth(wei: U256) -> f64 {
+    wei.to_string().parse::<f64>().unwrap_or(0.0) / 1e18
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unattributed_rewards_split_without_losing_wei() {
+        let mut ledger = CostLedger::new();
+        for (index, city) in ["A", "B", "C"].iter().enumerate() {
+            ledger.record_cost(CostEntry::new(
+                OperationType::SubmitData,
+                Some(city.to_string()),
+                1_700_000_000 + index as i64,
+                H256::zero(),
+                U256::from(21_000),
+                U256::from(1),
+                true,
+            ));
+        }
+        ledger.record_reward(None, 1_700_000_100, U256::from(100), None);
+
+        let report = ledger.build_report(None, None);
+        let shares: Vec<U256> = report.daily.iter().map(|row| row.rewards_wei).collect();
+        assert_eq!(shares, vec![U256::from(33), U256::from(33), U256::from(34)]);
+        assert_eq!(report.total_rewards_wei, U256::from(100));
+        assert_eq!(report.operation_csv().lines().nth(1), Some("SubmitData,3,63000,63000,0.000000000000021"));
+    }
+}
@@ -0,0 +1,703 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::DateTime;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+use crate::ingestion::IngestReport;
+use crate::quality_control::QcFlags;
+use crate::units::{self, Unit, UnitSystem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportMode {
+    // The first bad row aborts the import and nothing is ingested; parsed rows are staged on disk meanwhile.
+    Strict,
+    // Bad rows are skipped and listed in the report.
+    Lenient,
+}
+
+// Source column names per field; the defaults match what `export_data` writes.
+// Names match with or without a `[unit]` suffix, so `temperature` also finds `temperature[degF]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub timestamp: String,
+    pub location: Option<String>,
+    pub variables: BTreeMap<WeatherVariable, String>,
+    pub source: Option<String>,
+    pub qc_flags: Option<String>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            timestamp: "timestamp".to_string(),
+            location: Some("location".to_string()),
+            variables: WeatherVariable::ALL.iter().map(|v| (*v, v.name().to_string())).collect(),
+            source: Some("source".to_string()),
+            qc_flags: Some("qc_flags".to_string()),
+        }
+    }
+}
+
+impl ColumnMapping {
+    pub fn with_timestamp(mut self, column: &str) -> Self {
+        self.timestamp = column.to_string();
+        self
+    }
+
+    pub fn with_location(mut self, column: Option<&str>) -> Self {
+        self.location = column.map(str::to_string);
+        self
+    }
+
+    pub fn with_column(mut self, variable: WeatherVariable, column: &str) -> Self {
+        self.variables.insert(variable, column.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConfig {
+    pub mode: ImportMode,
+    pub mapping: ColumnMapping,
+    pub delimiter: char,
+    pub default_location: Option<String>,
+    pub default_source: String,
+    pub batch_size: usize,
+    // Units of unannotated columns; a `[unit]` suffix in the header takes precedence.
+    #[serde(default)]
+    pub units: UnitSystem,
+}
+
+impl ImportConfig {
+    pub fn strict() -> Self {
+        Self::new(ImportMode::Strict)
+    }
+
+    pub fn lenient() -> Self {
+        Self::new(ImportMode::Lenient)
+    }
+
+    fn new(mode: ImportMode) -> Self {
+        Self {
+            mode,
+            mapping: ColumnMapping::default(),
+            delimiter: ',',
+            default_location: None,
+            default_source: "import".to_string(),
+            batch_size: 10_000,
+            units: UnitSystem::metric(),
+        }
+    }
+
+    pub fn with_mapping(mut self, mapping: ColumnMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    pub fn with_units(mut self, units: UnitSystem) -> Self {
+        self.units = units;
+        self
+    }
+}
+
+// CSV rows are records counted from the header as row 1; JSON rows count objects from 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowError {
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for RowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "Row {}, column {}: {}", self.row, column, self.message),
+            None => write!(f, "Row {}: {}", self.row, self.message),
+        }
+    }
+}
+
+impl std::error::Error for RowError {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub rows_read: usize,
+    pub rows_rejected: usize,
+    pub ingest: IngestReport,
+    pub errors: Vec<RowError>,
+}
+
+// Parsed rows of a strict import, one JSON record per line in the temp directory; removed on drop.
+pub struct StagedRows {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl StagedRows {
+    pub fn create() -> io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!("weather-import-{}-{}.jsonl", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(Self { path, writer })
+    }
+
+    pub fn push(&mut self, point: &WeatherDataPoint) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, point)?;
+        self.writer.write_all(b"\n")
+    }
+
+    // Rows come back in the order they were staged.
+    pub fn read(&mut self) -> io::Result<impl Iterator<Item = io::Result<WeatherDataPoint>> + use<>> {
+        self.writer.flush()?;
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(reader.lines().map(|line| Ok(serde_json::from_str(&line?)?)))
+    }
+}
+
+impl Drop for StagedRows {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Quotes a field only when it contains the delimiter, a quote or a line break (RFC 4180).
+pub fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Reads records one at a time, so quoted fields may span lines without loading the whole file.
+pub struct CsvRecords<R: BufRead> {
+    reader: R,
+    config: ImportConfig,
+    columns: ResolvedColumns,
+    row: usize,
+    done: bool,
+}
+
+struct ResolvedColumns {
+    timestamp: usize,
+    location: Option<usize>,
+    variables: Vec<(WeatherVariable, usize, Unit)>,
+    source: Option<usize>,
+    qc_flags: Option<usize>,
+}
+
+impl<R: BufRead> CsvRecords<R> {
+    pub fn new(mut reader: R, config: &ImportConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let header = read_csv_record(&mut reader, config.delimiter)?.ok_or("CSV input is empty")?;
+        let index: HashMap<&str, usize> = header
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (units::parse_annotated_name(name).0, i))
+            .collect();
+        let find = |column: &str| {
+            index
+                .get(units::parse_annotated_name(column).0)
+                .copied()
+                .ok_or_else(|| format!("CSV header has no column {}", column))
+        };
+
+        let mapping = &config.mapping;
+        let location = match (&mapping.location, &config.default_location) {
+            (Some(column), None) => Some(find(column)?),
+            (Some(column), Some(_)) => index.get(column.as_str()).copied(),
+            (None, Some(_)) => None,
+            (None, None) => return Err("No location column is mapped and no default location is set".into()),
+        };
+        let columns = ResolvedColumns {
+            timestamp: find(&mapping.timestamp)?,
+            location,
+            variables: mapping
+                .variables
+                .iter()
+                .map(|(variable, column)| {
+                    let i = find(column)?;
+                    let unit = column_unit(*variable, units::parse_annotated_name(&header[i]).1, &config.units)?;
+                    Ok((*variable, i, unit))
+                })
+                .collect::<Result<_, String>>()?,
+            // Older exports have no source or QC columns; those fall back to the defaults.
+            source: mapping.source.as_ref().and_then(|c| index.get(c.as_str()).copied()),
+            qc_flags: mapping.qc_flags.as_ref().and_then(|c| index.get(c.as_str()).copied()),
+        };
+
+        Ok(Self { reader, config: config.clone(), columns, row: 1, done: false })
+    }
+
+    fn convert(&self, fields: &[String]) -> Result<WeatherDataPoint, RowError> {
+        let row = self.row;
+        let cell = |index: usize, column: &str| {
+            fields.get(index).map(String::as_str).ok_or_else(|| RowError {
+                row,
+                column: Some(column.to_string()),
+                message: "missing field".to_string(),
+            })
+        };
+        let mapping = &self.config.mapping;
+
+        let timestamp = parse_timestamp(cell(self.columns.timestamp, &mapping.timestamp)?)
+            .map_err(|message| RowError { row, column: Some(mapping.timestamp.clone()), message })?;
+        let location = match (self.columns.location, mapping.location.as_deref()) {
+            (Some(index), Some(column)) if !cell(index, column)?.trim().is_empty() => cell(index, column)?.trim().to_string(),
+            _ => self.config.default_location.clone().ok_or_else(|| RowError {
+                row,
+                column: mapping.location.clone(),
+                message: "empty location".to_string(),
+            })?,
+        };
+
+        let mut values = HashMap::new();
+        for (variable, index, unit) in &self.columns.variables {
+            let column = &mapping.variables[variable];
+            let raw = cell(*index, column)?.trim();
+            let value = if raw.is_empty() {
+                f64::NAN
+            } else {
+                raw.parse::<f64>().map_err(|_| RowError {
+                    row,
+                    column: Some(column.clone()),
+                    message: format!("invalid number {:?}", raw),
+                })?
+            };
+            values.insert(*variable, unit.to_canonical(value));
+        }
+
+        let source = self.columns.source
+            .and_then(|index| fields.get(index))
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .unwrap_or_else(|| self.config.default_source.clone());
+        let qc_flags = match (self.columns.qc_flags, mapping.qc_flags.as_deref()) {
+            (Some(index), Some(column)) if !cell(index, column)?.trim().is_empty() => {
+                let raw = cell(index, column)?.trim();
+                QcFlags(raw.parse().map_err(|_| RowError {
+                    row,
+                    column: Some(column.to_string()),
+                    message: format!("invalid QC flags {:?}", raw),
+                })?)
+            }
+            _ => QcFlags::default(),
+        };
+
+        Ok(build_point(timestamp, location, &values, source, qc_flags))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvRecords<R> {
+    type Item = Result<WeatherDataPoint, RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            self.row += 1;
+            let fields = match read_csv_record(&mut self.reader, self.config.delimiter) {
+                Ok(Some(fields)) => fields,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    // A broken stream cannot be resynchronised.
+                    self.done = true;
+                    return Some(Err(RowError { row: self.row, column: None, message: e.to_string() }));
+                }
+            };
+
+            if fields.len() == 1 && fields[0].trim().is_empty() {
+                continue;
+            }
+            return Some(self.convert(&fields));
+        }
+    }
+}
+
+// One logical record; Ok(None) at end of input.
+fn read_csv_record<R: BufRead>(reader: &mut R, delimiter: char) -> std::io::Result<Option<Vec<String>>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut read_any = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            if !read_any {
+                return Ok(None);
+            }
+            if in_quotes {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "unterminated quoted field"));
+            }
+            fields.push(field);
+            return Ok(Some(fields));
+        }
+        read_any = true;
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.push(c),
+                }
+            } else {
+                match c {
+                    '"' if field.is_empty() => in_quotes = true,
+                    '\r' if chars.peek() == Some(&'\n') => {}
+                    '\n' => {
+                        fields.push(field);
+                        return Ok(Some(fields));
+                    }
+                    c if c == delimiter => fields.push(std::mem::take(&mut field)),
+                    _ => field.push(c),
+                }
+            }
+        }
+
+        if !in_quotes {
+            fields.push(field);
+            return Ok(Some(fields));
+        }
+    }
+}
+
+// Accepts both the JSON array `export_data` writes and newline-delimited objects.
+pub struct JsonRecords<R: BufRead> {
+    reader: R,
+    config: ImportConfig,
+    row: usize,
+    done: bool,
+}
+
+impl<R: BufRead> JsonRecords<R> {
+    pub fn new(reader: R, config: &ImportConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        if config.mapping.location.is_none() && config.default_location.is_none() {
+            return Err("No location column is mapped and no default location is set".into());
+        }
+        Ok(Self { reader, config: config.clone(), row: 0, done: false })
+    }
+
+    // Bytes of the next top-level object, skipping array brackets and separators between objects.
+    fn next_object(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let mut object = Vec::new();
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+
+        loop {
+            let buffer = self.reader.fill_buf()?;
+            if buffer.is_empty() {
+                if depth > 0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated JSON object"));
+                }
+                return Ok(None);
+            }
+
+            let mut consumed = 0;
+            for &byte in buffer {
+                consumed += 1;
+                if depth == 0 {
+                    match byte {
+                        b'{' => {
+                            depth = 1;
+                            object.push(byte);
+                        }
+                        b'[' | b']' | b',' | b' ' | b'\t' | b'\r' | b'\n' => {}
+                        other => {
+                            self.reader.consume(consumed);
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("unexpected character {:?} between records", other as char),
+                            ));
+                        }
+                    }
+                    continue;
+                }
+
+                object.push(byte);
+                if in_string {
+                    match byte {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            self.reader.consume(consumed);
+                            return Ok(Some(object));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.reader.consume(consumed);
+        }
+    }
+
+    fn convert(&self, bytes: &[u8]) -> Result<WeatherDataPoint, RowError> {
+        let row = self.row;
+        let error = |column: Option<&str>, message: String| RowError { row, column: column.map(str::to_string), message };
+        let object: serde_json::Map<String, Value> = serde_json::from_slice(bytes).map_err(|e| error(None, e.to_string()))?;
+        let mapping = &self.config.mapping;
+
+        let timestamp = match object.get(&mapping.timestamp) {
+            Some(Value::Number(n)) => n.as_i64().ok_or_else(|| error(Some(&mapping.timestamp), format!("invalid timestamp {}", n)))?,
+            Some(Value::String(s)) => parse_timestamp(s).map_err(|message| error(Some(&mapping.timestamp), message))?,
+            _ => return Err(error(Some(&mapping.timestamp), "missing timestamp".to_string())),
+        };
+        let location = mapping
+            .location
+            .as_ref()
+            .and_then(|column| object.get(column))
+            .and_then(Value::as_str)
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.trim().to_string())
+            .or_else(|| self.config.default_location.clone())
+            .ok_or_else(|| error(mapping.location.as_deref(), "missing location".to_string()))?;
+
+        let mut values = HashMap::new();
+        for (variable, column) in &mapping.variables {
+            let name = units::parse_annotated_name(column).0;
+            let (key, field) = match object.iter().find(|(key, _)| units::parse_annotated_name(key).0 == name) {
+                Some(entry) => entry,
+                None => return Err(error(Some(column), "missing field".to_string())),
+            };
+            let unit = column_unit(*variable, units::parse_annotated_name(key).1, &self.config.units)
+                .map_err(|message| error(Some(key), message))?;
+
+            // serde_json writes non-finite numbers as null.
+            let value = match field {
+                Value::Null => f64::NAN,
+                Value::Number(n) => n.as_f64().unwrap_or(f64::NAN),
+                Value::String(s) => s.trim().parse().map_err(|_| error(Some(key), format!("invalid number {:?}", s)))?,
+                other => return Err(error(Some(key), format!("invalid number {}", other))),
+            };
+            values.insert(*variable, unit.to_canonical(value));
+        }
+
+        let source = mapping
+            .source
+            .as_ref()
+            .and_then(|column| object.get(column))
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| self.config.default_source.clone());
+        let qc_flags = match mapping.qc_flags.as_ref().and_then(|column| object.get(column)) {
+            Some(Value::Number(n)) => QcFlags(
+                n.as_u64()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| error(mapping.qc_flags.as_deref(), format!("invalid QC flags {}", n)))?,
+            ),
+            _ => QcFlags::default(),
+        };
+
+        Ok(build_point(timestamp, location, &values, source, qc_flags))
+    }
+}
+
+impl<R: BufRead> Iterator for JsonRecords<R> {
+    type Item = Result<WeatherDataPoint, RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        self.row += 1;
+        match self.next_object() {
+            Ok(Some(bytes)) => Some(self.convert(&bytes)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(RowError { row: self.row, column: None, message: e.to_string() }))
+            }
+        }
+    }
+}
+
+// The unit named in a `name[unit]` header, else the configured one; it must measure the variable.
+fn column_unit(variable: WeatherVariable, annotation: Option<&str>, units: &UnitSystem) -> Result<Unit, String> {
+    let expected = units.unit(variable);
+    let unit = match annotation {
+        Some(symbol) => Unit::from_symbol(symbol).ok_or_else(|| format!("unknown unit {:?}", symbol))?,
+        None => return Ok(expected),
+    };
+    if !unit.same_dimension(&expected) {
+        return Err(format!("{} is not a unit of {}", unit.symbol(), variable.name()));
+    }
+    Ok(unit)
+}
+
+// Unix seconds or RFC 3339.
+fn parse_timestamp(raw: &str) -> Result<i64, String> {
+    let raw = raw.trim();
+    raw.parse::<i64>()
+        .or_else(|_| DateTime::parse_from_rfc3339(raw).map(|t| t.timestamp()))
+        .map_err(|_| format!("invalid timestamp {:?}", raw))
+}
+
+// Unmapped variables are missing rather than zero, so QC range checks catch them.
+fn build_point(
+    timestamp: i64,
+    location: String,
+    values: &HashMap<WeatherVariable, f64>,
+    source: String,
+    qc_flags: QcFlags,
+) -> WeatherDataPoint {
+    let value = |variable: WeatherVariable| values.get(&variable).copied().unwrap_or(f64::NAN);
+    WeatherDataPoint {
+        timestamp,
+        location,
+        temperature: value(WeatherVariable::Temperature),
+        humidity: value(WeatherVariable::Humidity),
+        pressure: value(WeatherVariable::Pressure),
+        wind_speed: value(WeatherVariable::WindSpeed),
+        wind_direction: value(WeatherVariable::WindDirection),
+        precipitation: value(WeatherVariable::Precipitation),
+        qc_flags,
+        source,
+        // Stamped per import batch by DataProcessor::add_batch_data.
+        provenance: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::{DataProcessor, ExportFormat};
+    use crate::data_query::TimeWindow;
+
+    fn point(location: &str, timestamp: i64, temperature: f64) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: location.to_string(),
+            temperature,
+            humidity: 55.0,
+            pressure: 1012.0,
+            wind_speed: 4.0,
+            wind_direction: 270.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::CHECKED,
+            source: "metar, \"edited\"".to_string(),
+            provenance: None,
+        }
+    }
+
+    const CSV_HEADER: &str = "timestamp,location,temperature,humidity,pressure,wind_speed,wind_direction,precipitation\n";
+
+    #[test]
+    fn fields_are_quoted_for_the_delimiter_in_use() {
+        assert_eq!(csv_field("Portland, OR", ','), "\"Portland, OR\"");
+        assert_eq!(csv_field("Portland, OR", ';'), "Portland, OR");
+        assert_eq!(csv_field("a;b", ';'), "\"a;b\"");
+        assert_eq!(csv_field("say \"hi\"", ';'), "\"say \"\"hi\"\"\"");
+    }
+
+    #[tokio::test]
+    async fn csv_export_round_trips_locations_with_delimiters_and_quotes() {
+        let exporter = DataProcessor::new();
+        exporter.add_batch_data(vec![point("Portland, OR", 1_700_000_000, 12.5), point("Portland, OR", 1_700_003_600, 13.0)]).await.unwrap();
+        let csv = exporter.export_data("Portland, OR", ExportFormat::Csv).await.unwrap();
+
+        let importer = DataProcessor::new();
+        let report = importer.import_csv(csv.as_bytes(), &ImportConfig::strict()).await.unwrap();
+        assert_eq!((report.rows_read, report.rows_rejected), (2, 0));
+
+        let points = importer.query_points("Portland, OR", TimeWindow::new(0, i64::MAX)).await;
+        assert_eq!(points.iter().map(|p| p.temperature).collect::<Vec<_>>(), vec![12.5, 13.0]);
+        assert_eq!(points[0].source, "metar, \"edited\"");
+    }
+
+    #[test]
+    fn quoted_fields_may_span_lines() {
+        let csv = format!(
+            "{}1700000000,\"Portland,\nOR\",10,50,1010,3,180,0\n1700003600,KJFK,11,50,1010,3,180,0\n",
+            CSV_HEADER
+        );
+        let rows: Vec<WeatherDataPoint> = CsvRecords::new(csv.as_bytes(), &ImportConfig::strict())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].location, "Portland,\nOR");
+        assert_eq!(rows[1].location, "KJFK");
+    }
+
+    #[tokio::test]
+    async fn strict_imports_abort_and_lenient_imports_report_bad_rows() {
+        let csv = format!(
+            "{}1700000000,KJFK,10,50,1010,3,180,0\n1700003600,KJFK,warm,50,1010,3,180,0\nyesterday,KJFK,12,50,1010,3,180,0\n1700010800,KJFK,13,50,1010,3,180,0\n",
+            CSV_HEADER
+        );
+
+        let strict = DataProcessor::new();
+        let error = strict.import_csv(csv.as_bytes(), &ImportConfig::strict()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Row 3, column temperature: invalid number \"warm\"");
+        assert!(strict.get_location_summary().await.is_empty());
+
+        let lenient = DataProcessor::new();
+        let report = lenient.import_csv(csv.as_bytes(), &ImportConfig::lenient()).await.unwrap();
+        assert_eq!((report.rows_read, report.rows_rejected), (4, 2));
+        let columns: Vec<(usize, Option<&str>)> = report.errors.iter().map(|e| (e.row, e.column.as_deref())).collect();
+        assert_eq!(columns, vec![(3, Some("temperature")), (4, Some("timestamp"))]);
+        assert_eq!(lenient.get_location_summary().await.get("KJFK"), Some(&2));
+    }
+
+    #[test]
+    fn json_arrays_and_newline_delimited_objects_read_alike() {
+        let objects = [
+            r#"{"timestamp":1700000000,"location":"KJFK","temperature":10.0,"humidity":50,"pressure":null,"wind_speed":3,"wind_direction":180,"precipitation":0,"source":"a {b}"}"#,
+            r#"{"timestamp":"2023-11-14T23:13:20Z","location":"KJFK","temperature":11.5,"humidity":50,"pressure":1010,"wind_speed":3,"wind_direction":180,"precipitation":0}"#,
+        ];
+        let array = format!("[\n  {},\n  {}\n]", objects[0], objects[1]);
+        let ndjson = format!("{}\n{}\n", objects[0], objects[1]);
+
+        let read = |input: &str| -> Vec<WeatherDataPoint> {
+            JsonRecords::new(input.as_bytes(), &ImportConfig::strict()).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        let summary = |points: Vec<WeatherDataPoint>| -> Vec<(i64, f64, bool, String)> {
+            points.into_iter().map(|p| (p.timestamp, p.temperature, p.pressure.is_nan(), p.source)).collect()
+        };
+
+        let expected = vec![
+            (1_700_000_000, 10.0, true, "a {b}".to_string()),
+            (1_700_003_600, 11.5, false, "import".to_string()),
+        ];
+        assert_eq!(summary(read(&array)), expected);
+        assert_eq!(summary(read(&ndjson)), expected);
+
+        let errors: Vec<RowError> = JsonRecords::new("{\"timestamp\":1} 42".as_bytes(), &ImportConfig::lenient())
+            .unwrap()
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errors.iter().map(|e| e.row).collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
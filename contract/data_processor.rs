@@ -1,25 +1,66 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
-use rayon::prelude::*;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{NaiveDate, Utc};
 
+use crate::anomaly_detection::{self, AnomalyConfig, AnomalyEvent};
+use crate::blockchain_interface::{compute_data_hash, OracleData};
+use crate::columnar_export::{self, BatchWriter, ColumnarExportOptions, ExportPartition, ExportedFile, Partitioning};
+use crate::climatology::{self, ClimateDeparture, ClimateNormals, ClimatologyConfig};
+use crate::data_import::{self, CsvRecords, ImportConfig, ImportMode, ImportReport, JsonRecords, RowError, StagedRows};
+use crate::data_query::{DataQuery, QueryRow, TimeWindow, WeatherVariable};
+use crate::data_storage::WeatherStorage;
+use crate::degree_days::{self, DegreeDayConfig, DegreeDaySeries};
+use crate::derived_variables::{DerivedValues, DerivedVariable};
+use crate::extreme_values::{self, BlockMaximum, ExtremeConfig, ExtremeFit};
+use crate::gap_filling::{self, GapFillConfig};
+use crate::ingestion::{self, ConflictPolicy, IngestOutcome, IngestReport};
+use crate::location_series::{LocationSeries, Observation, SeriesSummary};
+use crate::provenance::{self, DisputeEvidence, Provenance, ReporterSignature, SourceWeights, UpstreamEvidence, UpstreamRecord};
+use crate::quality_control::{QcConfig, QcContext, QcFlags, QcPipeline};
+use crate::rolling_windows::{RollingConfig, RollingEvent, RollingThreshold, RollingValue, RollingWindows};
+use crate::snapshot::{self, Snapshot, SnapshotInfo};
+use crate::source_parsers::{self, RawFormat};
+use crate::station_registry::{self, EstimateConfig, SpatialEstimate, Station, StationDistance, StationRegistry};
+use crate::resampling::{self, ResampleConfig, ResampledBucket};
+use crate::units::{self, to_oracle_fixed, UnitSystem};
+
+// Measurements are always canonical: °C, %, hPa, m/s, degrees and mm (see units::UnitSystem::metric).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeatherDataPoint {
     pub timestamp: i64,
     pub location: String,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub temperature: f64,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub humidity: f64,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub pressure: f64,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub wind_speed: f64,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub wind_direction: f64,
+    #[serde(deserialize_with = "missing_as_nan")]
     pub precipitation: f64,
+    #[serde(default)]
+    pub qc_flags: QcFlags,
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+}
+
+// serde_json writes a missing (NaN) value as null; read it back as missing.
+fn missing_as_nan<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::NAN))
 }
 
 #[derive(Debug)]
 pub struct ProcessedData {
     pub location: String,
+    pub window: Option<TimeWindow>,
     pub average_temperature: f64,
     pub max_temperature: f64,
     pub min_temperature: f64,
@@ -28,19 +69,46 @@ pub struct ProcessedData {
     pub pressure_average: f64,
     pub wind_average: f64,
     pub precipitation_total: f64,
+    pub dew_point_average: f64,
+    pub max_heat_index: f64,
+    pub min_wind_chill: f64,
+    pub max_apparent_temperature: f64,
+    pub min_apparent_temperature: f64,
+    pub sea_level_pressure_average: Option<f64>,
+    pub temperature_departure: Option<ClimateDeparture>,
     pub data_points: usize,
 }
 
 pub struct DataProcessor {
-    data_cache: Arc<RwLock<HashMap<String, Vec<WeatherDataPoint>>>>,
+    // Series are copy-on-write so a snapshot can hold them while ingestion carries on.
+    data_cache: Arc<RwLock<HashMap<String, Arc<LocationSeries>>>>,
     processing_stats: Arc<RwLock<ProcessingStats>>,
+    storage: Option<Arc<dyn WeatherStorage>>,
+    // Every ingest path checks points; settlement only accepts checked ones.
+    qc_pipeline: Arc<QcPipeline>,
+    stations: Arc<RwLock<StationRegistry>>,
+    conflict_policy: ConflictPolicy,
+    rolling: Option<Arc<RwLock<RollingWindows>>>,
+    normals: Arc<RwLock<HashMap<(String, WeatherVariable), ClimateNormals>>>,
+    // Raw payloads from ingest_raw by hash, kept while stored points still reference them.
+    upstream: Arc<RwLock<HashMap<[u8; 32], String>>>,
+    // Serialises writers so storage I/O can run under a read lock on data_cache; taken before any other lock.
+    ingest: Arc<tokio::sync::Mutex<()>>,
+    climate_departures: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessingStats {
+    pub total_processed: u64,
+    pub average_processing_time: Duration,
+    // Wall-clock rather than Instant so the stats survive a snapshot.
+    pub last_update: Option<SystemTime>,
 }
 
-#[derive(Debug, Default)]
-struct ProcessingStats {
-    total_processed: u64,
-    average_processing_time: Duration,
-    last_update: Instant,
+impl Default for DataProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DataProcessor {
@@ -48,211 +116,1105 @@ impl DataProcessor {
         Self {
             data_cache: Arc::new(RwLock::new(HashMap::new())),
             processing_stats: Arc::new(RwLock::new(ProcessingStats::default())),
+            storage: None,
+            qc_pipeline: Arc::new(QcPipeline::from_config(QcConfig::default())),
+            stations: Arc::new(RwLock::new(StationRegistry::new())),
+            conflict_policy: ConflictPolicy::default(),
+            rolling: None,
+            normals: Arc::new(RwLock::new(HashMap::new())),
+            upstream: Arc::new(RwLock::new(HashMap::new())),
+            ingest: Arc::new(tokio::sync::Mutex::new(())),
+            climate_departures: false,
+        }
+    }
+
+    pub async fn with_storage(storage: Arc<dyn WeatherStorage>) -> std::io::Result<Self> {
+        Self::new().open_storage(storage).await
+    }
+
+    // The log holds every applied point in order, so replaying it through the conflict policy rebuilds
+    // the cache. Set a PreferHigherQuality policy before opening: other policies replay as KeepLast,
+    // which also covers logs written before keyed ingestion.
+    pub async fn open_storage(mut self, storage: Arc<dyn WeatherStorage>) -> std::io::Result<Self> {
+        let replay_policy = match &self.conflict_policy {
+            ConflictPolicy::PreferHigherQuality { .. } => self.conflict_policy.clone(),
+            _ => ConflictPolicy::KeepLast,
+        };
+
+        let mut cache = HashMap::new();
+        for location in storage.locations()? {
+            let mut points = storage.load(&location)?;
+            points.sort_by_key(|p| p.timestamp);
+
+            let mut series = LocationSeries::new(location.clone(), None);
+            for point in points {
+                ingestion::upsert(&mut series, point, &replay_policy);
+            }
+            cache.insert(location, Arc::new(series));
+        }
+
+        self.data_cache = Arc::new(RwLock::new(cache));
+        self.storage = Some(storage);
+        Ok(self)
+    }
+
+    pub fn with_qc_pipeline(mut self, pipeline: QcPipeline) -> Self {
+        self.qc_pipeline = Arc::new(pipeline);
+        self
+    }
+
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    // Adds the temperature departure from stored normals to processed data, at the cost of evaluating the
+    // normal at every sample in the window.
+    pub fn with_climate_departures(mut self) -> Self {
+        self.climate_departures = true;
+        self
+    }
+
+    pub fn with_rolling_windows(mut self, config: RollingConfig) -> Self {
+        self.rolling = Some(Arc::new(RwLock::new(RollingWindows::new(config))));
+        self
+    }
+
+    pub async fn subscribe(&self) -> Option<tokio::sync::broadcast::Receiver<RollingEvent>> {
+        Some(self.rolling.as_ref()?.read().await.subscribe())
+    }
+
+    pub async fn add_rolling_threshold(&self, threshold: RollingThreshold) {
+        if let Some(rolling) = &self.rolling {
+            rolling.write().await.add_threshold(threshold);
+        }
+    }
+
+    pub async fn rolling_values(&self, location: &str) -> Vec<RollingValue> {
+        let location = &self.resolve_location(location).await;
+        match &self.rolling {
+            Some(rolling) => rolling.read().await.values(location),
+            None => Vec::new(),
         }
     }
 
-    pub async fn add_data_point(&self, point: WeatherDataPoint) {
+    // Series stored under one of the station's names before it was registered move to its ID, in memory
+    // and in storage. If the storage write fails the old series stay on disk and move again on the next
+    // registration after a restart.
+    pub async fn register_station(&self, station: Station) -> Result<(), Box<dyn std::error::Error>> {
+        let _ingest = self.ingest.lock().await;
+        let id = station.id.clone();
+        let elevation = station.elevation_m;
+        let mut stations = self.stations.write().await;
+        stations.register(station)?;
+
         let mut cache = self.data_cache.write().await;
-        cache.entry(point.location.clone())
-            .or_insert_with(Vec::new)
-            .push(point);
+        let aliases: Vec<String> = cache
+            .keys()
+            .filter(|name| **name != id && stations.resolve(name).is_some_and(|station| station.id == id))
+            .cloned()
+            .collect();
+        drop(stations);
+
+        let mut moved = Vec::new();
+        for alias in &aliases {
+            let old = cache.remove(alias).unwrap_or_else(|| Arc::new(LocationSeries::new(alias.clone(), None)));
+            let series = Arc::make_mut(cache.entry(id.clone()).or_insert_with(|| Arc::new(LocationSeries::new(id.clone(), elevation))));
+            for mut point in old.points(0..old.len()) {
+                point.location = id.clone();
+                if !matches!(ingestion::upsert(series, point.clone(), &self.conflict_policy), IngestOutcome::Ignored) {
+                    moved.push(point);
+                }
+            }
+        }
+        if let Some(series) = cache.get_mut(&id).filter(|series| series.elevation() != elevation) {
+            Arc::make_mut(series).set_elevation(elevation);
+        }
+
+        let _cache = cache.downgrade();
+        if let Some(storage) = &self.storage {
+            if !moved.is_empty() {
+                storage.append(&moved)?;
+            }
+            for alias in &aliases {
+                storage.rewrite(alias, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    // Station IDs and aliases name the series stored under the station's ID; other names are used as given.
+    async fn resolve_location(&self, name: &str) -> String {
+        self.stations.read().await.resolve(name).map_or_else(|| name.to_string(), |station| station.id.clone())
+    }
+
+    pub async fn station(&self, name: &str) -> Option<Station> {
+        self.stations.read().await.resolve(name).cloned()
+    }
+
+    pub async fn nearest_stations(&self, latitude: f64, longitude: f64, count: usize) -> Vec<StationDistance> {
+        self.stations.read().await.nearest(latitude, longitude, count)
+    }
+
+    pub async fn stations_within(&self, latitude: f64, longitude: f64, radius_km: f64) -> Vec<StationDistance> {
+        self.stations.read().await.within_radius(latitude, longitude, radius_km)
+    }
+
+    pub async fn station_neighbors(&self, radius_km: f64) -> HashMap<String, Vec<String>> {
+        self.stations.read().await.neighbor_map(radius_km)
+    }
+
+    // Uses each nearby station's observation closest to `timestamp`, ignoring QC-failed points.
+    pub async fn estimate_at(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        variable: WeatherVariable,
+        timestamp: i64,
+        config: &EstimateConfig,
+    ) -> Option<SpatialEstimate> {
+        let tolerance_secs = config.tolerance_secs;
+        let nearby = self.stations_within(latitude, longitude, config.radius_km).await;
+        let cache = self.data_cache.read().await;
+
+        let samples: Vec<(&Station, f64)> = nearby
+            .iter()
+            .filter_map(|candidate| {
+                let series = cache.get(&candidate.station.id)?;
+                let index = series
+                    .range_between(timestamp - tolerance_secs, timestamp + tolerance_secs + 1)
+                    .filter(|i| !series.qc_flags()[*i].failed())
+                    .min_by_key(|i| (series.timestamps()[*i] - timestamp).abs())?;
+                Some((&candidate.station, series.column(variable)[index]))
+            })
+            .collect();
+
+        station_registry::interpolate(&samples, latitude, longitude, config.method)
+    }
+
+    pub async fn add_data_point(&self, point: WeatherDataPoint) -> std::io::Result<IngestReport> {
+        self.add_batch_data(vec![point]).await
+    }
+
+    // Pressure reduction uses elevations from the station registry.
+    pub async fn ingest_raw(&self, format: RawFormat, payload: &str) -> Result<IngestReport, Box<dyn std::error::Error>> {
+        self.ingest_provenance(format, payload, Provenance::new(payload.as_bytes())).await
+    }
+
+    // For payloads relayed by another oracle that signed them; a signature that does not match the payload is rejected.
+    pub async fn ingest_raw_signed(
+        &self,
+        format: RawFormat,
+        payload: &str,
+        signature: ReporterSignature,
+    ) -> Result<IngestReport, Box<dyn std::error::Error>> {
+        let provenance = Provenance::new(payload.as_bytes());
+        if !signature.verify(&provenance.payload_hash) {
+            return Err(format!("Signature does not match the payload for reporter {}", signature.reporter).into());
+        }
+        self.ingest_provenance(format, payload, provenance.with_signature(signature)).await
+    }
+
+    async fn ingest_provenance(&self, format: RawFormat, payload: &str, provenance: Provenance) -> Result<IngestReport, Box<dyn std::error::Error>> {
+        let mut points = {
+            let stations = self.stations.read().await;
+            source_parsers::parse(format, payload, &stations)?
+        };
+        for point in &mut points {
+            point.provenance = Some(provenance.clone());
+        }
+
+        let upstream = UpstreamRecord { payload_hash: provenance.payload_hash, payload: payload.to_string() };
+        Ok(self.add_checked_batch(points, Some(upstream)).await?)
     }
 
-    pub async fn add_batch_data(&self, points: Vec<WeatherDataPoint>) {
+    // For producers that do not report canonical units.
+    pub async fn add_batch_data_in(&self, points: Vec<WeatherDataPoint>, units: &UnitSystem) -> std::io::Result<IngestReport> {
+        self.add_batch_data(points.iter().map(|point| units.to_canonical(point)).collect()).await
+    }
+
+    pub async fn add_batch_data(&self, points: Vec<WeatherDataPoint>) -> std::io::Result<IngestReport> {
+        self.add_checked_batch(points, None).await
+    }
+
+    // `upstream` is retained only once some point referring to it has been stored.
+    async fn add_checked_batch(
+        &self,
+        points: Vec<WeatherDataPoint>,
+        upstream: Option<UpstreamRecord>,
+    ) -> std::io::Result<IngestReport> {
+        let _ingest = self.ingest.lock().await;
+        let stations = self.stations.read().await;
         let mut cache = self.data_cache.write().await;
-        for point in points {
-            cache.entry(point.location.clone())
-                .or_insert_with(Vec::new)
-                .push(point);
+        let mut report = IngestReport::default();
+        let mut applied = Vec::new();
+
+        // A batch submitted without provenance is its own upstream record.
+        let batch_provenance = points
+            .iter()
+            .any(|p| p.provenance.is_none())
+            .then(|| Provenance::from_hash(provenance::points_hash(&points)));
+
+        // Points are checked in order so step and persistence checks see earlier points of the batch.
+        for mut point in points {
+            if point.provenance.is_none() {
+                point.provenance = batch_provenance.clone();
+            }
+            if let Some(station) = stations.resolve(&point.location) {
+                point.location = station.id.clone();
+            }
+            apply_qc(&self.qc_pipeline, &cache, &mut point);
+            let series = Arc::make_mut(cache.entry(point.location.clone()).or_insert_with(|| {
+                Arc::new(LocationSeries::new(point.location.clone(), stations.get(&point.location).and_then(|s| s.elevation_m)))
+            }));
+            let outcome = ingestion::upsert(series, point.clone(), &self.conflict_policy);
+            report.record(&outcome);
+
+            if !matches!(outcome, IngestOutcome::Ignored) {
+                applied.push((point, outcome));
+            }
+        }
+
+        // Readers may see the batch while it is written; writers wait on the ingest lock.
+        let cache = cache.downgrade();
+        if let Some(storage) = &self.storage {
+            let points: Vec<WeatherDataPoint> = applied.iter().map(|(point, _)| point.clone()).collect();
+            if let Err(e) = storage.append(&points) {
+                drop(cache);
+                let mut cache = self.data_cache.write().await;
+                for (point, outcome) in applied.into_iter().rev() {
+                    if let Some(series) = cache.get_mut(&point.location) {
+                        ingestion::revert(Arc::make_mut(series), &point, outcome);
+                    }
+                }
+                return Err(e);
+            }
+        }
+        if let Some(record) = upstream.filter(|_| !applied.is_empty()) {
+            self.upstream.write().await.insert(record.payload_hash, record.payload);
         }
+
+        if let Some(rolling) = &self.rolling {
+            let mut by_location: HashMap<&str, Vec<(&WeatherDataPoint, bool)>> = HashMap::new();
+            for (point, outcome) in &applied {
+                by_location
+                    .entry(point.location.as_str())
+                    .or_default()
+                    .push((point, matches!(outcome, IngestOutcome::Updated { .. })));
+            }
+            let mut rolling = rolling.write().await;
+            for (location, points) in by_location {
+                if let Some(series) = cache.get(location) {
+                    rolling.observe(series, &points);
+                }
+            }
+        }
+        Ok(report)
     }
 
     pub async fn process_location_data(&self, location: &str) -> Option<ProcessedData> {
+        self.process_location_window(location, None).await
+    }
+
+    pub async fn process_window(&self, location: &str, window: TimeWindow) -> Option<ProcessedData> {
+        self.process_location_window(location, Some(window)).await
+    }
+
+    // Points sharing a timestamp are blended by source weight before summarising, so overlapping
+    // sources are not double counted; sources with zero weight are left out entirely.
+    pub async fn process_by_source(&self, location: &str, window: Option<TimeWindow>, weights: &SourceWeights) -> Option<ProcessedData> {
+        let location = &self.resolve_location(location).await;
         let start_time = Instant::now();
-        let cache = self.data_cache.read().await;
-        let data = cache.get(location)?;
+        let normals = self.normals.read().await;
+        let normals = normals
+            .get(&(location.to_string(), WeatherVariable::Temperature))
+            .filter(|_| self.climate_departures);
+        let (blended, elevation) = {
+            let cache = self.data_cache.read().await;
+            let series = cache.get(location)?;
+            let range = series.range(window);
+            let mut blended = Vec::new();
+            let mut start = range.start;
+            while start < range.end {
+                let timestamp = series.timestamps()[start];
+                let end = series.range_between(timestamp, timestamp + 1).end.min(range.end);
+                let group: Vec<(Observation, &str)> = (start..end).map(|i| (series.observation(i), series.source(i))).collect();
+                blended.extend(weights.blend(&group));
+                start = end;
+            }
+            (blended, series.elevation())
+        };
 
-        if data.is_empty() {
+        let temperature_departure = normals.and_then(|normals| {
+            let samples: Vec<(i64, f64)> = blended
+                .iter()
+                .filter(|o| o.qc_flags.settlement_eligible())
+                .map(|o| (o.timestamp, o.temperature))
+                .collect();
+            normals.window_departure(&samples)
+        });
+        let summary = SeriesSummary::from_observations(blended, elevation);
+        if summary.count == 0 {
             return None;
         }
 
-        let processed = self.process_data_parallel(data.clone());
+        let mut processed = self.build_processed_data(location, window, &summary);
+        processed.temperature_departure = temperature_departure;
         let processing_time = start_time.elapsed();
 
         let mut stats = self.processing_stats.write().await;
         stats.total_processed += 1;
         stats.average_processing_time = (stats.average_processing_time + processing_time) / 2;
-        stats.last_update = Instant::now();
+        stats.last_update = Some(SystemTime::now());
 
         Some(processed)
     }
 
-    fn process_data_parallel(&self, data: Vec<WeatherDataPoint>) -> ProcessedData {
-        let location = data[0].location.clone();
-        let data_points = data.len();
+    // Stored points at an on-chain reading's timestamp; the one whose oracle hash matches is what was
+    // submitted, shown with its provenance and, for raw ingestion, the upstream payload itself.
+    pub async fn dispute_evidence(&self, reading: &OracleData) -> DisputeEvidence {
+        let timestamp = reading.timestamp as i64;
+        let points = {
+            let location = self.resolve_location(&reading.city).await;
+            let cache = self.data_cache.read().await;
+            cache
+                .get(&location)
+                .map(|series| series.points(series.range_between(timestamp, timestamp + 1)))
+                .unwrap_or_default()
+        };
 
-        let temperatures: Vec<f64> = data.par_iter().map(|d| d.temperature).collect();
-        let humidities: Vec<f64> = data.par_iter().map(|d| d.humidity).collect();
-        let pressures: Vec<f64> = data.par_iter().map(|d| d.pressure).collect();
-        let wind_speeds: Vec<f64> = data.par_iter().map(|d| d.wind_speed).collect();
-        let precipitations: Vec<f64> = data.par_iter().map(|d| d.precipitation).collect();
+        let upstream = self.upstream.read().await;
+        let candidates = points
+            .into_iter()
+            .map(|point| {
+                let hash = compute_data_hash(
+                    &reading.city,
+                    to_oracle_fixed(point.temperature),
+                    to_oracle_fixed(point.humidity),
+                    reading.timestamp,
+                );
+                let record = point.provenance.as_ref().and_then(|provenance| {
+                    upstream.get(&provenance.payload_hash).map(|payload| UpstreamRecord {
+                        payload_hash: provenance.payload_hash,
+                        payload: payload.clone(),
+                    })
+                });
+                UpstreamEvidence { hash_matches: hash == reading.data_hash, upstream: record, point }
+            })
+            .collect();
 
-        let average_temperature = temperatures.par_iter().sum::<f64>() / data_points as f64;
-        let max_temperature = temperatures.par_iter().cloned().reduce(|| f64::NEG_INFINITY, f64::max);
-        let min_temperature = temperatures.par_iter().cloned().reduce(|| f64::INFINITY, f64::min);
+        DisputeEvidence { reading: reading.clone(), candidates }
+    }
 
-        let temperature_trend = self.calculate_trend(&temperatures);
+    async fn process_location_window(&self, location: &str, window: Option<TimeWindow>) -> Option<ProcessedData> {
+        let location = &self.resolve_location(location).await;
+        let start_time = Instant::now();
+        let (summary, temperature_departure) = {
+            let normals = self.normals.read().await;
+            let normals = normals
+                .get(&(location.to_string(), WeatherVariable::Temperature))
+                .filter(|_| self.climate_departures);
+            let cache = self.data_cache.read().await;
+            let series = cache.get(location)?;
+            let range = series.range(window);
+            let departure = normals.and_then(|normals| {
+                normals.window_departure(&eligible_samples(series, range.clone(), WeatherVariable::Temperature))
+            });
+            (series.summarize(range), departure)
+        };
 
-        let humidity_average = humidities.par_iter().sum::<f64>() / data_points as f64;
-        let pressure_average = pressures.par_iter().sum::<f64>() / data_points as f64;
-        let wind_average = wind_speeds.par_iter().sum::<f64>() / data_points as f64;
-        let precipitation_total = precipitations.par_iter().sum::<f64>();
+        if summary.count == 0 {
+            return None;
+        }
 
-        ProcessedData {
-            location,
-            average_temperature,
-            max_temperature,
-            min_temperature,
-            temperature_trend,
-            humidity_average,
-            pressure_average,
-            wind_average,
-            precipitation_total,
-            data_points,
+        let mut processed = self.build_processed_data(location, window, &summary);
+        processed.temperature_departure = temperature_departure;
+        let processing_time = start_time.elapsed();
+
+        let mut stats = self.processing_stats.write().await;
+        stats.total_processed += 1;
+        stats.average_processing_time = (stats.average_processing_time + processing_time) / 2;
+        stats.last_update = Some(SystemTime::now());
+
+        Some(processed)
+    }
+
+    async fn points_in_window(&self, location: &str, window: Option<TimeWindow>) -> Vec<WeatherDataPoint> {
+        let location = &self.resolve_location(location).await;
+        let cache = self.data_cache.read().await;
+        match cache.get(location) {
+            Some(series) => series.points(series.range(window)),
+            None => Vec::new(),
         }
     }
 
-    fn calculate_trend(&self, values: &[f64]) -> f64 {
-        if values.len() < 2 {
-            return 0.0;
+    pub async fn query(&self, query: &DataQuery) -> Vec<QueryRow> {
+        let variables = query.selected_variables();
+        let mut locations = Vec::with_capacity(query.locations.len());
+        for location in &query.locations {
+            let location = self.resolve_location(location).await;
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
         }
+        let cache = self.data_cache.read().await;
 
-        let n = values.len() as f64;
-        let x_sum: f64 = (0..values.len()).map(|i| i as f64).sum();
-        let y_sum: f64 = values.iter().sum();
-        let xy_sum: f64 = values.iter().enumerate().map(|(i, &y)| i as f64 * y).sum();
-        let x_squared_sum: f64 = (0..values.len()).map(|i| (i as f64).powi(2)).sum();
+        let mut rows: Vec<QueryRow> = locations
+            .iter()
+            .filter_map(|location| cache.get(location))
+            .flat_map(|series| series.range(query.window).map(move |i| (series, i)))
+            .map(|(series, i)| (series.point(i), series.elevation()))
+            .filter(|(point, _)| query.matches(point))
+            .map(|(point, elevation)| QueryRow::project(&point, &variables, &query.derived, elevation))
+            .collect();
+
+        rows.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.location.cmp(&b.location)));
+        if let Some(limit) = query.limit {
+            rows.truncate(limit);
+        }
 
-        let slope = (n * xy_sum - x_sum * y_sum) / (n * x_squared_sum - x_sum.powi(2));
-        slope
+        rows
+    }
+
+    pub async fn query_points(&self, location: &str, window: TimeWindow) -> Vec<WeatherDataPoint> {
+        self.points_in_window(location, Some(window)).await
+    }
+
+    pub async fn resample(
+        &self,
+        location: &str,
+        window: Option<TimeWindow>,
+        config: &ResampleConfig,
+    ) -> Result<Vec<ResampledBucket>, Box<dyn std::error::Error>> {
+        let points = self.points_in_window(location, window).await;
+        resampling::resample(&points, config)
+    }
+
+    fn build_processed_data(&self, location: &str, window: Option<TimeWindow>, summary: &SeriesSummary) -> ProcessedData {
+        ProcessedData {
+            location: location.to_string(),
+            window,
+            average_temperature: summary.mean(summary.temperature_sum, summary.temperature_count),
+            max_temperature: summary.temperature_max,
+            min_temperature: summary.temperature_min,
+            temperature_trend: summary.temperature_trend(),
+            humidity_average: summary.mean(summary.humidity_sum, summary.humidity_count),
+            pressure_average: summary.mean(summary.pressure_sum, summary.pressure_count),
+            wind_average: summary.mean(summary.wind_speed_sum, summary.wind_speed_count),
+            precipitation_total: summary.precipitation_sum,
+            dew_point_average: summary.mean(summary.dew_point_sum, summary.dew_point_count),
+            max_heat_index: summary.heat_index_max,
+            min_wind_chill: summary.wind_chill_min,
+            max_apparent_temperature: summary.apparent_temperature_max,
+            min_apparent_temperature: summary.apparent_temperature_min,
+            sea_level_pressure_average: summary.sea_level_pressure_sum.map(|sum| summary.mean(sum, summary.sea_level_pressure_count)),
+            temperature_departure: None,
+            data_points: summary.count,
+        }
     }
 
     pub async fn get_processing_stats(&self) -> ProcessingStats {
         self.processing_stats.read().await.clone()
     }
 
-    pub async fn clear_old_data(&self, max_age_seconds: i64) {
+    // The state is copied under the ingest lock, so the snapshot is one consistent point in time; series are
+    // shared rather than copied, and a writer copies a series only if it changes one the snapshot still holds.
+    // Encoding, compression and the write run on a blocking thread after ingestion has resumed.
+    pub async fn save_snapshot<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<SnapshotInfo> {
+        let (snapshot, mut series) = {
+            let _ingest = self.ingest.lock().await;
+            let stations = self.stations.read().await.clone();
+            let normals: Vec<ClimateNormals> = self.normals.read().await.values().cloned().collect();
+            let series: Vec<Arc<LocationSeries>> = self.data_cache.read().await.values().cloned().collect();
+            let stats = self.processing_stats.read().await.clone();
+            let upstream: Vec<UpstreamRecord> = self
+                .upstream
+                .read()
+                .await
+                .iter()
+                .map(|(hash, payload)| UpstreamRecord { payload_hash: *hash, payload: payload.clone() })
+                .collect();
+
+            let snapshot = Snapshot {
+                info: SnapshotInfo {
+                    version: snapshot::SNAPSHOT_VERSION,
+                    created_at: Utc::now().timestamp(),
+                    locations: series.len(),
+                    points: series.iter().map(|s| s.len()).sum(),
+                },
+                stats,
+                stations,
+                normals,
+                series: Vec::new(),
+                upstream,
+            };
+            (snapshot, series)
+        };
+
+        let path = path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut snapshot = snapshot;
+            // Sorted so identical state always produces an identical file.
+            series.sort_by(|a, b| a.location().cmp(b.location()));
+            snapshot.normals.sort_by(|a, b| (&a.location, a.variable).cmp(&(&b.location, b.variable)));
+            snapshot.upstream.sort_by_key(|record| record.payload_hash);
+            snapshot::write_snapshot_series(&path, &snapshot, series.iter().map(Arc::as_ref))?;
+            Ok(snapshot.info)
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+
+    // Replaces the cached locations, stats, stations, normals and upstream payloads. Attached storage is
+    // rewritten to match first; a failed rewrite leaves the cache untouched and storage partly restored,
+    // which restoring again completes.
+    pub async fn restore_snapshot<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<SnapshotInfo> {
+        let snapshot = snapshot::read_snapshot(path.as_ref())?;
+
+        let _ingest = self.ingest.lock().await;
+        if let Some(storage) = &self.storage {
+            for series in &snapshot.series {
+                storage.rewrite(series.location(), &series.points(0..series.len()))?;
+            }
+            for location in storage.locations()? {
+                if !snapshot.series.iter().any(|series| series.location() == location) {
+                    storage.rewrite(&location, &[])?;
+                }
+            }
+        }
+
+        let mut stations = self.stations.write().await;
+        let mut normals = self.normals.write().await;
+        let mut cache = self.data_cache.write().await;
+        let mut stats = self.processing_stats.write().await;
+        let mut upstream = self.upstream.write().await;
+        *stations = snapshot.stations;
+        *normals = snapshot.normals.into_iter().map(|n| ((n.location.clone(), n.variable), n)).collect();
+        *cache = snapshot.series.into_iter().map(|s| (s.location().to_string(), Arc::new(s))).collect();
+        *stats = snapshot.stats;
+        *upstream = snapshot.upstream.into_iter().map(|record| (record.payload_hash, record.payload)).collect();
+        Ok(snapshot.info)
+    }
+
+    pub async fn clear_old_data(&self, max_age_seconds: i64) -> std::io::Result<()> {
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
+        let _ingest = self.ingest.lock().await;
         let mut cache = self.data_cache.write().await;
-        for data in cache.values_mut() {
-            data.retain(|point| current_time - point.timestamp < max_age_seconds);
+        let trimmed: Vec<String> = cache
+            .iter_mut()
+            .filter_map(|(location, series)| {
+                let keep = |timestamp: i64| current_time - timestamp < max_age_seconds;
+                // Timestamps are sorted, so only a series whose oldest point has expired needs a private copy.
+                if series.timestamps().first().is_none_or(|oldest| keep(*oldest)) {
+                    return None;
+                }
+                let removed = Arc::make_mut(series).retain_timestamps(keep);
+                (removed > 0).then(|| location.clone())
+            })
+            .collect();
+
+        let cache = cache.downgrade();
+        if let Some(storage) = &self.storage {
+            for location in &trimmed {
+                let series = &cache[location];
+                storage.rewrite(location, &series.points(0..series.len()))?;
+            }
         }
+
+        let referenced: HashSet<[u8; 32]> = cache
+            .values()
+            .flat_map(|series| (0..series.len()).filter_map(|i| series.provenance(i).map(|p| p.payload_hash)))
+            .collect();
+        drop(cache);
+        self.upstream.write().await.retain(|hash, _| referenced.contains(hash));
+        Ok(())
     }
 
     pub async fn get_location_summary(&self) -> HashMap<String, usize> {
         let cache = self.data_cache.read().await;
         cache.iter()
-            .map(|(location, data)| (location.clone(), data.len()))
+            .map(|(location, series)| (location.clone(), series.len()))
             .collect()
     }
 
     pub async fn export_data(&self, location: &str, format: ExportFormat) -> Option<String> {
+        self.export_data_in(location, format, &UnitSystem::metric()).await
+    }
+
+    // Measurement keys and headers carry their unit, e.g. `temperature[degF]`.
+    pub async fn export_data_in(&self, location: &str, format: ExportFormat, units: &UnitSystem) -> Option<String> {
+        let location = &self.resolve_location(location).await;
         let cache = self.data_cache.read().await;
-        let data = cache.get(location)?;
+        let series = cache.get(location)?;
+        let elevation = series.elevation();
+        let data = series.points(0..series.len());
+        drop(cache);
 
         match format {
-            ExportFormat::Json => serde_json::to_string(data).ok(),
-            ExportFormat::Csv => self.export_to_csv(data),
+            ExportFormat::Json => {
+                let records: Vec<serde_json::Map<String, serde_json::Value>> = data
+                    .iter()
+                    .map(|point| {
+                        let mut record = serde_json::Map::new();
+                        record.insert("timestamp".to_string(), point.timestamp.into());
+                        record.insert("location".to_string(), point.location.clone().into());
+                        // Non-finite values become null, as serde_json writes them.
+                        for (name, value) in export_values(point, elevation, units) {
+                            record.insert(name, value.map_or(serde_json::Value::Null, serde_json::Value::from));
+                        }
+                        record.insert("source".to_string(), point.source.clone().into());
+                        record.insert("qc_flags".to_string(), point.qc_flags.0.into());
+                        record
+                    })
+                    .collect();
+                serde_json::to_string(&records).ok()
+            }
+            ExportFormat::Csv => self.export_to_csv(&data, elevation, units),
+            // Binary formats are written by export_columnar and export_partitioned.
+            ExportFormat::Parquet | ExportFormat::ArrowIpc => None,
         }
     }
 
-    fn export_to_csv(&self, data: &[WeatherDataPoint]) -> Option<String> {
-        let mut csv = String::from("timestamp,location,temperature,humidity,pressure,wind_speed,wind_direction,precipitation\n");
+    pub async fn export_columnar<W: std::io::Write + Send>(
+        &self,
+        writer: W,
+        format: ExportFormat,
+        options: &ColumnarExportOptions,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let locations = self.export_locations(options).await;
+        let schema = columnar_export::schema(&options.units);
+        let mut output = BatchWriter::new(format, writer, &schema, options.batch_rows)?;
+        let rows = self.write_batches(&mut output, &schema, &locations, options).await?;
+        output.finish()?;
+        Ok(rows)
+    }
 
-        for point in data {
-            csv.push_str(&format!(
-                "{},{},{},{},{},{},{},{}\n",
-                point.timestamp,
-                point.location,
-                point.temperature,
-                point.humidity,
-                point.pressure,
-                point.wind_speed,
-                point.wind_direction,
-                point.precipitation
-            ));
+    pub async fn export_partitioned<P: AsRef<std::path::Path>>(
+        &self,
+        root: P,
+        format: ExportFormat,
+        partitioning: Partitioning,
+        options: &ColumnarExportOptions,
+    ) -> Result<Vec<ExportedFile>, Box<dyn std::error::Error>> {
+        let locations = self.export_locations(options).await;
+        let mut partitions = Vec::new();
+
+        match partitioning {
+            Partitioning::None => partitions.push(ExportPartition { locations, location: None, month: None, window: options.window }),
+            Partitioning::Location => {
+                for location in locations {
+                    partitions.push(ExportPartition {
+                        locations: vec![location.clone()],
+                        location: Some(location),
+                        month: None,
+                        window: options.window,
+                    });
+                }
+            }
+            Partitioning::Month => {
+                if let Some((start, end)) = self.time_bounds(&locations, options.window).await {
+                    for (month, window) in columnar_export::month_windows(start, end) {
+                        partitions.push(ExportPartition { locations: locations.clone(), location: None, month: Some(month), window: Some(window) });
+                    }
+                }
+            }
+            Partitioning::LocationAndMonth => {
+                for location in locations {
+                    let selected = vec![location.clone()];
+                    if let Some((start, end)) = self.time_bounds(&selected, options.window).await {
+                        for (month, window) in columnar_export::month_windows(start, end) {
+                            partitions.push(ExportPartition {
+                                locations: selected.clone(),
+                                location: Some(location.clone()),
+                                month: Some(month),
+                                window: Some(window),
+                            });
+                        }
+                    }
+                }
+            }
         }
 
-        Some(csv)
+        let schema = columnar_export::schema(&options.units);
+        let mut files = Vec::new();
+        for partition in partitions {
+            let window = match (partition.window, options.window) {
+                (Some(partition), Some(requested)) => Some(TimeWindow::new(partition.start.max(requested.start), partition.end.min(requested.end))),
+                (window, _) => window,
+            };
+            if self.time_bounds(&partition.locations, window).await.is_none() {
+                continue;
+            }
+
+            let path = columnar_export::partition_path(root.as_ref(), partition.location.as_deref(), partition.month.as_deref(), &format);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            let mut output = BatchWriter::new(format.clone(), file, &schema, options.batch_rows)?;
+            let partition_options = ColumnarExportOptions { window, ..options.clone() };
+            let rows = self.write_batches(&mut output, &schema, &partition.locations, &partition_options).await?;
+            output.finish()?;
+            files.push(ExportedFile { path, rows });
+        }
+
+        Ok(files)
     }
 
-    pub async fn find_anomalies(&self, location: &str, threshold: f64) -> Vec<WeatherDataPoint> {
+    async fn export_locations(&self, options: &ColumnarExportOptions) -> Vec<String> {
+        if !options.locations.is_empty() {
+            let stations = self.stations.read().await;
+            return options
+                .locations
+                .iter()
+                .map(|name| stations.resolve(name).map_or_else(|| name.clone(), |station| station.id.clone()))
+                .collect();
+        }
+
+        let mut locations: Vec<String> = self.data_cache.read().await.keys().cloned().collect();
+        locations.sort();
+        locations
+    }
+
+    async fn time_bounds(&self, locations: &[String], window: Option<TimeWindow>) -> Option<(i64, i64)> {
         let cache = self.data_cache.read().await;
-        let data = cache.get(location)?;
+        locations
+            .iter()
+            .filter_map(|location| cache.get(location))
+            .filter_map(|series| {
+                let range = series.range(window);
+                if range.is_empty() {
+                    return None;
+                }
+                Some((series.timestamps()[range.start], series.timestamps()[range.end - 1]))
+            })
+            .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)))
+    }
+
+    // Copies one batch at a time under the read lock, so ingestion is never blocked for a whole export
+    // and the history is never materialised alongside the encoded output.
+    async fn write_batches<W: std::io::Write + Send>(
+        &self,
+        output: &mut BatchWriter<W>,
+        schema: &arrow::datatypes::SchemaRef,
+        locations: &[String],
+        options: &ColumnarExportOptions,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let (window, batch_rows) = (options.window, options.batch_rows);
+        let dictionary = Arc::new(arrow::array::StringArray::from(locations.to_vec()));
+        let end = window.map_or(i64::MAX, |w| w.end);
+        let mut rows = 0;
+
+        for (key, location) in locations.iter().enumerate() {
+            let mut cursor = window.map_or(i64::MIN, |w| w.start);
+            while cursor < end {
+                let batch = {
+                    let cache = self.data_cache.read().await;
+                    let series = match cache.get(location) {
+                        Some(series) => series,
+                        None => break,
+                    };
+                    let range = series.range_between(cursor, end);
+                    if range.is_empty() {
+                        break;
+                    }
+
+                    // Batches end on a timestamp boundary so the next one can resume from a timestamp.
+                    let timestamps = series.timestamps();
+                    let mut stop = (range.start + batch_rows.max(1)).min(range.end);
+                    while stop < range.end && timestamps[stop] == timestamps[stop - 1] {
+                        stop += 1;
+                    }
+                    cursor = timestamps[stop - 1].saturating_add(1);
+                    columnar_export::build_batch(schema, &dictionary, key as i32, series, range.start..stop, &options.units)?
+                };
 
-        if data.len() < 10 {
-            return Vec::new();
+                rows += batch.num_rows();
+                output.write(&batch)?;
+                if cursor == i64::MAX {
+                    break;
+                }
+            }
         }
 
-        let temperatures: Vec<f64> = data.iter().map(|d| d.temperature).collect();
-        let mean = temperatures.iter().sum::<f64>() / temperatures.len() as f64;
-        let std_dev = (temperatures.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / temperatures.len() as f64).sqrt();
+        Ok(rows)
+    }
 
-        data.iter()
-            .filter(|point| (point.temperature - mean).abs() > threshold * std_dev)
-            .cloned()
-            .collect()
+    fn export_to_csv(&self, data: &[WeatherDataPoint], elevation: Option<f64>, units: &UnitSystem) -> Option<String> {
+        let mut csv = String::from("timestamp,location");
+        for variable in WeatherVariable::ALL {
+            csv.push_str(&format!(",{}", units::annotated_name(variable.name(), units.unit(variable))));
+        }
+        for variable in DerivedVariable::ALL {
+            csv.push_str(&format!(",{}", units::annotated_name(variable.name(), units.derived_unit(variable))));
+        }
+        csv.push_str(",source,qc_flags\n");
+
+        for point in data {
+            csv.push_str(&format!("{},{}", point.timestamp, data_import::csv_field(&point.location, ',')));
+            // Only a missing sea-level pressure (no station elevation) is left empty.
+            for (_, value) in export_values(point, elevation, units) {
+                csv.push_str(&format!(",{}", value.map(|v| v.to_string()).unwrap_or_default()));
+            }
+            csv.push_str(&format!(",{},{}\n", data_import::csv_field(&point.source, ','), point.qc_flags.0));
+        }
+
+        Some(csv)
     }
 
-    pub async fn interpolate_missing_data(&self, location: &str) -> Vec<WeatherDataPoint> {
-        let cache = self.data_cache.read().await;
-        let data = cache.get(location)?;
-
-        if data.len() < 2 {
-            return data.clone();
-        }
-
-        let mut interpolated = Vec::new();
-        let mut sorted_data = data.clone();
-        sorted_data.sort_by_key(|d| d.timestamp);
-
-        for i in 0..sorted_data.len() - 1 {
-            interpolated.push(sorted_data[i].clone());
-
-            let time_diff = sorted_data[i + 1].timestamp - sorted_data[i].timestamp;
-            if time_diff > 3600 {
-                let steps = (time_diff / 3600) as usize;
-                for step in 1..steps {
-                    let ratio = step as f64 / steps as f64;
-                    let interpolated_point = WeatherDataPoint {
-                        timestamp: sorted_data[i].timestamp + (step as i64 * 3600),
-                        location: sorted_data[i].location.clone(),
-                        temperature: sorted_data[i].temperature + ratio * (sorted_data[i + 1].temperature - sorted_data[i].temperature),
-                        humidity: sorted_data[i].humidity + ratio * (sorted_data[i + 1].humidity - sorted_data[i].humidity),
-                        pressure: sorted_data[i].pressure + ratio * (sorted_data[i + 1].pressure - sorted_data[i].pressure),
-                        wind_speed: sorted_data[i].wind_speed + ratio * (sorted_data[i + 1].wind_speed - sorted_data[i].wind_speed),
-                        wind_direction: sorted_data[i].wind_direction,
-                        precipitation: 0.0,
-                    };
-                    interpolated.push(interpolated_point);
+    pub async fn import_csv<R: std::io::BufRead>(&self, reader: R, config: &ImportConfig) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let records = CsvRecords::new(reader, config)?;
+        self.import_records(records, config).await
+    }
+
+    pub async fn import_json<R: std::io::BufRead>(&self, reader: R, config: &ImportConfig) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let records = JsonRecords::new(reader, config)?;
+        self.import_records(records, config).await
+    }
+
+    pub async fn import_file<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        format: ExportFormat,
+        config: &ImportConfig,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        match format {
+            ExportFormat::Json => self.import_json(reader, config).await,
+            ExportFormat::Csv => self.import_csv(reader, config).await,
+            ExportFormat::Parquet | ExportFormat::ArrowIpc => Err(format!("Importing {:?} is not supported", format).into()),
+        }
+    }
+
+    // Lenient imports are ingested batch by batch; strict ones are staged on disk and only ingested once every
+    // row has parsed. Imported QC flags are re-derived like those of any other ingested point.
+    async fn import_records<I>(&self, records: I, config: &ImportConfig) -> Result<ImportReport, Box<dyn std::error::Error>>
+    where
+        I: Iterator<Item = Result<WeatherDataPoint, RowError>>,
+    {
+        let batch_size = config.batch_size.max(1);
+        let mut staged = match config.mode {
+            ImportMode::Strict => Some(StagedRows::create()?),
+            ImportMode::Lenient => None,
+        };
+        let mut report = ImportReport::default();
+        let mut batch = Vec::new();
+
+        for record in records {
+            report.rows_read += 1;
+            match (record, &mut staged) {
+                (Ok(point), Some(staged)) => staged.push(&point)?,
+                (Ok(point), None) => batch.push(point),
+                (Err(error), _) if config.mode == ImportMode::Strict => return Err(error.into()),
+                (Err(error), _) => {
+                    report.rows_rejected += 1;
+                    report.errors.push(error);
+                }
+            }
+
+            if batch.len() >= batch_size {
+                let ingested = self.add_checked_batch(std::mem::take(&mut batch), None).await?;
+                report.ingest.merge(&ingested);
+            }
+        }
+
+        if let Some(mut staged) = staged {
+            for point in staged.read()? {
+                batch.push(point?);
+                if batch.len() >= batch_size {
+                    let ingested = self.add_checked_batch(std::mem::take(&mut batch), None).await?;
+                    report.ingest.merge(&ingested);
                 }
             }
         }
+        if !batch.is_empty() {
+            let ingested = self.add_checked_batch(batch, None).await?;
+            report.ingest.merge(&ingested);
+        }
+        Ok(report)
+    }
+
+    pub async fn degree_days(
+        &self,
+        location: &str,
+        season_start: NaiveDate,
+        through: NaiveDate,
+        config: &DegreeDayConfig,
+    ) -> Result<DegreeDaySeries, Box<dyn std::error::Error>> {
+        let location = &self.resolve_location(location).await;
+        let window = TimeWindow::new(
+            TimeWindow::calendar_day(season_start, config.timezone).ok_or("Season start does not exist in timezone")?.start,
+            TimeWindow::calendar_day(through, config.timezone).ok_or("Season end does not exist in timezone")?.end,
+        );
+        let samples: Vec<(i64, f64)> = {
+            let cache = self.data_cache.read().await;
+            match cache.get(location) {
+                Some(series) => series
+                    .range(Some(window))
+                    .filter(|i| series.qc_flags()[*i].settlement_eligible())
+                    .map(|i| (series.timestamps()[i], series.column(WeatherVariable::Temperature)[i]))
+                    .collect(),
+                None => Vec::new(),
+            }
+        };
+
+        degree_days::compute_degree_days(location, &samples, config, season_start, through)
+    }
+
+    pub async fn compute_normals(&self, location: &str, variable: WeatherVariable, config: &ClimatologyConfig) -> Option<ClimateNormals> {
+        let location = &self.resolve_location(location).await;
+        let points = self.points_in_window(location, None).await;
+        let normals = climatology::compute_normals(location, &points, variable, config)?;
+        self.normals.write().await.insert((location.to_string(), variable), normals.clone());
+        Some(normals)
+    }
+
+    pub async fn normals(&self, location: &str, variable: WeatherVariable) -> Option<ClimateNormals> {
+        let location = &self.resolve_location(location).await;
+        self.normals.read().await.get(&(location.to_string(), variable)).cloned()
+    }
+
+    pub async fn save_normals<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let normals: Vec<ClimateNormals> = self.normals.read().await.values().cloned().collect();
+        let json = serde_json::to_vec(&normals)?;
+        std::fs::write(path, json)
+    }
+
+    pub async fn load_normals<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<usize> {
+        let normals: Vec<ClimateNormals> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let count = normals.len();
+        let mut stored = self.normals.write().await;
+        for entry in normals {
+            stored.insert((entry.location.clone(), entry.variable), entry);
+        }
+        Ok(count)
+    }
+
+    pub async fn departure_from_normal(&self, location: &str, variable: WeatherVariable, timestamp: i64, value: f64) -> Option<ClimateDeparture> {
+        Some(self.normals(location, variable).await?.departure(timestamp, value))
+    }
 
-        if !sorted_data.is_empty() {
-            interpolated.push(sorted_data.last().unwrap().clone());
+    pub async fn window_departure(&self, location: &str, variable: WeatherVariable, window: Option<TimeWindow>) -> Option<ClimateDeparture> {
+        let location = &self.resolve_location(location).await;
+        let normals = self.normals(location, variable).await?;
+        let cache = self.data_cache.read().await;
+        let series = cache.get(location)?;
+        normals.window_departure(&eligible_samples(series, series.range(window), variable))
+    }
+
+    pub async fn block_maxima(&self, location: &str, variable: WeatherVariable, config: &ExtremeConfig) -> Vec<BlockMaximum> {
+        let samples = self.eligible_history(location, variable).await;
+        extreme_values::block_maxima(&extreme_values::daily_values(&samples, config), config)
+    }
+
+    pub async fn fit_gev(&self, location: &str, variable: WeatherVariable, config: &ExtremeConfig) -> Result<ExtremeFit, Box<dyn std::error::Error>> {
+        let samples = self.eligible_history(location, variable).await;
+        extreme_values::fit_gev(location, variable, &samples, config)
+    }
+
+    pub async fn fit_gpd(&self, location: &str, variable: WeatherVariable, config: &ExtremeConfig) -> Result<ExtremeFit, Box<dyn std::error::Error>> {
+        let samples = self.eligible_history(location, variable).await;
+        extreme_values::fit_gpd(location, variable, &samples, config)
+    }
+
+    async fn eligible_history(&self, location: &str, variable: WeatherVariable) -> Vec<(i64, f64)> {
+        let location = &self.resolve_location(location).await;
+        let cache = self.data_cache.read().await;
+        match cache.get(location) {
+            Some(series) => eligible_samples(series, 0..series.len(), variable),
+            None => Vec::new(),
         }
+    }
 
-        interpolated
+    // The baseline comes from the location's history outside the window; without a window the whole
+    // series is scored against itself.
+    pub async fn find_anomalies(&self, location: &str, window: Option<TimeWindow>, config: &AnomalyConfig) -> Vec<AnomalyEvent> {
+        let location = &self.resolve_location(location).await;
+        let (points, history) = {
+            let cache = self.data_cache.read().await;
+            let series = match cache.get(location) {
+                Some(series) => series,
+                None => return Vec::new(),
+            };
+            let tested = series.range(window);
+            let history = match window {
+                Some(_) => {
+                    let mut history = series.points(0..tested.start);
+                    history.extend(series.points(tested.end..series.len()));
+                    history
+                }
+                None => series.points(tested.clone()),
+            };
+            (series.points(tested), history)
+        };
+        anomaly_detection::detect_anomalies(&points, &history, config)
+    }
+
+    pub async fn interpolate_missing_data(&self, location: &str, config: &GapFillConfig) -> Vec<WeatherDataPoint> {
+        let points = self.points_in_window(location, None).await;
+        gap_filling::fill_gaps(&points, config)
+    }
+}
+
+fn apply_qc(pipeline: &QcPipeline, cache: &HashMap<String, Arc<LocationSeries>>, point: &mut WeatherDataPoint) {
+    let context = QcContext {
+        history: cache.get(&point.location).map(Arc::as_ref),
+        neighbors: pipeline
+            .neighbors_of(&point.location)
+            .iter()
+            .filter_map(|neighbor| cache.get(neighbor).map(Arc::as_ref))
+            .collect(),
+    };
+
+    // Re-checking must not make imported gap-filled points look measured.
+    let interpolated = point.qc_flags.is_interpolated();
+    point.qc_flags = pipeline.evaluate(point, &context);
+    if interpolated {
+        point.qc_flags.insert(QcFlags::INTERPOLATED);
     }
 }
 
+// Measured then derived values under their annotated names, converted from canonical units.
+fn export_values(point: &WeatherDataPoint, elevation: Option<f64>, units: &UnitSystem) -> Vec<(String, Option<f64>)> {
+    let derived = DerivedValues::compute(point, elevation);
+    let measured = WeatherVariable::ALL.iter().map(|variable| {
+        let unit = units.unit(*variable);
+        (units::annotated_name(variable.name(), unit), Some(unit.from_canonical(variable.value(point))))
+    });
+    let derived = DerivedVariable::ALL.iter().map(|variable| {
+        let unit = units.derived_unit(*variable);
+        (units::annotated_name(variable.name(), unit), derived.get(*variable).map(|v| unit.from_canonical(v)))
+    });
+    measured.chain(derived).collect()
+}
+
+fn eligible_samples(series: &LocationSeries, range: std::ops::Range<usize>, variable: WeatherVariable) -> Vec<(i64, f64)> {
+    let values = series.column(variable);
+    range
+        .filter(|i| series.qc_flags()[*i].settlement_eligible() && values[*i].is_finite())
+        .map(|i| (series.timestamps()[i], values[i]))
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum ExportFormat {
     Json,
     Csv,
+    Parquet,
+    ArrowIpc,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::ArrowIpc => "arrow",
+        }
+    }
 }
\ No newline at end of file
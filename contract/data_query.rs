@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::derived_variables::{DerivedValues, DerivedVariable};
+use crate::quality_control::QcFlags;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WeatherVariable {
+    Temperature,
+    Humidity,
+    Pressure,
+    WindSpeed,
+    WindDirection,
+    Precipitation,
+}
+
+impl WeatherVariable {
+    pub const ALL: [WeatherVariable; 6] = [
+        WeatherVariable::Temperature,
+        WeatherVariable::Humidity,
+        WeatherVariable::Pressure,
+        WeatherVariable::WindSpeed,
+        WeatherVariable::WindDirection,
+        WeatherVariable::Precipitation,
+    ];
+
+    pub fn value(&self, point: &WeatherDataPoint) -> f64 {
+        match self {
+            WeatherVariable::Temperature => point.temperature,
+            WeatherVariable::Humidity => point.humidity,
+            WeatherVariable::Pressure => point.pressure,
+            WeatherVariable::WindSpeed => point.wind_speed,
+            WeatherVariable::WindDirection => point.wind_direction,
+            WeatherVariable::Precipitation => point.precipitation,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherVariable::Temperature => "temperature",
+            WeatherVariable::Humidity => "humidity",
+            WeatherVariable::Pressure => "pressure",
+            WeatherVariable::WindSpeed => "wind_speed",
+            WeatherVariable::WindDirection => "wind_direction",
+            WeatherVariable::Precipitation => "precipitation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl TimeWindow {
+    pub fn new(start: i64, end: i64) -> Self {
+        Self { start, end }
+    }
+
+    pub fn last_seconds(seconds: i64, now: i64) -> Self {
+        Self { start: now - seconds, end: now }
+    }
+
+    pub fn last_hours(hours: i64) -> Self {
+        let now = Utc::now().timestamp();
+        Self::last_seconds(hours * 3600, now)
+    }
+
+    pub fn calendar_day(date: NaiveDate, timezone: Tz) -> Option<Self> {
+        let end = local_start_of_day(timezone, date.checked_add_signed(Duration::days(1))?)?;
+        Some(Self { start: local_start_of_day(timezone, date)?, end })
+    }
+
+    pub fn contains(&self, timestamp: i64) -> bool {
+        timestamp >= self.start && timestamp < self.end
+    }
+
+    pub fn duration_secs(&self) -> i64 {
+        self.end - self.start
+    }
+}
+
+// Where a DST jump skips midnight, the day starts at the first local time that exists.
+pub fn local_start_of_day(timezone: Tz, date: NaiveDate) -> Option<i64> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    (0..=180)
+        .find_map(|minutes| timezone.from_local_datetime(&(midnight + Duration::minutes(minutes))).earliest())
+        .map(|start| start.timestamp())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataQuery {
+    pub locations: Vec<String>,
+    pub window: Option<TimeWindow>,
+    pub variables: Vec<WeatherVariable>,
+    pub derived: Vec<DerivedVariable>,
+    pub limit: Option<usize>,
+    pub exclude_failed_qc: bool,
+    pub exclude_interpolated: bool,
+    // Empty selects every source.
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+impl DataQuery {
+    pub fn for_locations(locations: Vec<String>) -> Self {
+        Self {
+            locations,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_window(mut self, window: TimeWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    pub fn with_variables(mut self, variables: Vec<WeatherVariable>) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    pub fn with_derived(mut self, derived: Vec<DerivedVariable>) -> Self {
+        self.derived = derived;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn excluding_failed_qc(mut self) -> Self {
+        self.exclude_failed_qc = true;
+        self
+    }
+
+    pub fn with_sources(mut self, sources: Vec<String>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn settlement_grade(mut self) -> Self {
+        self.exclude_failed_qc = true;
+        self.exclude_interpolated = true;
+        self
+    }
+
+    pub fn selected_variables(&self) -> Vec<WeatherVariable> {
+        if self.variables.is_empty() {
+            WeatherVariable::ALL.to_vec()
+        } else {
+            self.variables.clone()
+        }
+    }
+
+    pub fn matches(&self, point: &WeatherDataPoint) -> bool {
+        self.window.is_none_or(|w| w.contains(point.timestamp))
+            && !(self.exclude_failed_qc && point.qc_flags.failed())
+            && !(self.exclude_interpolated && point.qc_flags.is_interpolated())
+            && (self.sources.is_empty() || self.sources.contains(&point.source))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRow {
+    pub timestamp: i64,
+    pub location: String,
+    pub values: BTreeMap<WeatherVariable, f64>,
+    #[serde(default)]
+    pub derived: BTreeMap<DerivedVariable, f64>,
+    pub qc_flags: QcFlags,
+    #[serde(default)]
+    pub source: String,
+}
+
+impl QueryRow {
+    pub fn project(
+        point: &WeatherDataPoint,
+        variables: &[WeatherVariable],
+        derived: &[DerivedVariable],
+        elevation_m: Option<f64>,
+    ) -> Self {
+        let derived_values = if derived.is_empty() {
+            BTreeMap::new()
+        } else {
+            let computed = DerivedValues::compute(point, elevation_m);
+            derived.iter().filter_map(|d| computed.get(*d).map(|value| (*d, value))).collect()
+        };
+
+        Self {
+            timestamp: point.timestamp,
+            location: point.location.clone(),
+            values: variables.iter().map(|v| (*v, v.value(point))).collect(),
+            derived: derived_values,
+            qc_flags: point.qc_flags,
+            source: point.source.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar_days_around_a_skipped_midnight() {
+        // Chile moved its clocks from 00:00 to 01:00 on 2022-09-11, so that day starts at 01:00 local (04:00 UTC).
+        let santiago = chrono_tz::America::Santiago;
+        let before = TimeWindow::calendar_day(NaiveDate::from_ymd_opt(2022, 9, 10).unwrap(), santiago).unwrap();
+        let gap = TimeWindow::calendar_day(NaiveDate::from_ymd_opt(2022, 9, 11).unwrap(), santiago).unwrap();
+        assert_eq!(before.end, gap.start);
+        assert_eq!(before.duration_secs(), 24 * 3600);
+        assert_eq!(gap.start, Utc.with_ymd_and_hms(2022, 9, 11, 4, 0, 0).unwrap().timestamp());
+        assert_eq!(gap.duration_secs(), 23 * 3600);
+    }
+}
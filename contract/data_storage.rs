@@ -0,0 +1,750 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use sha3::{Digest, Sha3_256};
+
+use crate::data_processor::WeatherDataPoint;
+
+const SEGMENT_PREFIX: &str = "seg-";
+const SEGMENT_EXT: &str = "log";
+const COMPACT_EXT: &str = "compact";
+const TMP_EXT: &str = "tmp";
+const RECORD_HEADER_LEN: u64 = 8;
+const DIR_PREFIX: &str = "loc-";
+const HASHED_DIR_PREFIX: &str = "loch-";
+const LOCATION_NAME_FILE: &str = "location";
+// Most filesystems cap a path component at 255 bytes.
+const MAX_DIR_NAME_LEN: usize = 255;
+
+pub trait WeatherStorage: Send + Sync {
+    fn append(&self, points: &[WeatherDataPoint]) -> io::Result<()>;
+    fn load(&self, location: &str) -> io::Result<Vec<WeatherDataPoint>>;
+    fn load_range(&self, location: &str, start: i64, end: i64) -> io::Result<Vec<WeatherDataPoint>>;
+    fn locations(&self) -> io::Result<Vec<String>>;
+    fn rewrite(&self, location: &str, points: &[WeatherDataPoint]) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct DiskStorageConfig {
+    pub max_segment_bytes: u64,
+    pub sync_on_append: bool,
+}
+
+impl Default for DiskStorageConfig {
+    fn default() -> Self {
+        Self {
+            max_segment_bytes: 64 * 1024 * 1024,
+            sync_on_append: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RecordPos {
+    segment: u64,
+    offset: u64,
+}
+
+#[derive(Debug, Clone)]
+struct SegmentMeta {
+    id: u64,
+    len: u64,
+    min_timestamp: i64,
+    max_timestamp: i64,
+}
+
+#[derive(Debug, Default)]
+struct LocationIndex {
+    segments: Vec<SegmentMeta>,
+    by_timestamp: BTreeMap<i64, Vec<RecordPos>>,
+    // Set when a failed append or rewrite could not be undone; the directory is left to recovery on reopen.
+    torn: bool,
+}
+
+// What an append added to a location, so it can be undone if a later step fails.
+struct AppendUndo {
+    segment_count: usize,
+    last_segment: Option<SegmentMeta>,
+    timestamps: Vec<i64>,
+}
+
+pub struct DiskStorage {
+    root: PathBuf,
+    config: DiskStorageConfig,
+    indexes: Mutex<HashMap<String, LocationIndex>>,
+}
+
+impl DiskStorage {
+    pub fn open<P: AsRef<Path>>(root: P, config: DiskStorageConfig) -> io::Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        let mut indexes = HashMap::new();
+        for entry in fs::read_dir(&root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let location = if dir_name.starts_with(HASHED_DIR_PREFIX) {
+                match fs::read_to_string(entry.path().join(LOCATION_NAME_FILE)) {
+                    Ok(location) => Some(location),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(e),
+                }
+            } else {
+                decode_location_dir(&dir_name)
+            };
+            if let Some(location) = location {
+                let index = recover_location(&entry.path())?;
+                indexes.insert(location, index);
+            }
+        }
+
+        Ok(Self {
+            root,
+            config,
+            indexes: Mutex::new(indexes),
+        })
+    }
+
+    fn location_dir(&self, location: &str) -> PathBuf {
+        self.root.join(encode_location_dir(location))
+    }
+
+    fn create_location_dir(&self, location: &str) -> io::Result<PathBuf> {
+        let dir = self.location_dir(location);
+        fs::create_dir_all(&dir)?;
+
+        // Hashed directory names cannot be decoded, so the location is stored beside the segments.
+        let name_path = dir.join(LOCATION_NAME_FILE);
+        if dir_name_is_hashed(&dir) && !name_path.exists() {
+            let tmp_path = dir.join(format!("{}.{}", LOCATION_NAME_FILE, TMP_EXT));
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(location.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, &name_path)?;
+            sync_dir(&dir)?;
+        }
+
+        Ok(dir)
+    }
+
+    fn append_location(&self, index: &mut LocationIndex, location: &str, points: &[&WeatherDataPoint]) -> io::Result<AppendUndo> {
+        if index.torn {
+            return Err(io::Error::other(format!("Storage for {} has an unrecovered torn write; reopen to recover", location)));
+        }
+
+        let dir = self.create_location_dir(location)?;
+        let mut undo = AppendUndo {
+            segment_count: index.segments.len(),
+            last_segment: index.segments.last().cloned(),
+            timestamps: Vec::new(),
+        };
+
+        match self.write_location(index, &dir, points, &mut undo) {
+            Ok(()) => Ok(undo),
+            Err(e) => {
+                self.rollback(index, &dir, undo);
+                Err(e)
+            }
+        }
+    }
+
+    fn write_location(
+        &self,
+        index: &mut LocationIndex,
+        dir: &Path,
+        points: &[&WeatherDataPoint],
+        undo: &mut AppendUndo,
+    ) -> io::Result<()> {
+        let mut pending: Vec<(i64, Vec<u8>)> = Vec::with_capacity(points.len());
+        for point in points {
+            pending.push((point.timestamp, encode_record(point)?));
+        }
+
+        let mut cursor = 0;
+        while cursor < pending.len() {
+            let needs_new_segment = match index.segments.last() {
+                Some(segment) => segment.len >= self.config.max_segment_bytes,
+                None => true,
+            };
+
+            if needs_new_segment {
+                let id = index.segments.last().map_or(1, |s| s.id + 1);
+                index.segments.push(SegmentMeta {
+                    id,
+                    len: 0,
+                    min_timestamp: i64::MAX,
+                    max_timestamp: i64::MIN,
+                });
+                File::create(segment_path(dir, id, SEGMENT_EXT))?;
+                sync_dir(dir)?;
+            }
+
+            let segment = index.segments.last_mut().unwrap();
+            let mut buffer = Vec::new();
+            let mut positions = Vec::new();
+            let mut offset = segment.len;
+
+            while cursor < pending.len() && (buffer.is_empty() || offset < self.config.max_segment_bytes) {
+                let (timestamp, record) = &pending[cursor];
+                positions.push((*timestamp, RecordPos { segment: segment.id, offset }));
+                offset += record.len() as u64;
+                buffer.extend_from_slice(record);
+                cursor += 1;
+            }
+
+            let mut file = OpenOptions::new().append(true).open(segment_path(dir, segment.id, SEGMENT_EXT))?;
+            file.write_all(&buffer)?;
+            if self.config.sync_on_append {
+                file.sync_data()?;
+            }
+
+            segment.len = offset;
+            for (timestamp, pos) in positions {
+                segment.min_timestamp = segment.min_timestamp.min(timestamp);
+                segment.max_timestamp = segment.max_timestamp.max(timestamp);
+                index.by_timestamp.entry(timestamp).or_default().push(pos);
+                undo.timestamps.push(timestamp);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Truncates the segments back to their pre-append length so a partial write leaves no
+    // tear for later appends to land behind.
+    fn rollback(&self, index: &mut LocationIndex, dir: &Path, undo: AppendUndo) {
+        for timestamp in undo.timestamps.iter().rev() {
+            if let Some(positions) = index.by_timestamp.get_mut(timestamp) {
+                positions.pop();
+                if positions.is_empty() {
+                    index.by_timestamp.remove(timestamp);
+                }
+            }
+        }
+
+        let mut restored = true;
+        for segment in index.segments.drain(undo.segment_count..) {
+            match fs::remove_file(segment_path(dir, segment.id, SEGMENT_EXT)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => restored = false,
+                _ => {}
+            }
+        }
+
+        if let Some(last) = undo.last_segment {
+            let truncated = OpenOptions::new()
+                .write(true)
+                .open(segment_path(dir, last.id, SEGMENT_EXT))
+                .and_then(|file| {
+                    file.set_len(last.len)?;
+                    file.sync_data()
+                });
+            restored &= truncated.is_ok();
+            if let Some(segment) = index.segments.last_mut() {
+                *segment = last;
+            }
+        }
+
+        index.torn = !restored;
+    }
+
+    fn read_positions(&self, location: &str, positions: &[RecordPos]) -> io::Result<Vec<WeatherDataPoint>> {
+        let dir = self.location_dir(location);
+        let mut open_segment: Option<(u64, File)> = None;
+        let mut points = Vec::with_capacity(positions.len());
+
+        for pos in positions {
+            let reopen = !matches!(&open_segment, Some((id, _)) if *id == pos.segment);
+            if reopen {
+                open_segment = Some((pos.segment, File::open(segment_path(&dir, pos.segment, SEGMENT_EXT))?));
+            }
+
+            let (_, file) = open_segment.as_mut().unwrap();
+            file.seek(SeekFrom::Start(pos.offset))?;
+            match read_record(file)? {
+                Some((point, _)) => points.push(point),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Indexed record is missing or corrupt")),
+            }
+        }
+
+        Ok(points)
+    }
+}
+
+impl WeatherStorage for DiskStorage {
+    fn append(&self, points: &[WeatherDataPoint]) -> io::Result<()> {
+        let mut by_location: BTreeMap<&str, Vec<&WeatherDataPoint>> = BTreeMap::new();
+        for point in points {
+            by_location.entry(point.location.as_str()).or_default().push(point);
+        }
+
+        // All locations land or none do: a failure undoes the locations already written.
+        let mut indexes = self.indexes.lock().unwrap();
+        let mut written: Vec<(&str, AppendUndo)> = Vec::new();
+        for (location, location_points) in by_location {
+            let index = indexes.entry(location.to_string()).or_default();
+            match self.append_location(index, location, &location_points) {
+                Ok(undo) => written.push((location, undo)),
+                Err(e) => {
+                    for (location, undo) in written.into_iter().rev() {
+                        if let Some(index) = indexes.get_mut(location) {
+                            self.rollback(index, &self.location_dir(location), undo);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load(&self, location: &str) -> io::Result<Vec<WeatherDataPoint>> {
+        self.load_range(location, i64::MIN, i64::MAX)
+    }
+
+    fn load_range(&self, location: &str, start: i64, end: i64) -> io::Result<Vec<WeatherDataPoint>> {
+        let positions: Vec<RecordPos> = {
+            let indexes = self.indexes.lock().unwrap();
+            let index = match indexes.get(location) {
+                Some(index) => index,
+                None => return Ok(Vec::new()),
+            };
+            if start >= end {
+                return Ok(Vec::new());
+            }
+
+            index.by_timestamp
+                .range(start..end)
+                .flat_map(|(_, positions)| positions.iter().copied())
+                .collect()
+        };
+
+        self.read_positions(location, &positions)
+    }
+
+    fn locations(&self) -> io::Result<Vec<String>> {
+        let indexes = self.indexes.lock().unwrap();
+        let mut locations: Vec<String> = indexes
+            .iter()
+            .filter(|(_, index)| !index.by_timestamp.is_empty())
+            .map(|(location, _)| location.clone())
+            .collect();
+        locations.sort();
+        Ok(locations)
+    }
+
+    fn rewrite(&self, location: &str, points: &[WeatherDataPoint]) -> io::Result<()> {
+        let dir = self.create_location_dir(location)?;
+
+        let mut indexes = self.indexes.lock().unwrap();
+        let index = indexes.entry(location.to_string()).or_default();
+        if index.torn {
+            return Err(io::Error::other(format!("Storage for {} has an unrecovered torn write; reopen to recover", location)));
+        }
+        let compact_id = index.segments.last().map_or(1, |s| s.id + 1);
+
+        // Write the replacement segment under a temporary name, then promote it in steps
+        // that recover_location can finish or roll back after a crash.
+        let tmp_path = segment_path(&dir, compact_id, TMP_EXT);
+        let mut file = File::create(&tmp_path)?;
+        for point in points {
+            file.write_all(&encode_record(point)?)?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        let promoted = promote_compaction(&dir, &index.segments, compact_id);
+
+        // A failed promotion may have deleted some of the old segments, so rebuild the index
+        // from disk the way a reopen would rather than keep pointing at them.
+        let rebuilt = match promoted {
+            Ok(()) => scan_segments(&dir),
+            Err(_) => recover_location(&dir),
+        };
+        match rebuilt {
+            Ok(rebuilt) => *index = rebuilt,
+            Err(e) => {
+                *index = LocationIndex { torn: true, ..LocationIndex::default() };
+                return Err(e);
+            }
+        }
+        promoted
+    }
+}
+
+fn promote_compaction(dir: &Path, segments: &[SegmentMeta], compact_id: u64) -> io::Result<()> {
+    let compact_path = segment_path(dir, compact_id, COMPACT_EXT);
+    fs::rename(segment_path(dir, compact_id, TMP_EXT), &compact_path)?;
+    sync_dir(dir)?;
+
+    for segment in segments {
+        fs::remove_file(segment_path(dir, segment.id, SEGMENT_EXT))?;
+    }
+    fs::rename(&compact_path, segment_path(dir, compact_id, SEGMENT_EXT))?;
+    sync_dir(dir)
+}
+
+fn recover_location(dir: &Path) -> io::Result<LocationIndex> {
+    let mut compacted: Option<u64> = None;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        match parse_segment_name(&path) {
+            Some((_, ext)) if ext == TMP_EXT => fs::remove_file(&path)?,
+            Some((id, ext)) if ext == COMPACT_EXT => compacted = Some(compacted.map_or(id, |c: u64| c.max(id))),
+            _ => {}
+        }
+    }
+
+    // A completed compaction supersedes every older segment.
+    if let Some(compact_id) = compacted {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some((id, ext)) = parse_segment_name(&path) {
+                if ext == SEGMENT_EXT && id < compact_id {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        fs::rename(segment_path(dir, compact_id, COMPACT_EXT), segment_path(dir, compact_id, SEGMENT_EXT))?;
+        sync_dir(dir)?;
+    }
+
+    scan_segments(dir)
+}
+
+fn scan_segments(dir: &Path) -> io::Result<LocationIndex> {
+    let mut ids: Vec<u64> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| parse_segment_name(&entry.path()))
+        .filter(|(_, ext)| ext == SEGMENT_EXT)
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort_unstable();
+
+    let mut index = LocationIndex::default();
+    let last_id = ids.last().copied();
+
+    for id in ids {
+        let path = segment_path(dir, id, SEGMENT_EXT);
+        let mut reader = BufReader::new(File::open(&path)?);
+        let mut meta = SegmentMeta {
+            id,
+            len: 0,
+            min_timestamp: i64::MAX,
+            max_timestamp: i64::MIN,
+        };
+
+        loop {
+            match read_record(&mut reader) {
+                Ok(Some((point, record_len))) => {
+                    index.by_timestamp
+                        .entry(point.timestamp)
+                        .or_default()
+                        .push(RecordPos { segment: id, offset: meta.len });
+                    meta.min_timestamp = meta.min_timestamp.min(point.timestamp);
+                    meta.max_timestamp = meta.max_timestamp.max(point.timestamp);
+                    meta.len += record_len;
+                }
+                Ok(None) => break,
+                // A torn write can only affect the tail of the active segment.
+                Err(_) if Some(id) == last_id => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let file_len = fs::metadata(&path)?.len();
+        if file_len != meta.len {
+            if Some(id) != last_id {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Corrupt segment {:?}", path)));
+            }
+            let file = OpenOptions::new().write(true).open(&path)?;
+            file.set_len(meta.len)?;
+            file.sync_all()?;
+        }
+
+        index.segments.push(meta);
+    }
+
+    Ok(index)
+}
+
+fn encode_record(point: &WeatherDataPoint) -> io::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(point).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<(WeatherDataPoint, u64)>> {
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    match read_exact_or_eof(reader, &mut header)? {
+        0 => return Ok(None),
+        n if n < header.len() => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated record header")),
+        _ => {}
+    }
+
+    let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let checksum = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    if crc32fast::hash(&payload) != checksum {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Record checksum mismatch"));
+    }
+
+    let point = serde_json::from_slice(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some((point, RECORD_HEADER_LEN + len as u64)))
+}
+
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn segment_path(dir: &Path, id: u64, ext: &str) -> PathBuf {
+    dir.join(format!("{}{:010}.{}", SEGMENT_PREFIX, id, ext))
+}
+
+fn parse_segment_name(path: &Path) -> Option<(u64, String)> {
+    let name = path.file_name()?.to_str()?;
+    let rest = name.strip_prefix(SEGMENT_PREFIX)?;
+    let (id, ext) = rest.split_once('.')?;
+    Some((id.parse().ok()?, ext.to_string()))
+}
+
+fn encode_location_dir(location: &str) -> String {
+    if DIR_PREFIX.len() + 2 * location.len() > MAX_DIR_NAME_LEN {
+        return format!("{}{}", HASHED_DIR_PREFIX, hex(&Sha3_256::digest(location.as_bytes())));
+    }
+    format!("{}{}", DIR_PREFIX, hex(location.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn dir_name_is_hashed(dir: &Path) -> bool {
+    dir.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(HASHED_DIR_PREFIX))
+}
+
+fn decode_location_dir(name: &str) -> Option<String> {
+    let hex = name.strip_prefix(DIR_PREFIX)?;
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+    String::from_utf8(bytes?).ok()
+}
+
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::data_processor::DataProcessor;
+    use crate::data_query::TimeWindow;
+    use crate::quality_control::QcFlags;
+
+    fn point(location: &str, timestamp: i64, temperature: f64) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: location.to_string(),
+            temperature,
+            humidity: 60.0,
+            pressure: f64::NAN,
+            wind_speed: 3.0,
+            wind_direction: 180.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::CHECKED,
+            source: "metar".to_string(),
+            provenance: None,
+        }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("weather-storage-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn small_segments() -> DiskStorageConfig {
+        DiskStorageConfig { max_segment_bytes: 512, sync_on_append: false }
+    }
+
+    fn temperatures(points: &[WeatherDataPoint]) -> Vec<(i64, f64)> {
+        points.iter().map(|p| (p.timestamp, p.temperature)).collect()
+    }
+
+    fn files_with_ext(dir: &Path, ext: &str) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| parse_segment_name(&entry.unwrap().path()))
+            .filter(|(_, e)| e == ext)
+            .count()
+    }
+
+    #[test]
+    fn reopen_truncates_a_torn_tail_and_keeps_complete_records() {
+        let root = temp_root("torn-tail");
+        let storage = DiskStorage::open(&root, DiskStorageConfig::default()).unwrap();
+        storage.append(&[point("KJFK", 100, 20.0), point("KJFK", 200, 21.0)]).unwrap();
+        drop(storage);
+
+        // A crash mid-append leaves a partial record behind the last complete one.
+        let segment = segment_path(&root.join(encode_location_dir("KJFK")), 1, SEGMENT_EXT);
+        let complete_len = fs::metadata(&segment).unwrap().len();
+        let record = encode_record(&point("KJFK", 300, 22.0)).unwrap();
+        OpenOptions::new().append(true).open(&segment).unwrap().write_all(&record[..record.len() / 2]).unwrap();
+
+        let storage = DiskStorage::open(&root, DiskStorageConfig::default()).unwrap();
+        assert_eq!(temperatures(&storage.load("KJFK").unwrap()), vec![(100, 20.0), (200, 21.0)]);
+        assert_eq!(fs::metadata(&segment).unwrap().len(), complete_len);
+
+        storage.append(&[point("KJFK", 300, 22.0)]).unwrap();
+        drop(storage);
+        let storage = DiskStorage::open(&root, DiskStorageConfig::default()).unwrap();
+        assert_eq!(storage.load("KJFK").unwrap().len(), 3);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn failed_append_rolls_back_locations_already_written() {
+        let root = temp_root("rollback");
+        let storage = DiskStorage::open(&root, DiskStorageConfig::default()).unwrap();
+        storage.append(&[point("KBOS", 100, 10.0)]).unwrap();
+        let segment = segment_path(&root.join(encode_location_dir("KBOS")), 1, SEGMENT_EXT);
+        let len_before = fs::metadata(&segment).unwrap().len();
+
+        // KBOS is written first; a file squatting on KJFK's directory makes the second location fail.
+        fs::write(root.join(encode_location_dir("KJFK")), b"not a directory").unwrap();
+        assert!(storage.append(&[point("KBOS", 200, 11.0), point("KJFK", 200, 20.0)]).is_err());
+
+        assert_eq!(temperatures(&storage.load("KBOS").unwrap()), vec![(100, 10.0)]);
+        assert_eq!(fs::metadata(&segment).unwrap().len(), len_before);
+
+        // The rolled-back segment takes later appends without a gap.
+        storage.append(&[point("KBOS", 300, 12.0)]).unwrap();
+        drop(storage);
+        let storage = DiskStorage::open(&root, DiskStorageConfig::default()).unwrap();
+        assert_eq!(temperatures(&storage.load("KBOS").unwrap()), vec![(100, 10.0), (300, 12.0)]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reopen_finishes_a_compaction_and_discards_unpromoted_rewrites() {
+        let root = temp_root("compaction");
+        let storage = DiskStorage::open(&root, small_segments()).unwrap();
+        let original: Vec<WeatherDataPoint> = (0..10).map(|i| point("KJFK", i * 100, i as f64)).collect();
+        storage.append(&original).unwrap();
+        storage.append(&[point("KORD", 100, 5.0)]).unwrap();
+        drop(storage);
+
+        let encoded = |points: &[WeatherDataPoint]| -> Vec<u8> {
+            points.iter().flat_map(|p| encode_record(p).unwrap()).collect()
+        };
+
+        // Crash after the rewrite was promoted to .compact but before the old segments went away.
+        let jfk = root.join(encode_location_dir("KJFK"));
+        assert!(files_with_ext(&jfk, SEGMENT_EXT) > 1);
+        fs::write(segment_path(&jfk, 50, COMPACT_EXT), encoded(&[point("KJFK", 900, 9.5)])).unwrap();
+
+        // Crash before the rewrite was promoted at all: the old segments stay authoritative.
+        let ord = root.join(encode_location_dir("KORD"));
+        fs::write(segment_path(&ord, 2, TMP_EXT), encoded(&[point("KORD", 100, -1.0)])).unwrap();
+
+        let storage = DiskStorage::open(&root, small_segments()).unwrap();
+        assert_eq!(temperatures(&storage.load("KJFK").unwrap()), vec![(900, 9.5)]);
+        assert_eq!(temperatures(&storage.load("KORD").unwrap()), vec![(100, 5.0)]);
+        assert_eq!(files_with_ext(&jfk, SEGMENT_EXT), 1);
+        assert_eq!(files_with_ext(&jfk, COMPACT_EXT), 0);
+        assert_eq!(files_with_ext(&ord, TMP_EXT), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn failed_rewrite_rebuilds_the_index_from_disk() {
+        let root = temp_root("failed-rewrite");
+        let storage = DiskStorage::open(&root, small_segments()).unwrap();
+        let original: Vec<WeatherDataPoint> = (0..10).map(|i| point("KJFK", i * 100, i as f64)).collect();
+        storage.append(&original).unwrap();
+
+        // A directory in place of the newest segment makes removing it fail after older ones are gone.
+        let dir = root.join(encode_location_dir("KJFK"));
+        let last_id = storage.indexes.lock().unwrap()["KJFK"].segments.last().unwrap().id;
+        let last = segment_path(&dir, last_id, SEGMENT_EXT);
+        fs::remove_file(&last).unwrap();
+        fs::create_dir(&last).unwrap();
+
+        assert!(storage.rewrite("KJFK", &[point("KJFK", 500, 50.0)]).is_err());
+        // The index no longer points at the deleted segments, and writes wait for a reopen.
+        assert!(storage.load("KJFK").unwrap().is_empty());
+        assert!(storage.append(&[point("KJFK", 600, 60.0)]).is_err());
+        drop(storage);
+
+        fs::remove_dir(&last).unwrap();
+        let storage = DiskStorage::open(&root, small_segments()).unwrap();
+        assert_eq!(temperatures(&storage.load("KJFK").unwrap()), vec![(500, 50.0)]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn memory_and_disk_backed_processors_agree() {
+        let root = temp_root("backends");
+        let mut points: Vec<WeatherDataPoint> = (0..40)
+            .map(|i| point(if i % 3 == 0 { "KBOS" } else { "KJFK" }, 1_700_000_000 + (i % 17) * 600, i as f64))
+            .collect();
+        points.reverse();
+        points[5].humidity = f64::NAN;
+
+        let memory = DataProcessor::new();
+        let storage = Arc::new(DiskStorage::open(&root, small_segments()).unwrap());
+        let disk = DataProcessor::with_storage(storage).await.unwrap();
+        memory.add_batch_data(points[..25].to_vec()).await.unwrap();
+        memory.add_batch_data(points[25..].to_vec()).await.unwrap();
+        disk.add_batch_data(points[..25].to_vec()).await.unwrap();
+        disk.add_batch_data(points[25..].to_vec()).await.unwrap();
+        drop(disk);
+
+        let reopened = Arc::new(DiskStorage::open(&root, small_segments()).unwrap());
+        let disk = DataProcessor::with_storage(reopened).await.unwrap();
+        let summary = memory.get_location_summary().await;
+        assert_eq!(summary.get("KJFK"), Some(&17));
+        assert_eq!(summary, disk.get_location_summary().await);
+
+        let window = TimeWindow::new(i64::MIN, i64::MAX);
+        for location in ["KBOS", "KJFK"] {
+            let bits = |points: Vec<WeatherDataPoint>| -> Vec<(i64, u64, u64)> {
+                points.iter().map(|p| (p.timestamp, p.temperature.to_bits(), p.humidity.to_bits())).collect()
+            };
+            assert_eq!(
+                bits(memory.query_points(location, window).await),
+                bits(disk.query_points(location, window).await)
+            );
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
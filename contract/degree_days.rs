@@ -0,0 +1,307 @@
+use chrono::{Duration, NaiveDate};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_query::TimeWindow;
+pub use crate::units::TemperatureUnit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DegreeDayKind {
+    Heating,
+    Cooling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DailyTemperatureMethod {
+    Mean,
+    MaxMinAverage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MissingDayPolicy {
+    Exclude,
+    Interpolate,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeDayConfig {
+    pub base: f64,
+    pub unit: TemperatureUnit,
+    pub method: DailyTemperatureMethod,
+    pub timezone: Tz,
+    // A day counts only when samples fall in at least min_coverage of its expected intervals;
+    // None accepts any day with a single sample.
+    pub expected_interval_secs: Option<i64>,
+    pub min_coverage: f64,
+    pub missing: MissingDayPolicy,
+}
+
+impl DegreeDayConfig {
+    pub fn fahrenheit(timezone: Tz) -> Self {
+        Self {
+            base: 65.0,
+            unit: TemperatureUnit::Fahrenheit,
+            method: DailyTemperatureMethod::MaxMinAverage,
+            timezone,
+            expected_interval_secs: Some(3600),
+            min_coverage: 0.75,
+            missing: MissingDayPolicy::Exclude,
+        }
+    }
+
+    pub fn celsius(timezone: Tz) -> Self {
+        Self {
+            base: 18.0,
+            unit: TemperatureUnit::Celsius,
+            ..Self::fahrenheit(timezone)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DayStatus {
+    Observed,
+    Interpolated,
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeDay {
+    pub date: NaiveDate,
+    pub temperature: Option<f64>,
+    pub samples: usize,
+    pub status: DayStatus,
+    pub hdd: f64,
+    pub cdd: f64,
+    pub cumulative_hdd: f64,
+    pub cumulative_cdd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeDaySeries {
+    pub location: String,
+    pub base: f64,
+    pub unit: TemperatureUnit,
+    pub season_start: NaiveDate,
+    pub through: NaiveDate,
+    pub days: Vec<DegreeDay>,
+    pub total_hdd: f64,
+    pub total_cdd: f64,
+    pub missing_days: Vec<NaiveDate>,
+    pub interpolated_days: Vec<NaiveDate>,
+}
+
+impl DegreeDaySeries {
+    pub fn index(&self, kind: DegreeDayKind) -> f64 {
+        match kind {
+            DegreeDayKind::Heating => self.total_hdd,
+            DegreeDayKind::Cooling => self.total_cdd,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing_days.is_empty()
+    }
+
+    pub fn cumulative_through(&self, date: NaiveDate, kind: DegreeDayKind) -> Option<f64> {
+        self.days.iter().rev().find(|d| d.date <= date).map(|d| match kind {
+            DegreeDayKind::Heating => d.cumulative_hdd,
+            DegreeDayKind::Cooling => d.cumulative_cdd,
+        })
+    }
+}
+
+// Samples are (unix timestamp, temperature in °C); `through` is inclusive.
+pub fn compute_degree_days(
+    location: &str,
+    samples: &[(i64, f64)],
+    config: &DegreeDayConfig,
+    season_start: NaiveDate,
+    through: NaiveDate,
+) -> Result<DegreeDaySeries, Box<dyn std::error::Error>> {
+    if through < season_start {
+        return Err("Degree-day range ends before the season starts".into());
+    }
+
+    let mut sorted: Vec<(i64, f64)> = samples.iter().copied().filter(|(_, t)| t.is_finite()).collect();
+    sorted.sort_by_key(|(ts, _)| *ts);
+
+    let mut daily: Vec<(NaiveDate, Option<f64>, usize)> = Vec::new();
+    let mut date = season_start;
+    while date <= through {
+        let window = TimeWindow::calendar_day(date, config.timezone)
+            .ok_or_else(|| format!("{} does not exist in {}", date, config.timezone))?;
+        let begin = sorted.partition_point(|(ts, _)| *ts < window.start);
+        let end = sorted.partition_point(|(ts, _)| *ts < window.end);
+        let day = &sorted[begin..end];
+
+        daily.push((date, daily_temperature(day, &window, config), day.len()));
+        date += Duration::days(1);
+    }
+
+    let observed: Vec<Option<f64>> = daily.iter().map(|(_, t, _)| *t).collect();
+    let mut days = Vec::with_capacity(daily.len());
+    let mut missing_days = Vec::new();
+    let mut interpolated_days = Vec::new();
+    let (mut cumulative_hdd, mut cumulative_cdd) = (0.0, 0.0);
+
+    for (i, (date, temperature, samples)) in daily.into_iter().enumerate() {
+        let (temperature, status) = match temperature {
+            Some(t) => (Some(t), DayStatus::Observed),
+            None => match config.missing {
+                MissingDayPolicy::Fail => return Err(format!("No complete temperature record for {} on {}", location, date).into()),
+                MissingDayPolicy::Exclude => (None, DayStatus::Missing),
+                MissingDayPolicy::Interpolate => match interpolate_day(&observed, i) {
+                    Some(t) => (Some(t), DayStatus::Interpolated),
+                    None => (None, DayStatus::Missing),
+                },
+            },
+        };
+
+        match status {
+            DayStatus::Missing => missing_days.push(date),
+            DayStatus::Interpolated => interpolated_days.push(date),
+            DayStatus::Observed => {}
+        }
+
+        let (hdd, cdd) = match temperature {
+            Some(t) => ((config.base - t).max(0.0), (t - config.base).max(0.0)),
+            None => (0.0, 0.0),
+        };
+        cumulative_hdd += hdd;
+        cumulative_cdd += cdd;
+
+        days.push(DegreeDay {
+            date,
+            temperature,
+            samples,
+            status,
+            hdd,
+            cdd,
+            cumulative_hdd,
+            cumulative_cdd,
+        });
+    }
+
+    Ok(DegreeDaySeries {
+        location: location.to_string(),
+        base: config.base,
+        unit: config.unit,
+        season_start,
+        through,
+        days,
+        total_hdd: cumulative_hdd,
+        total_cdd: cumulative_cdd,
+        missing_days,
+        interpolated_days,
+    })
+}
+
+fn daily_temperature(samples: &[(i64, f64)], window: &TimeWindow, config: &DegreeDayConfig) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    // Coverage counts the intervals holding a sample, so a burst of readings cannot stand in for a day.
+    if let Some(interval) = config.expected_interval_secs.filter(|i| *i > 0) {
+        let expected = (window.duration_secs() / interval).max(1) as f64;
+        let mut covered: Vec<i64> = samples.iter().map(|(ts, _)| (ts - window.start) / interval).collect();
+        covered.dedup();
+        if (covered.len() as f64) < (expected * config.min_coverage).ceil() {
+            return None;
+        }
+    }
+
+    let values: Vec<f64> = samples.iter().map(|(_, t)| config.unit.from_canonical(*t)).collect();
+
+    match config.method {
+        DailyTemperatureMethod::Mean => Some(values.iter().sum::<f64>() / values.len() as f64),
+        DailyTemperatureMethod::MaxMinAverage => {
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            Some((max + min) / 2.0)
+        }
+    }
+}
+
+// Linear between the nearest observed days; a day at either edge of the range takes its only neighbour.
+fn interpolate_day(observed: &[Option<f64>], index: usize) -> Option<f64> {
+    let before = (0..index).rev().find_map(|i| observed[i].map(|t| (i, t)));
+    let after = (index + 1..observed.len()).find_map(|i| observed[i].map(|t| (i, t)));
+
+    match (before, after) {
+        (Some((i0, t0)), Some((i1, t1))) => Some(t0 + (t1 - t0) * (index - i0) as f64 / (i1 - i0) as f64),
+        (Some((_, t)), None) | (None, Some((_, t))) => Some(t),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIDNIGHT: i64 = 1_704_067_200; // 2024-01-01T00:00:00Z
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    // Hourly samples at a constant temperature for each listed day of January 2024 (UTC).
+    fn hourly(days: &[(u32, f64)]) -> Vec<(i64, f64)> {
+        days.iter()
+            .flat_map(|(day, temperature)| (0..24).map(move |hour| (MIDNIGHT + (*day as i64 - 1) * 86_400 + hour * 3600, *temperature)))
+            .collect()
+    }
+
+    #[test]
+    fn heating_and_cooling_accumulate_against_the_base() {
+        let config = DegreeDayConfig::celsius(chrono_tz::UTC);
+        let series = compute_degree_days("NYC", &hourly(&[(1, 10.0), (2, 20.0)]), &config, date(1), date(2)).unwrap();
+
+        assert_eq!((series.days[0].hdd, series.days[0].cdd), (8.0, 0.0));
+        assert_eq!((series.days[1].hdd, series.days[1].cdd), (0.0, 2.0));
+        assert_eq!((series.total_hdd, series.total_cdd), (8.0, 2.0));
+        assert_eq!(series.cumulative_through(date(1), DegreeDayKind::Heating), Some(8.0));
+        assert!(series.is_complete());
+    }
+
+    #[test]
+    fn sparse_days_fail_coverage() {
+        let config = DegreeDayConfig::celsius(chrono_tz::UTC);
+        // A burst of readings inside one hour covers a single interval.
+        let burst: Vec<(i64, f64)> = (0..30).map(|minute| (MIDNIGHT + minute * 60, 10.0)).collect();
+        let series = compute_degree_days("NYC", &burst, &config, date(1), date(1)).unwrap();
+        assert_eq!(series.missing_days, vec![date(1)]);
+        assert_eq!(series.days[0].samples, 30);
+    }
+
+    #[test]
+    fn missing_days_follow_the_policy() {
+        let samples = hourly(&[(1, 10.0), (3, 14.0)]);
+        let mut config = DegreeDayConfig::celsius(chrono_tz::UTC);
+
+        let excluded = compute_degree_days("NYC", &samples, &config, date(1), date(3)).unwrap();
+        assert_eq!(excluded.missing_days, vec![date(2)]);
+        assert_eq!(excluded.total_hdd, 8.0 + 4.0);
+
+        config.missing = MissingDayPolicy::Interpolate;
+        let interpolated = compute_degree_days("NYC", &samples, &config, date(1), date(3)).unwrap();
+        assert_eq!(interpolated.interpolated_days, vec![date(2)]);
+        assert_eq!(interpolated.days[1].status, DayStatus::Interpolated);
+        assert_eq!(interpolated.days[1].temperature, Some(12.0));
+        assert!(interpolated.is_complete());
+
+        config.missing = MissingDayPolicy::Fail;
+        assert!(compute_degree_days("NYC", &samples, &config, date(1), date(3)).is_err());
+    }
+
+    #[test]
+    fn days_around_a_skipped_midnight_exist() {
+        let config = DegreeDayConfig::celsius(chrono_tz::America::Santiago);
+        let start = NaiveDate::from_ymd_opt(2022, 9, 10).unwrap();
+        let through = NaiveDate::from_ymd_opt(2022, 9, 12).unwrap();
+        let series = compute_degree_days("SCL", &[], &config, start, through).unwrap();
+        assert_eq!(series.missing_days.len(), 3);
+    }
+}
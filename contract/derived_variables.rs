@@ -0,0 +1,188 @@
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::resampling::wind_components;
+use crate::units::TemperatureUnit;
+
+// Inputs follow WeatherDataPoint units: °C, % relative humidity, hPa, m/s, degrees.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DerivedVariable {
+    DewPoint,
+    AbsoluteHumidity,
+    HeatIndex,
+    WindChill,
+    ApparentTemperature,
+    SeaLevelPressure,
+    WindU,
+    WindV,
+}
+
+impl DerivedVariable {
+    pub const ALL: [DerivedVariable; 8] = [
+        DerivedVariable::DewPoint,
+        DerivedVariable::AbsoluteHumidity,
+        DerivedVariable::HeatIndex,
+        DerivedVariable::WindChill,
+        DerivedVariable::ApparentTemperature,
+        DerivedVariable::SeaLevelPressure,
+        DerivedVariable::WindU,
+        DerivedVariable::WindV,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DerivedVariable::DewPoint => "dew_point",
+            DerivedVariable::AbsoluteHumidity => "absolute_humidity",
+            DerivedVariable::HeatIndex => "heat_index",
+            DerivedVariable::WindChill => "wind_chill",
+            DerivedVariable::ApparentTemperature => "apparent_temperature",
+            DerivedVariable::SeaLevelPressure => "sea_level_pressure",
+            DerivedVariable::WindU => "wind_u",
+            DerivedVariable::WindV => "wind_v",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DerivedValues {
+    pub dew_point: f64,
+    pub absolute_humidity: f64,
+    pub heat_index: f64,
+    pub wind_chill: f64,
+    pub apparent_temperature: f64,
+    pub sea_level_pressure: Option<f64>,
+    pub wind_u: f64,
+    pub wind_v: f64,
+}
+
+impl DerivedValues {
+    pub fn compute(point: &WeatherDataPoint, elevation_m: Option<f64>) -> Self {
+        Self::from_values(
+            point.temperature,
+            point.humidity,
+            point.pressure,
+            point.wind_speed,
+            point.wind_direction,
+            elevation_m,
+        )
+    }
+
+    pub fn from_values(
+        temperature: f64,
+        humidity: f64,
+        pressure: f64,
+        wind_speed: f64,
+        wind_direction: f64,
+        elevation_m: Option<f64>,
+    ) -> Self {
+        let (wind_u, wind_v) = wind_components(wind_speed, wind_direction);
+
+        Self {
+            dew_point: dew_point(temperature, humidity),
+            absolute_humidity: absolute_humidity(temperature, humidity),
+            heat_index: heat_index(temperature, humidity),
+            wind_chill: wind_chill(temperature, wind_speed),
+            apparent_temperature: apparent_temperature(temperature, humidity, wind_speed),
+            sea_level_pressure: elevation_m.map(|h| sea_level_pressure(pressure, temperature, h)),
+            wind_u,
+            wind_v,
+        }
+    }
+
+    pub fn get(&self, variable: DerivedVariable) -> Option<f64> {
+        match variable {
+            DerivedVariable::DewPoint => Some(self.dew_point),
+            DerivedVariable::AbsoluteHumidity => Some(self.absolute_humidity),
+            DerivedVariable::HeatIndex => Some(self.heat_index),
+            DerivedVariable::WindChill => Some(self.wind_chill),
+            DerivedVariable::ApparentTemperature => Some(self.apparent_temperature),
+            DerivedVariable::SeaLevelPressure => self.sea_level_pressure,
+            DerivedVariable::WindU => Some(self.wind_u),
+            DerivedVariable::WindV => Some(self.wind_v),
+        }
+    }
+}
+
+// Magnus formula with the Alduchov-Eskridge coefficients, in hPa.
+pub fn saturation_vapor_pressure(temperature_c: f64) -> f64 {
+    6.1094 * (17.625 * temperature_c / (temperature_c + 243.04)).exp()
+}
+
+pub fn dew_point(temperature_c: f64, relative_humidity: f64) -> f64 {
+    let rh = relative_humidity.clamp(0.1, 100.0);
+    let gamma = (rh / 100.0).ln() + 17.625 * temperature_c / (temperature_c + 243.04);
+    243.04 * gamma / (17.625 - gamma)
+}
+
+pub fn relative_humidity(temperature_c: f64, dew_point_c: f64) -> f64 {
+    (100.0 * saturation_vapor_pressure(dew_point_c) / saturation_vapor_pressure(temperature_c)).clamp(0.0, 100.0)
+}
+
+// Grams of water vapour per cubic metre of air.
+pub fn absolute_humidity(temperature_c: f64, relative_humidity: f64) -> f64 {
+    let vapor_pressure_hpa = saturation_vapor_pressure(temperature_c) * relative_humidity / 100.0;
+    let vapor_pressure_pa = vapor_pressure_hpa * 100.0;
+    vapor_pressure_pa / (461.5 * (temperature_c + 273.15)) * 1000.0
+}
+
+// NWS heat index: Steadman's simple form below 80 °F, Rothfusz regression with adjustments above.
+pub fn heat_index(temperature_c: f64, relative_humidity: f64) -> f64 {
+    let t = TemperatureUnit::Fahrenheit.from_canonical(temperature_c);
+    let rh = relative_humidity;
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (simple + t) / 2.0 < 80.0 {
+        return TemperatureUnit::Fahrenheit.to_canonical(simple);
+    }
+
+    let mut hi = -42.379 + 2.04901523 * t + 10.14333127 * rh
+        - 0.22475541 * t * rh
+        - 6.83783e-3 * t * t
+        - 5.481717e-2 * rh * rh
+        + 1.22874e-3 * t * t * rh
+        + 8.5282e-4 * t * rh * rh
+        - 1.99e-6 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+    }
+
+    TemperatureUnit::Fahrenheit.to_canonical(hi)
+}
+
+// NWS/Environment Canada wind chill; outside its domain the air temperature is returned.
+pub fn wind_chill(temperature_c: f64, wind_speed_ms: f64) -> f64 {
+    let wind_kmh = wind_speed_ms * 3.6;
+    if temperature_c > 10.0 || wind_kmh <= 4.8 {
+        return temperature_c;
+    }
+
+    let v = wind_kmh.powf(0.16);
+    13.12 + 0.6215 * temperature_c - 11.37 * v + 0.3965 * temperature_c * v
+}
+
+// "Feels like" as published by NWS: heat index when warm, wind chill when cold, air temperature otherwise.
+pub fn apparent_temperature(temperature_c: f64, relative_humidity: f64, wind_speed_ms: f64) -> f64 {
+    if TemperatureUnit::Fahrenheit.from_canonical(temperature_c) >= 80.0 {
+        heat_index(temperature_c, relative_humidity)
+    } else if temperature_c <= 10.0 {
+        wind_chill(temperature_c, wind_speed_ms)
+    } else {
+        temperature_c
+    }
+}
+
+// Hypsometric reduction using the standard lapse rate.
+pub fn sea_level_pressure(station_pressure_hpa: f64, temperature_c: f64, elevation_m: f64) -> f64 {
+    let lapse = 0.0065 * elevation_m;
+    station_pressure_hpa * (1.0 - lapse / (temperature_c + lapse + 273.15)).powf(-5.257)
+}
+
+// Inverse of sea_level_pressure, for sources that only report reduced pressure.
+pub fn station_pressure(sea_level_pressure_hpa: f64, temperature_c: f64, elevation_m: f64) -> f64 {
+    let lapse = 0.0065 * elevation_m;
+    sea_level_pressure_hpa * (1.0 - lapse / (temperature_c + lapse + 273.15)).powf(5.257)
+}
@@ -0,0 +1,838 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_import::csv_field;
+use crate::data_processor::ExportFormat;
+use crate::data_query::WeatherVariable;
+use crate::station_registry::solve_linear;
+
+const DAYS_PER_YEAR: f64 = 365.25;
+const MIN_FIT_SAMPLES: usize = 10;
+// Below this the Gumbel / exponential limit is used to avoid dividing by a vanishing shape.
+const SHAPE_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DailyStatistic {
+    Max,
+    Min,
+    Mean,
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tail {
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockSize {
+    Year,
+    Month,
+}
+
+impl BlockSize {
+    pub fn blocks_per_year(&self) -> f64 {
+        match self {
+            BlockSize::Year => 1.0,
+            BlockSize::Month => 12.0,
+        }
+    }
+
+    fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            BlockSize::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+            BlockSize::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap(),
+        }
+    }
+
+    fn days_in(&self, start: NaiveDate) -> i64 {
+        let next = match self {
+            BlockSize::Year => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1),
+            BlockSize::Month if start.month() == 12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1),
+            BlockSize::Month => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1),
+        };
+        (next.unwrap() - start).num_days()
+    }
+}
+
+// For the lower tail, Quantile(0.95) selects the coldest 5% of days and Value(v) means "below v".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ThresholdChoice {
+    Quantile(f64),
+    Value(f64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtremeConfig {
+    pub timezone: Tz,
+    pub daily: DailyStatistic,
+    pub tail: Tail,
+    pub min_hours_per_day: usize,
+    pub block: BlockSize,
+    pub min_block_coverage: f64,
+    pub threshold: ThresholdChoice,
+    pub run_length_days: i64,
+}
+
+impl ExtremeConfig {
+    pub fn new(timezone: Tz, daily: DailyStatistic) -> Self {
+        Self {
+            timezone,
+            daily,
+            tail: if daily == DailyStatistic::Min { Tail::Lower } else { Tail::Upper },
+            min_hours_per_day: 18,
+            block: BlockSize::Year,
+            min_block_coverage: 0.8,
+            threshold: ThresholdChoice::Quantile(0.95),
+            run_length_days: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DailyValue {
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlockMaximum {
+    pub block_start: NaiveDate,
+    pub date: NaiveDate,
+    pub value: f64,
+    pub days: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExtremeDistribution {
+    Gev { location: f64, scale: f64, shape: f64, blocks_per_year: f64 },
+    Gpd { threshold: f64, scale: f64, shape: f64, exceedances_per_year: f64 },
+}
+
+impl ExtremeDistribution {
+    // The parameterisation the likelihood is optimised in; the covariance matrix refers to it.
+    fn parameters(&self) -> Vec<f64> {
+        match *self {
+            ExtremeDistribution::Gev { location, scale, shape, .. } => vec![location, scale.ln(), shape],
+            ExtremeDistribution::Gpd { scale, shape, .. } => vec![scale.ln(), shape],
+        }
+    }
+
+    fn with_parameters(&self, parameters: &[f64]) -> Self {
+        match *self {
+            ExtremeDistribution::Gev { blocks_per_year, .. } => ExtremeDistribution::Gev {
+                location: parameters[0],
+                scale: parameters[1].exp(),
+                shape: parameters[2],
+                blocks_per_year,
+            },
+            ExtremeDistribution::Gpd { threshold, exceedances_per_year, .. } => ExtremeDistribution::Gpd {
+                threshold,
+                scale: parameters[0].exp(),
+                shape: parameters[1],
+                exceedances_per_year,
+            },
+        }
+    }
+
+    // Level exceeded on average once every `period_years`, on the fitted (upper-tail) scale.
+    fn level(&self, period_years: f64) -> Option<f64> {
+        match *self {
+            ExtremeDistribution::Gev { location, scale, shape, blocks_per_year } => {
+                let blocks = period_years * blocks_per_year;
+                if blocks <= 1.0 {
+                    return None;
+                }
+                let y = -(1.0 - 1.0 / blocks).ln();
+                Some(if shape.abs() < SHAPE_EPSILON {
+                    location - scale * y.ln()
+                } else {
+                    location + scale / shape * (y.powf(-shape) - 1.0)
+                })
+            }
+            ExtremeDistribution::Gpd { threshold, scale, shape, exceedances_per_year } => {
+                let m = period_years * exceedances_per_year;
+                if m <= 1.0 {
+                    return None;
+                }
+                Some(if shape.abs() < SHAPE_EPSILON {
+                    threshold + scale * m.ln()
+                } else {
+                    threshold + scale / shape * (m.powf(shape) - 1.0)
+                })
+            }
+        }
+    }
+
+    // None when the level is outside what the model describes: beyond a bounded tail's endpoint, or below a POT threshold.
+    fn period(&self, level: f64) -> Option<f64> {
+        let rate_per_year = match *self {
+            ExtremeDistribution::Gev { location, scale, shape, blocks_per_year } => {
+                let y = (level - location) / scale;
+                let reduced = if shape.abs() < SHAPE_EPSILON {
+                    (-y).exp()
+                } else {
+                    let t = 1.0 + shape * y;
+                    if t <= 0.0 {
+                        return None;
+                    }
+                    t.powf(-1.0 / shape)
+                };
+                (1.0 - (-reduced).exp()) * blocks_per_year
+            }
+            ExtremeDistribution::Gpd { threshold, scale, shape, exceedances_per_year } => {
+                if level < threshold {
+                    return None;
+                }
+                let y = (level - threshold) / scale;
+                let survival = if shape.abs() < SHAPE_EPSILON {
+                    (-y).exp()
+                } else {
+                    let t = 1.0 + shape * y;
+                    if t <= 0.0 {
+                        return None;
+                    }
+                    t.powf(-1.0 / shape)
+                };
+                survival * exceedances_per_year
+            }
+        };
+
+        if rate_per_year > 0.0 { Some(1.0 / rate_per_year) } else { None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReturnLevel {
+    pub period_years: f64,
+    pub level: f64,
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReturnPeriod {
+    pub level: f64,
+    pub period_years: f64,
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtremeFit {
+    pub location: String,
+    pub variable: WeatherVariable,
+    pub daily: DailyStatistic,
+    pub tail: Tail,
+    pub distribution: ExtremeDistribution,
+    pub sample_size: usize,
+    pub years: f64,
+    pub negative_log_likelihood: f64,
+    // Inverse observed information in (location, ln scale, shape) or (ln scale, shape) order.
+    pub covariance: Option<Vec<Vec<f64>>>,
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+}
+
+impl ExtremeFit {
+    // Delta-method bounds at the given two-sided confidence, e.g. 0.95.
+    pub fn return_level(&self, period_years: f64, confidence: f64) -> Option<ReturnLevel> {
+        let level = self.distribution.level(period_years)?;
+        let spread = self.delta_spread(confidence, |d| d.level(period_years));
+        let (lower, upper) = match spread {
+            Some(spread) => (Some(level - spread), Some(level + spread)),
+            None => (None, None),
+        };
+
+        Some(match self.tail {
+            Tail::Upper => ReturnLevel { period_years, level, lower, upper },
+            Tail::Lower => ReturnLevel { period_years, level: -level, lower: upper.map(|u| -u), upper: lower.map(|l| -l) },
+        })
+    }
+
+    // Bounds are computed on ln(period) so they stay positive.
+    pub fn return_period(&self, level: f64, confidence: f64) -> Option<ReturnPeriod> {
+        let fitted_level = if self.tail == Tail::Lower { -level } else { level };
+        let period_years = self.distribution.period(fitted_level)?;
+        let spread = self.delta_spread(confidence, |d| d.period(fitted_level).map(f64::ln));
+
+        Some(ReturnPeriod {
+            level,
+            period_years,
+            lower: spread.map(|s| (period_years.ln() - s).exp()),
+            upper: spread.map(|s| (period_years.ln() + s).exp()),
+        })
+    }
+
+    pub fn report(&self, periods_years: &[f64], confidence: f64) -> ExtremeReport {
+        ExtremeReport {
+            fit: self.clone(),
+            confidence,
+            return_levels: periods_years.iter().filter_map(|p| self.return_level(*p, confidence)).collect(),
+        }
+    }
+
+    fn delta_spread<F: Fn(&ExtremeDistribution) -> Option<f64>>(&self, confidence: f64, quantity: F) -> Option<f64> {
+        let covariance = self.covariance.as_ref()?;
+        let parameters = self.distribution.parameters();
+        let gradient = numeric_gradient(&parameters, |p| quantity(&self.distribution.with_parameters(p)))?;
+
+        let variance: f64 = (0..parameters.len())
+            .flat_map(|i| (0..parameters.len()).map(move |j| (i, j)))
+            .map(|(i, j)| gradient[i] * covariance[i][j] * gradient[j])
+            .sum();
+        if !variance.is_finite() || variance < 0.0 {
+            return None;
+        }
+        Some(normal_quantile(0.5 + confidence.clamp(0.0, 0.999_999) / 2.0) * variance.sqrt())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtremeReport {
+    pub fit: ExtremeFit,
+    pub confidence: f64,
+    pub return_levels: Vec<ReturnLevel>,
+}
+
+impl ExtremeReport {
+    pub fn export(&self, format: ExportFormat) -> Option<String> {
+        match format {
+            ExportFormat::Json => serde_json::to_string(self).ok(),
+            ExportFormat::Csv => Some(self.to_csv()),
+            ExportFormat::Parquet | ExportFormat::ArrowIpc => None,
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let fit = &self.fit;
+        let (model, location, scale, shape, threshold, rate) = match fit.distribution {
+            ExtremeDistribution::Gev { location, scale, shape, blocks_per_year } => ("gev", Some(location), scale, shape, None, blocks_per_year),
+            ExtremeDistribution::Gpd { threshold, scale, shape, exceedances_per_year } => ("gpd", None, scale, shape, Some(threshold), exceedances_per_year),
+        };
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+        let mut csv = String::from(
+            "location,variable,daily,tail,model,location_param,scale,shape,threshold,events_per_year,\
+             sample_size,years,confidence,period_years,level,lower,upper\n",
+        );
+        for level in &self.return_levels {
+            csv.push_str(&format!(
+                "{},{:?},{:?},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                csv_field(&fit.location, ','),
+                fit.variable,
+                fit.daily,
+                fit.tail,
+                model,
+                optional(location),
+                scale,
+                shape,
+                optional(threshold),
+                rate,
+                fit.sample_size,
+                fit.years,
+                self.confidence,
+                level.period_years,
+                level.level,
+                optional(level.lower),
+                optional(level.upper)
+            ));
+        }
+        csv
+    }
+}
+
+// Aggregates samples to local calendar days, dropping days with too few distinct hours observed.
+pub fn daily_values(samples: &[(i64, f64)], config: &ExtremeConfig) -> Vec<DailyValue> {
+    let mut days: BTreeMap<NaiveDate, (Vec<f64>, [bool; 24])> = BTreeMap::new();
+    for (timestamp, value) in samples {
+        if !value.is_finite() {
+            continue;
+        }
+        let local = match config.timezone.timestamp_opt(*timestamp, 0).single() {
+            Some(local) => local,
+            None => continue,
+        };
+        let entry = days.entry(local.date_naive()).or_insert_with(|| (Vec::new(), [false; 24]));
+        entry.0.push(*value);
+        entry.1[local.hour() as usize] = true;
+    }
+
+    days.into_iter()
+        .filter(|(_, (_, hours))| hours.iter().filter(|h| **h).count() >= config.min_hours_per_day)
+        .map(|(date, (values, _))| {
+            let value = match config.daily {
+                DailyStatistic::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                DailyStatistic::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+                DailyStatistic::Mean => values.iter().sum::<f64>() / values.len() as f64,
+                DailyStatistic::Sum => values.iter().sum(),
+            };
+            DailyValue { date, value }
+        })
+        .collect()
+}
+
+// Maxima (or minima for the lower tail) of blocks with enough valid days.
+pub fn block_maxima(days: &[DailyValue], config: &ExtremeConfig) -> Vec<BlockMaximum> {
+    let mut blocks: BTreeMap<NaiveDate, Vec<DailyValue>> = BTreeMap::new();
+    for day in days {
+        blocks.entry(config.block.start_of(day.date)).or_default().push(*day);
+    }
+
+    blocks
+        .into_iter()
+        .filter(|(start, days)| days.len() as f64 >= config.block.days_in(*start) as f64 * config.min_block_coverage)
+        .filter_map(|(block_start, days)| {
+            let extreme = days.iter().max_by(|a, b| {
+                let (a, b) = (oriented(a.value, config.tail), oriented(b.value, config.tail));
+                a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+            })?;
+            Some(BlockMaximum { block_start, date: extreme.date, value: extreme.value, days: days.len() })
+        })
+        .collect()
+}
+
+pub fn fit_gev(
+    location: &str,
+    variable: WeatherVariable,
+    samples: &[(i64, f64)],
+    config: &ExtremeConfig,
+) -> Result<ExtremeFit, Box<dyn std::error::Error>> {
+    check_variable(variable)?;
+    let days = daily_values(samples, config);
+    let maxima = block_maxima(&days, config);
+    if maxima.len() < MIN_FIT_SAMPLES {
+        return Err(format!("Need at least {} complete blocks for a GEV fit, found {}", MIN_FIT_SAMPLES, maxima.len()).into());
+    }
+
+    let data: Vec<f64> = maxima.iter().map(|m| oriented(m.value, config.tail)).collect();
+    let (mean, sd) = mean_and_sd(&data);
+    // Gumbel method-of-moments start.
+    let scale = (sd * 6f64.sqrt() / std::f64::consts::PI).max(1e-6);
+    let start = vec![mean - 0.5772 * scale, scale.ln(), 0.1];
+
+    let nll = |p: &[f64]| gev_nll(&data, p);
+    let parameters = nelder_mead(&nll, start, &[scale * 0.5, 0.2, 0.1]);
+    let template = ExtremeDistribution::Gev { location: 0.0, scale: 1.0, shape: 0.0, blocks_per_year: config.block.blocks_per_year() };
+
+    finish_fit(location, variable, config, &days, template.with_parameters(&parameters), data.len(), nll)
+}
+
+pub fn fit_gpd(
+    location: &str,
+    variable: WeatherVariable,
+    samples: &[(i64, f64)],
+    config: &ExtremeConfig,
+) -> Result<ExtremeFit, Box<dyn std::error::Error>> {
+    check_variable(variable)?;
+    let days = daily_values(samples, config);
+    if days.is_empty() {
+        return Err("No complete days to fit".into());
+    }
+
+    let mut sorted: Vec<f64> = days.iter().map(|d| oriented(d.value, config.tail)).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let threshold = match config.threshold {
+        ThresholdChoice::Quantile(q) => sorted[((sorted.len() - 1) as f64 * q.clamp(0.0, 1.0)).round() as usize],
+        ThresholdChoice::Value(v) => oriented(v, config.tail),
+    };
+
+    let excesses: Vec<f64> = decluster(&days, threshold, config).into_iter().map(|peak| peak - threshold).collect();
+    if excesses.len() < MIN_FIT_SAMPLES {
+        return Err(format!("Need at least {} independent exceedances for a GPD fit, found {}", MIN_FIT_SAMPLES, excesses.len()).into());
+    }
+
+    let years = days.len() as f64 / DAYS_PER_YEAR;
+    let mean_excess = excesses.iter().sum::<f64>() / excesses.len() as f64;
+    let start = vec![mean_excess.max(1e-6).ln(), 0.1];
+
+    let nll = |p: &[f64]| gpd_nll(&excesses, p);
+    let parameters = nelder_mead(&nll, start, &[0.2, 0.1]);
+    let template = ExtremeDistribution::Gpd { threshold, scale: 1.0, shape: 0.0, exceedances_per_year: excesses.len() as f64 / years };
+
+    finish_fit(location, variable, config, &days, template.with_parameters(&parameters), excesses.len(), nll)
+}
+
+fn finish_fit<F: Fn(&[f64]) -> f64>(
+    location: &str,
+    variable: WeatherVariable,
+    config: &ExtremeConfig,
+    days: &[DailyValue],
+    distribution: ExtremeDistribution,
+    sample_size: usize,
+    nll: F,
+) -> Result<ExtremeFit, Box<dyn std::error::Error>> {
+    let parameters = distribution.parameters();
+    let negative_log_likelihood = nll(&parameters);
+    if !negative_log_likelihood.is_finite() {
+        return Err("Extreme-value fit did not converge".into());
+    }
+
+    Ok(ExtremeFit {
+        location: location.to_string(),
+        variable,
+        daily: config.daily,
+        tail: config.tail,
+        distribution,
+        sample_size,
+        years: days.len() as f64 / DAYS_PER_YEAR,
+        negative_log_likelihood,
+        covariance: covariance(&nll, &parameters),
+        first_date: days.first().map(|d| d.date).ok_or("No complete days to fit")?,
+        last_date: days.last().map(|d| d.date).ok_or("No complete days to fit")?,
+    })
+}
+
+fn check_variable(variable: WeatherVariable) -> Result<(), Box<dyn std::error::Error>> {
+    if variable == WeatherVariable::WindDirection {
+        return Err("Extreme-value analysis is not defined for wind direction".into());
+    }
+    Ok(())
+}
+
+// Everything is fitted as an upper tail; lower-tail values are negated on the way in and out.
+fn oriented(value: f64, tail: Tail) -> f64 {
+    match tail {
+        Tail::Upper => value,
+        Tail::Lower => -value,
+    }
+}
+
+// Runs declustering: a cluster ends after `run_length_days` days at or below the threshold, or a gap of that length.
+fn decluster(days: &[DailyValue], threshold: f64, config: &ExtremeConfig) -> Vec<f64> {
+    let mut peaks = Vec::new();
+    let mut current: Option<(NaiveDate, f64)> = None;
+
+    for day in days {
+        let value = oriented(day.value, config.tail);
+        if let Some((last_date, peak)) = current {
+            if (day.date - last_date).num_days() > config.run_length_days {
+                peaks.push(peak);
+                current = None;
+            }
+        }
+        if value > threshold {
+            current = Some(match current {
+                Some((_, peak)) => (day.date, peak.max(value)),
+                None => (day.date, value),
+            });
+        }
+    }
+    if let Some((_, peak)) = current {
+        peaks.push(peak);
+    }
+    peaks
+}
+
+// Shapes outside (-1, 1) give an unbounded likelihood or an infinite mean; they are excluded.
+fn gev_nll(data: &[f64], p: &[f64]) -> f64 {
+    let (location, scale, shape) = (p[0], p[1].exp(), p[2]);
+    if shape.abs() >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut total = data.len() as f64 * scale.ln();
+    for z in data {
+        let y = (z - location) / scale;
+        if shape.abs() < SHAPE_EPSILON {
+            total += y + (-y).exp();
+        } else {
+            let t = 1.0 + shape * y;
+            if t <= 0.0 {
+                return f64::INFINITY;
+            }
+            total += (1.0 + 1.0 / shape) * t.ln() + t.powf(-1.0 / shape);
+        }
+    }
+    total
+}
+
+fn gpd_nll(excesses: &[f64], p: &[f64]) -> f64 {
+    let (scale, shape) = (p[0].exp(), p[1]);
+    if shape.abs() >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut total = excesses.len() as f64 * scale.ln();
+    for y in excesses {
+        if shape.abs() < SHAPE_EPSILON {
+            total += y / scale;
+        } else {
+            let t = 1.0 + shape * y / scale;
+            if t <= 0.0 {
+                return f64::INFINITY;
+            }
+            total += (1.0 + 1.0 / shape) * t.ln();
+        }
+    }
+    total
+}
+
+fn nelder_mead<F: Fn(&[f64]) -> f64>(f: &F, start: Vec<f64>, steps: &[f64]) -> Vec<f64> {
+    let n = start.len();
+    let evaluate = |p: &[f64]| {
+        let value = f(p);
+        if value.is_nan() { f64::INFINITY } else { value }
+    };
+
+    let mut simplex: Vec<(Vec<f64>, f64)> = vec![(start.clone(), evaluate(&start))];
+    for (i, step) in steps.iter().enumerate() {
+        let mut vertex = start.clone();
+        vertex[i] += step;
+        let value = evaluate(&vertex);
+        simplex.push((vertex, value));
+    }
+
+    for _ in 0..1000 * n {
+        simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        if best.is_finite() && (worst - best).abs() <= 1e-10 * (1.0 + best.abs()) {
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..n).map(|j| simplex[..n].iter().map(|(v, _)| v[j]).sum::<f64>() / n as f64).collect();
+        let towards = |factor: f64| -> Vec<f64> {
+            centroid.iter().zip(simplex[n].0.iter()).map(|(c, w)| c + factor * (c - w)).collect()
+        };
+
+        let reflected = towards(1.0);
+        let reflected_value = evaluate(&reflected);
+        if reflected_value < best {
+            let expanded = towards(2.0);
+            let expanded_value = evaluate(&expanded);
+            simplex[n] = if expanded_value < reflected_value { (expanded, expanded_value) } else { (reflected, reflected_value) };
+            continue;
+        }
+        if reflected_value < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_value);
+            continue;
+        }
+
+        let contracted = towards(-0.5);
+        let contracted_value = evaluate(&contracted);
+        if contracted_value < worst {
+            simplex[n] = (contracted, contracted_value);
+            continue;
+        }
+
+        let anchor = simplex[0].0.clone();
+        for vertex in simplex.iter_mut().skip(1) {
+            let shrunk: Vec<f64> = anchor.iter().zip(vertex.0.iter()).map(|(a, v)| a + 0.5 * (v - a)).collect();
+            let value = evaluate(&shrunk);
+            *vertex = (shrunk, value);
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    simplex.swap_remove(0).0
+}
+
+fn step_for(value: f64) -> f64 {
+    1e-4 * value.abs().max(1.0)
+}
+
+fn numeric_gradient<F: Fn(&[f64]) -> Option<f64>>(parameters: &[f64], f: F) -> Option<Vec<f64>> {
+    (0..parameters.len())
+        .map(|i| {
+            let h = step_for(parameters[i]);
+            let (mut up, mut down) = (parameters.to_vec(), parameters.to_vec());
+            up[i] += h;
+            down[i] -= h;
+            Some((f(&up)? - f(&down)?) / (2.0 * h))
+        })
+        .collect()
+}
+
+// Inverse of the numerically differentiated observed information; None when it is not positive definite.
+fn covariance<F: Fn(&[f64]) -> f64>(nll: &F, parameters: &[f64]) -> Option<Vec<Vec<f64>>> {
+    let n = parameters.len();
+    let mut hessian = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            let (hi, hj) = (step_for(parameters[i]), step_for(parameters[j]));
+            let at = |di: f64, dj: f64| {
+                let mut p = parameters.to_vec();
+                p[i] += di;
+                p[j] += dj;
+                nll(&p)
+            };
+            hessian[i][j] = (at(hi, hj) - at(hi, -hj) - at(-hi, hj) + at(-hi, -hj)) / (4.0 * hi * hj);
+        }
+    }
+
+    // The Hessian is symmetric, so its inverse can be assembled from solved columns as rows.
+    let inverse: Vec<Vec<f64>> = (0..n)
+        .map(|column| {
+            let augmented: Vec<Vec<f64>> = hessian
+                .iter()
+                .enumerate()
+                .map(|(row, values)| {
+                    let mut values = values.clone();
+                    values.push(if row == column { 1.0 } else { 0.0 });
+                    values
+                })
+                .collect();
+            solve_linear(augmented)
+        })
+        .collect::<Option<_>>()?;
+
+    let valid = (0..n).all(|i| inverse[i][i].is_finite() && inverse[i][i] > 0.0);
+    if valid { Some(inverse) } else { None }
+}
+
+fn mean_and_sd(data: &[f64]) -> (f64, f64) {
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    let variance = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, variance.sqrt())
+}
+
+// Abramowitz & Stegun 26.2.23; absolute error below 4.5e-4, ample for confidence bounds.
+fn normal_quantile(p: f64) -> f64 {
+    let tail = if p < 0.5 { p } else { 1.0 - p };
+    let t = (-2.0 * tail.max(1e-300).ln()).sqrt();
+    let z = t - (2.515517 + 0.802853 * t + 0.010328 * t * t) / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t);
+    if p < 0.5 { -z } else { z }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const DAY: i64 = 86400;
+
+    // Evenly spaced plotting positions give a deterministic sample that follows the distribution closely.
+    fn plotting_positions(n: usize) -> impl Iterator<Item = f64> {
+        (0..n).map(move |i| (i as f64 + 0.5) / n as f64)
+    }
+
+    fn gumbel_quantile(location: f64, scale: f64, u: f64) -> f64 {
+        location - scale * (-u.ln()).ln()
+    }
+
+    fn gpd_quantile(scale: f64, shape: f64, u: f64) -> f64 {
+        scale / shape * ((1.0 - u).powf(-shape) - 1.0)
+    }
+
+    // One sample per year; with no coverage requirement each is its year's block maximum.
+    fn annual_samples(values: impl Iterator<Item = f64>) -> Vec<(i64, f64)> {
+        values
+            .enumerate()
+            .map(|(i, value)| {
+                let date = NaiveDate::from_ymd_opt(1800 + i as i32, 7, 1).unwrap();
+                (date.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp(), value)
+            })
+            .collect()
+    }
+
+    fn sparse_config(daily: DailyStatistic) -> ExtremeConfig {
+        ExtremeConfig {
+            min_hours_per_day: 1,
+            min_block_coverage: 0.0,
+            ..ExtremeConfig::new(Tz::UTC, daily)
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn gev_fit_recovers_gumbel_parameters_and_return_levels() {
+        let samples = annual_samples(plotting_positions(200).map(|u| gumbel_quantile(30.0, 2.0, u)));
+        let fit = fit_gev("KJFK", WeatherVariable::Temperature, &samples, &sparse_config(DailyStatistic::Max)).unwrap();
+
+        let ExtremeDistribution::Gev { location, scale, shape, .. } = fit.distribution else {
+            panic!("expected a GEV fit");
+        };
+        assert_eq!(fit.sample_size, 200);
+        assert_close(location, 30.0, 0.3);
+        assert_close(scale, 2.0, 0.2);
+        assert_close(shape, 0.0, 0.1);
+
+        let level = fit.return_level(100.0, 0.95).unwrap();
+        assert_close(level.level, gumbel_quantile(30.0, 2.0, 0.99), 1.0);
+        assert!(level.lower.unwrap() < level.level && level.level < level.upper.unwrap());
+        assert!(fit.return_level(100.0, 0.99).unwrap().upper.unwrap() > level.upper.unwrap());
+
+        let period = fit.return_period(level.level, 0.95).unwrap();
+        assert_close(period.period_years, 100.0, 1e-3);
+        assert!(period.lower.unwrap() < period.period_years && period.period_years < period.upper.unwrap());
+    }
+
+    #[test]
+    fn lower_tail_gev_fit_reports_levels_on_the_original_scale() {
+        // Annual minima of -X for Gumbel X: the 100-year low is the negated 100-year high of X.
+        let samples = annual_samples(plotting_positions(200).map(|u| -gumbel_quantile(10.0, 3.0, u)));
+        let config = sparse_config(DailyStatistic::Min);
+        assert_eq!(config.tail, Tail::Lower);
+        let fit = fit_gev("KORD", WeatherVariable::Temperature, &samples, &config).unwrap();
+
+        let level = fit.return_level(100.0, 0.95).unwrap();
+        assert_close(level.level, -gumbel_quantile(10.0, 3.0, 0.99), 1.5);
+        assert!(level.lower.unwrap() < level.level && level.level < level.upper.unwrap());
+        assert!(fit.return_level(10.0, 0.95).unwrap().level > level.level);
+
+        let period = fit.return_period(level.level, 0.95).unwrap();
+        assert_close(period.period_years, 100.0, 1e-3);
+        assert!(fit.return_period(level.level - 5.0, 0.95).unwrap().period_years > 100.0);
+    }
+
+    #[test]
+    fn gpd_fit_declusters_runs_and_recovers_parameters() {
+        let (threshold, scale, shape) = (25.0, 2.0, 0.1);
+        let excesses: Vec<f64> = plotting_positions(300).map(|u| gpd_quantile(scale, shape, u)).collect();
+
+        // Every five days a two-day run above the threshold, whose second day is lower; the runs
+        // must count as one exceedance each.
+        let start = Utc.with_ymd_and_hms(1990, 1, 1, 12, 0, 0).unwrap().timestamp();
+        let mut samples = Vec::new();
+        for (i, excess) in excesses.iter().enumerate() {
+            let base = start + 5 * i as i64 * DAY;
+            samples.push((base, threshold + excess));
+            samples.push((base + DAY, threshold + excess / 2.0 + 1e-3));
+            for offset in 2..5 {
+                samples.push((base + offset * DAY, threshold - 5.0));
+            }
+        }
+
+        let config = ExtremeConfig {
+            threshold: ThresholdChoice::Value(threshold),
+            ..sparse_config(DailyStatistic::Max)
+        };
+        let fit = fit_gpd("KJFK", WeatherVariable::Temperature, &samples, &config).unwrap();
+
+        let ExtremeDistribution::Gpd { scale: fitted_scale, shape: fitted_shape, exceedances_per_year, .. } = fit.distribution else {
+            panic!("expected a GPD fit");
+        };
+        assert_eq!(fit.sample_size, 300);
+        assert_close(exceedances_per_year, 300.0 / (1500.0 / DAYS_PER_YEAR), 1e-9);
+        assert_close(fitted_scale, scale, 0.3);
+        assert_close(fitted_shape, shape, 0.1);
+
+        let expected = threshold + gpd_quantile(scale, shape, 1.0 - 1.0 / (50.0 * exceedances_per_year));
+        let level = fit.return_level(50.0, 0.95).unwrap();
+        assert_close(level.level, expected, 0.1 * (expected - threshold));
+        assert!(level.lower.unwrap() < level.level && level.level < level.upper.unwrap());
+        assert_close(fit.return_period(level.level, 0.95).unwrap().period_years, 50.0, 1e-3);
+        assert!(fit.return_period(threshold - 1.0, 0.95).is_none());
+    }
+
+    #[test]
+    fn declustering_splits_runs_on_gaps_longer_than_the_run_length() {
+        let config = ExtremeConfig::new(Tz::UTC, DailyStatistic::Max);
+        let day = |d: u32, value: f64| DailyValue { date: NaiveDate::from_ymd_opt(2024, 1, d).unwrap(), value };
+        let days = [day(1, 12.0), day(2, 15.0), day(3, 9.0), day(4, 11.0), day(8, 13.0), day(9, 5.0)];
+
+        // Day 4 is within two days of day 2, so it joins its cluster; day 8 starts a new one.
+        assert_eq!(decluster(&days, 10.0, &config), vec![15.0, 13.0]);
+
+        // For the lower tail the threshold is negated too: days 3 and 9 fall below 10, six days apart.
+        let lower = ExtremeConfig::new(Tz::UTC, DailyStatistic::Min);
+        assert_eq!(decluster(&days, -10.0, &lower), vec![-9.0, -5.0]);
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::quality_control::QcFlags;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InterpolationMethod {
+    Linear,
+    CubicSpline,
+    Nearest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrecipitationFill {
+    Zero,
+    Missing,
+    DistributeNext,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GapFillConfig {
+    pub interval_secs: i64,
+    pub method: InterpolationMethod,
+    pub max_gap_secs: Option<i64>,
+    pub precipitation: PrecipitationFill,
+}
+
+impl Default for GapFillConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 3600,
+            method: InterpolationMethod::Linear,
+            max_gap_secs: Some(6 * 3600),
+            precipitation: PrecipitationFill::DistributeNext,
+        }
+    }
+}
+
+// Each source is filled from its own QC-clean observations; QC-failed points are passed through and
+// earlier gap-filled points are regenerated. Every variable interpolates between its own finite values,
+// so a missing reading leaves that variable unfilled rather than spoiling its neighbours.
+pub fn fill_gaps(points: &[WeatherDataPoint], config: &GapFillConfig) -> Vec<WeatherDataPoint> {
+    let mut by_source: BTreeMap<&str, Vec<&WeatherDataPoint>> = BTreeMap::new();
+    for point in points.iter().filter(|p| !p.qc_flags.is_interpolated()) {
+        by_source.entry(point.source.as_str()).or_default().push(point);
+    }
+
+    let mut filled: Vec<WeatherDataPoint> = by_source
+        .into_values()
+        .flat_map(|source_points| fill_source(source_points, config))
+        .collect();
+    filled.sort_by_key(|p| p.timestamp);
+    filled
+}
+
+fn fill_source(mut points: Vec<&WeatherDataPoint>, config: &GapFillConfig) -> Vec<WeatherDataPoint> {
+    points.sort_by_key(|p| p.timestamp);
+    let (mut knots, mut filled): (Vec<WeatherDataPoint>, Vec<WeatherDataPoint>) =
+        points.into_iter().cloned().partition(|p| !p.qc_flags.failed());
+    knots.dedup_by_key(|p| p.timestamp);
+
+    if knots.len() < 2 || config.interval_secs <= 0 {
+        filled.extend(knots);
+        return filled;
+    }
+
+    let interpolator = |value: fn(&WeatherDataPoint) -> f64, circular: bool| {
+        Interpolator::new(&knots, value, circular, config)
+    };
+    let temperature = interpolator(|p| p.temperature, false);
+    let humidity = interpolator(|p| p.humidity, false);
+    let pressure = interpolator(|p| p.pressure, false);
+    let wind_speed = interpolator(|p| p.wind_speed, false);
+    let wind_direction = interpolator(|p| p.wind_direction, true);
+
+    let mut distributed = Vec::new();
+    for i in 0..knots.len() - 1 {
+        let left = &knots[i];
+        let right = &knots[i + 1];
+        let gap = right.timestamp - left.timestamp;
+
+        if gap <= config.interval_secs || config.max_gap_secs.is_some_and(|max| gap > max) {
+            continue;
+        }
+
+        let mut timestamps = Vec::new();
+        let mut t = left.timestamp + config.interval_secs;
+        while t < right.timestamp {
+            timestamps.push(t);
+            t += config.interval_secs;
+        }
+
+        // Accumulations reported by the next observation cover the whole gap, so that observation
+        // keeps only its share and is marked as no longer measured.
+        let precipitation = match config.precipitation {
+            PrecipitationFill::Zero => 0.0,
+            PrecipitationFill::Missing => f64::NAN,
+            PrecipitationFill::DistributeNext if right.precipitation.is_finite() => {
+                let share = right.precipitation / (timestamps.len() + 1) as f64;
+                distributed.push((i + 1, share));
+                share
+            }
+            PrecipitationFill::DistributeNext => f64::NAN,
+        };
+
+        for timestamp in timestamps {
+            let x = timestamp as f64;
+            let wind = wind_speed.at(x);
+            filled.push(WeatherDataPoint {
+                timestamp,
+                location: left.location.clone(),
+                temperature: temperature.at(x),
+                humidity: humidity.at(x).clamp(0.0, 100.0),
+                pressure: pressure.at(x),
+                wind_speed: if wind.is_finite() { wind.max(0.0) } else { wind },
+                wind_direction: wind_direction.at(x).rem_euclid(360.0),
+                precipitation,
+                qc_flags: QcFlags::CHECKED | QcFlags::INTERPOLATED,
+                source: left.source.clone(),
+                provenance: None,
+            });
+        }
+    }
+
+    for (i, share) in distributed {
+        knots[i].precipitation = share;
+        knots[i].qc_flags.insert(QcFlags::INTERPOLATED);
+    }
+    filled.extend(knots);
+    filled
+}
+
+fn unwrap_degrees(values: &[f64]) -> Vec<f64> {
+    let mut unwrapped = Vec::with_capacity(values.len());
+    let mut offset = 0.0;
+
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            let delta = value - values[i - 1];
+            if delta > 180.0 {
+                offset -= 360.0;
+            } else if delta < -180.0 {
+                offset += 360.0;
+            }
+        }
+        unwrapped.push(value + offset);
+    }
+
+    unwrapped
+}
+
+struct Interpolator {
+    times: Vec<f64>,
+    values: Vec<f64>,
+    method: InterpolationMethod,
+    second_derivatives: Vec<f64>,
+    max_gap_secs: Option<i64>,
+}
+
+impl Interpolator {
+    fn new(knots: &[WeatherDataPoint], value: fn(&WeatherDataPoint) -> f64, circular: bool, config: &GapFillConfig) -> Self {
+        let (times, values): (Vec<f64>, Vec<f64>) = knots
+            .iter()
+            .filter(|p| value(p).is_finite())
+            .map(|p| (p.timestamp as f64, value(p)))
+            .unzip();
+        let values = if circular { unwrap_degrees(&values) } else { values };
+
+        let second_derivatives = if config.method == InterpolationMethod::CubicSpline {
+            natural_spline(&times, &values)
+        } else {
+            Vec::new()
+        };
+
+        Self { times, values, method: config.method, second_derivatives, max_gap_secs: config.max_gap_secs }
+    }
+
+    // NaN outside the variable's own observations or across a gap longer than max_gap_secs.
+    fn at(&self, x: f64) -> f64 {
+        let upper = self.times.partition_point(|t| *t <= x);
+        if upper == 0 || upper == self.times.len() {
+            return f64::NAN;
+        }
+        let segment = upper - 1;
+
+        let (x0, x1) = (self.times[segment], self.times[segment + 1]);
+        let (y0, y1) = (self.values[segment], self.values[segment + 1]);
+        let h = x1 - x0;
+        if self.max_gap_secs.is_some_and(|max| h > max as f64) {
+            return f64::NAN;
+        }
+
+        match self.method {
+            InterpolationMethod::Linear => y0 + (x - x0) / h * (y1 - y0),
+            InterpolationMethod::Nearest => if x - x0 <= x1 - x { y0 } else { y1 },
+            InterpolationMethod::CubicSpline => {
+                let (m0, m1) = (self.second_derivatives[segment], self.second_derivatives[segment + 1]);
+                let a = (x1 - x) / h;
+                let b = (x - x0) / h;
+                a * y0 + b * y1 + ((a.powi(3) - a) * m0 + (b.powi(3) - b) * m1) * h * h / 6.0
+            }
+        }
+    }
+}
+
+fn natural_spline(x: &[f64], y: &[f64]) -> Vec<f64> {
+    let n = x.len();
+    let mut m = vec![0.0; n];
+    if n < 3 {
+        return m;
+    }
+
+    // Thomas algorithm for the tridiagonal system with natural boundary conditions.
+    let mut c_prime = vec![0.0; n];
+    let mut d_prime = vec![0.0; n];
+
+    for i in 1..n - 1 {
+        let h0 = x[i] - x[i - 1];
+        let h1 = x[i + 1] - x[i];
+        let a = h0 / 6.0;
+        let b = (h0 + h1) / 3.0;
+        let c = h1 / 6.0;
+        let d = (y[i + 1] - y[i]) / h1 - (y[i] - y[i - 1]) / h0;
+
+        let denom = b - a * c_prime[i - 1];
+        c_prime[i] = c / denom;
+        d_prime[i] = (d - a * d_prime[i - 1]) / denom;
+    }
+
+    for i in (1..n - 1).rev() {
+        m[i] = d_prime[i] - c_prime[i] * m[i + 1];
+    }
+
+    m
+}
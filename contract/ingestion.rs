@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::location_series::{LocationSeries, Observation};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    #[default]
+    KeepFirst,
+    KeepLast,
+    // One point per (location, timestamp): QC-clean beats failed, then the higher source priority wins.
+    // A source may always correct its own point.
+    PreferHigherQuality { source_priority: HashMap<String, i32> },
+}
+
+#[derive(Debug, Clone)]
+pub enum IngestOutcome {
+    Inserted,
+    Updated { replaced: Vec<WeatherDataPoint> },
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub ignored: usize,
+}
+
+impl IngestReport {
+    pub fn record(&mut self, outcome: &IngestOutcome) {
+        match outcome {
+            IngestOutcome::Inserted => self.inserted += 1,
+            IngestOutcome::Updated { .. } => self.updated += 1,
+            IngestOutcome::Ignored => self.ignored += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &IngestReport) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.ignored += other.ignored;
+    }
+
+    pub fn total(&self) -> usize {
+        self.inserted + self.updated + self.ignored
+    }
+}
+
+// Points sharing a timestamp keep arrival order within the series.
+pub fn upsert(series: &mut LocationSeries, point: WeatherDataPoint, policy: &ConflictPolicy) -> IngestOutcome {
+    let same_time = series.range_between(point.timestamp, point.timestamp + 1);
+
+    match policy {
+        ConflictPolicy::KeepFirst | ConflictPolicy::KeepLast => {
+            let existing = same_time.clone().find(|i| series.source(*i) == point.source);
+            match existing {
+                None => {
+                    series.insert(same_time.end, &point);
+                    IngestOutcome::Inserted
+                }
+                Some(_) if matches!(policy, ConflictPolicy::KeepFirst) => IngestOutcome::Ignored,
+                Some(i) if same_observation(&series.observation(i), &point) => IngestOutcome::Ignored,
+                Some(i) => {
+                    let previous = series.replace(i, &point);
+                    IngestOutcome::Updated { replaced: vec![previous] }
+                }
+            }
+        }
+        ConflictPolicy::PreferHigherQuality { source_priority } => {
+            if same_time.is_empty() {
+                series.insert(same_time.end, &point);
+                return IngestOutcome::Inserted;
+            }
+
+            if let Some(i) = same_time.clone().find(|i| series.source(*i) == point.source) {
+                if same_observation(&series.observation(i), &point) && series.qc_flags()[i] == point.qc_flags {
+                    return IngestOutcome::Ignored;
+                }
+                let previous = series.replace(i, &point);
+                return IngestOutcome::Updated { replaced: vec![previous] };
+            }
+
+            let priority = |source: &str| source_priority.get(source).copied().unwrap_or(0);
+            let best = same_time
+                .clone()
+                .map(|i| (!series.qc_flags()[i].failed(), priority(series.source(i))))
+                .max()
+                .unwrap();
+            if (!point.qc_flags.failed(), priority(&point.source)) <= best {
+                return IngestOutcome::Ignored;
+            }
+
+            let replaced = series.remove_range(same_time.clone());
+            series.insert(same_time.start, &point);
+            IngestOutcome::Updated { replaced }
+        }
+    }
+}
+
+// Undoes an upsert of `point`; outcomes must be reverted in reverse order of application.
+pub fn revert(series: &mut LocationSeries, point: &WeatherDataPoint, outcome: IngestOutcome) {
+    let replaced = match outcome {
+        IngestOutcome::Ignored => return,
+        IngestOutcome::Inserted => Vec::new(),
+        IngestOutcome::Updated { replaced } => replaced,
+    };
+
+    let same_time = series.range_between(point.timestamp, point.timestamp + 1);
+    if let Some(i) = same_time.clone().find(|i| series.source(*i) == point.source) {
+        series.remove_range(i..i + 1);
+    }
+
+    for previous in replaced {
+        let at = series.range_between(previous.timestamp, previous.timestamp + 1).end;
+        series.insert(at, &previous);
+    }
+}
+
+// Missing values (NaN) compare equal so replaying a point with gaps is idempotent.
+fn same_observation(a: &Observation, b: &WeatherDataPoint) -> bool {
+    let same = |x: f64, y: f64| x == y || (x.is_nan() && y.is_nan());
+    same(a.temperature, b.temperature)
+        && same(a.humidity, b.humidity)
+        && same(a.pressure, b.pressure)
+        && same(a.wind_speed, b.wind_speed)
+        && same(a.wind_direction, b.wind_direction)
+        && same(a.precipitation, b.precipitation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality_control::QcFlags;
+
+    fn point(humidity: f64) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp: 1_700_000_000,
+            location: "KJFK".to_string(),
+            temperature: 20.0,
+            humidity,
+            pressure: 1013.0,
+            wind_speed: 3.0,
+            wind_direction: f64::NAN,
+            precipitation: 0.0,
+            qc_flags: QcFlags::default(),
+            source: "metar".to_string(),
+            provenance: None,
+        }
+    }
+
+    #[test]
+    fn replaying_point_with_missing_values_is_ignored() {
+        let policies = [
+            ConflictPolicy::KeepLast,
+            ConflictPolicy::KeepFirst,
+            ConflictPolicy::PreferHigherQuality { source_priority: HashMap::new() },
+        ];
+        for policy in policies {
+            let mut series = LocationSeries::new("KJFK".to_string(), None);
+            assert!(matches!(upsert(&mut series, point(f64::NAN), &policy), IngestOutcome::Inserted));
+            assert!(matches!(upsert(&mut series, point(f64::NAN), &policy), IngestOutcome::Ignored), "{:?}", policy);
+            assert_eq!(series.len(), 1);
+        }
+    }
+
+    #[test]
+    fn filling_a_missing_value_updates_under_keep_last() {
+        let mut series = LocationSeries::new("KJFK".to_string(), None);
+        upsert(&mut series, point(f64::NAN), &ConflictPolicy::KeepLast);
+        assert!(matches!(upsert(&mut series, point(55.0), &ConflictPolicy::KeepLast), IngestOutcome::Updated { .. }));
+        assert_eq!(series.observation(0).humidity, 55.0);
+    }
+}
@@ -0,0 +1,742 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::{TimeWindow, WeatherVariable};
+use crate::derived_variables::DerivedValues;
+use crate::provenance::{Provenance, ReporterSignature};
+use crate::quality_control::QcFlags;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub temperature: f64,
+    pub humidity: f64,
+    pub pressure: f64,
+    pub wind_speed: f64,
+    pub wind_direction: f64,
+    pub precipitation: f64,
+    pub qc_flags: QcFlags,
+}
+
+// QC-failed points are left out, and each variable counts only its finite values.
+#[derive(Debug, Clone, Copy)]
+pub struct SeriesSummary {
+    pub count: usize,
+    pub temperature_count: usize,
+    pub temperature_sum: f64,
+    pub temperature_max: f64,
+    pub temperature_min: f64,
+    // Σ x·temperature, Σ x and Σ x² with x the position in the range, for the least-squares trend.
+    pub temperature_index_sum: f64,
+    pub temperature_position_sum: f64,
+    pub temperature_position_sq_sum: f64,
+    pub humidity_count: usize,
+    pub humidity_sum: f64,
+    pub pressure_count: usize,
+    pub pressure_sum: f64,
+    pub wind_speed_count: usize,
+    pub wind_speed_sum: f64,
+    pub precipitation_sum: f64,
+    pub dew_point_count: usize,
+    pub dew_point_sum: f64,
+    pub heat_index_max: f64,
+    pub wind_chill_min: f64,
+    pub apparent_temperature_max: f64,
+    pub apparent_temperature_min: f64,
+    pub sea_level_pressure_count: usize,
+    pub sea_level_pressure_sum: Option<f64>,
+}
+
+impl SeriesSummary {
+    // Summary of observations that are not stored in a series, e.g. blended across sources.
+    pub fn from_observations<I: IntoIterator<Item = Observation>>(observations: I, elevation: Option<f64>) -> Self {
+        let mut summary = Self::empty(elevation);
+        for (position, observation) in observations.into_iter().enumerate() {
+            summary.add(position, &observation, elevation);
+        }
+        summary
+    }
+
+    fn empty(elevation: Option<f64>) -> Self {
+        Self {
+            count: 0,
+            temperature_count: 0,
+            temperature_sum: 0.0,
+            temperature_max: f64::NEG_INFINITY,
+            temperature_min: f64::INFINITY,
+            temperature_index_sum: 0.0,
+            temperature_position_sum: 0.0,
+            temperature_position_sq_sum: 0.0,
+            humidity_count: 0,
+            humidity_sum: 0.0,
+            pressure_count: 0,
+            pressure_sum: 0.0,
+            wind_speed_count: 0,
+            wind_speed_sum: 0.0,
+            precipitation_sum: 0.0,
+            dew_point_count: 0,
+            dew_point_sum: 0.0,
+            heat_index_max: f64::NEG_INFINITY,
+            wind_chill_min: f64::INFINITY,
+            apparent_temperature_max: f64::NEG_INFINITY,
+            apparent_temperature_min: f64::INFINITY,
+            sea_level_pressure_count: 0,
+            sea_level_pressure_sum: elevation.map(|_| 0.0),
+        }
+    }
+
+    // Adds an observation at `position` within the range; positions of later observations are the caller's to shift.
+    fn add(&mut self, position: usize, observation: &Observation, elevation: Option<f64>) {
+        if observation.qc_flags.failed() {
+            return;
+        }
+
+        let derived = self.accumulate(position, observation, elevation, 1.0);
+        let temperature = observation.temperature;
+        if temperature.is_finite() {
+            self.temperature_max = self.temperature_max.max(temperature);
+            self.temperature_min = self.temperature_min.min(temperature);
+        }
+        // f64::max and min ignore NaN, so missing derived values leave the extremes alone.
+        self.heat_index_max = self.heat_index_max.max(derived.heat_index);
+        self.wind_chill_min = self.wind_chill_min.min(derived.wind_chill);
+        self.apparent_temperature_max = self.apparent_temperature_max.max(derived.apparent_temperature);
+        self.apparent_temperature_min = self.apparent_temperature_min.min(derived.apparent_temperature);
+    }
+
+    // Takes an observation back out of the sums. Returns false when it held an extreme, which only a
+    // full recompute can restore.
+    fn remove(&mut self, position: usize, observation: &Observation, elevation: Option<f64>) -> bool {
+        if observation.qc_flags.failed() {
+            return true;
+        }
+
+        let derived = self.accumulate(position, observation, elevation, -1.0);
+        let temperature = observation.temperature;
+        !(temperature >= self.temperature_max
+            || temperature <= self.temperature_min
+            || derived.heat_index >= self.heat_index_max
+            || derived.wind_chill <= self.wind_chill_min
+            || derived.apparent_temperature >= self.apparent_temperature_max
+            || derived.apparent_temperature <= self.apparent_temperature_min)
+    }
+
+    fn accumulate(&mut self, position: usize, observation: &Observation, elevation: Option<f64>, sign: f64) -> DerivedValues {
+        let derived = DerivedValues::from_values(
+            observation.temperature,
+            observation.humidity,
+            observation.pressure,
+            observation.wind_speed,
+            observation.wind_direction,
+            elevation,
+        );
+
+        let step = |count: &mut usize, sum: &mut f64, value: f64| {
+            if value.is_finite() {
+                *count = if sign > 0.0 { *count + 1 } else { *count - 1 };
+                *sum += sign * value;
+            }
+        };
+
+        self.count = if sign > 0.0 { self.count + 1 } else { self.count - 1 };
+        step(&mut self.temperature_count, &mut self.temperature_sum, observation.temperature);
+        if observation.temperature.is_finite() {
+            let x = position as f64;
+            self.temperature_index_sum += sign * x * observation.temperature;
+            self.temperature_position_sum += sign * x;
+            self.temperature_position_sq_sum += sign * x * x;
+        }
+        step(&mut self.humidity_count, &mut self.humidity_sum, observation.humidity);
+        step(&mut self.pressure_count, &mut self.pressure_sum, observation.pressure);
+        step(&mut self.wind_speed_count, &mut self.wind_speed_sum, observation.wind_speed);
+        if observation.precipitation.is_finite() {
+            self.precipitation_sum += sign * observation.precipitation;
+        }
+        step(&mut self.dew_point_count, &mut self.dew_point_sum, derived.dew_point);
+        if let (Some(sum), Some(slp)) = (self.sea_level_pressure_sum.as_mut(), derived.sea_level_pressure) {
+            step(&mut self.sea_level_pressure_count, sum, slp);
+        }
+        derived
+    }
+
+    // Moves a counted temperature at `position` by `delta` positions.
+    fn shift(&mut self, position: usize, temperature: f64, delta: f64) {
+        let x = position as f64;
+        self.temperature_index_sum += delta * temperature;
+        self.temperature_position_sum += delta;
+        self.temperature_position_sq_sum += (x + delta).powi(2) - x * x;
+    }
+
+    // NaN when the variable has no values.
+    pub fn mean(&self, sum: f64, count: usize) -> f64 {
+        sum / count as f64
+    }
+
+    pub fn temperature_trend(&self) -> f64 {
+        if self.temperature_count < 2 {
+            return 0.0;
+        }
+
+        let n = self.temperature_count as f64;
+        let denominator = n * self.temperature_position_sq_sum - self.temperature_position_sum.powi(2);
+        if denominator.abs() <= f64::EPSILON {
+            return 0.0;
+        }
+        (n * self.temperature_index_sum - self.temperature_position_sum * self.temperature_sum) / denominator
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SeriesColumns {
+    pub timestamps: Vec<i64>,
+    pub temperature: Vec<f64>,
+    pub humidity: Vec<f64>,
+    pub pressure: Vec<f64>,
+    pub wind_speed: Vec<f64>,
+    pub wind_direction: Vec<f64>,
+    pub precipitation: Vec<f64>,
+    pub qc_flags: Vec<QcFlags>,
+    pub source_ids: Vec<u32>,
+    pub source_names: Vec<String>,
+    // Empty when the columns predate provenance: every point then has none.
+    pub provenance_ids: Vec<u32>,
+    pub provenance_records: Vec<Option<Provenance>>,
+}
+
+// Struct-of-arrays storage for one location, sorted by timestamp.
+#[derive(Debug, Clone)]
+pub struct LocationSeries {
+    location: String,
+    elevation: Option<f64>,
+    timestamps: Vec<i64>,
+    temperature: Vec<f64>,
+    humidity: Vec<f64>,
+    pressure: Vec<f64>,
+    wind_speed: Vec<f64>,
+    wind_direction: Vec<f64>,
+    precipitation: Vec<f64>,
+    qc_flags: Vec<QcFlags>,
+    source_ids: Vec<u32>,
+    source_names: Vec<String>,
+    // Interned like sources, one record per upstream payload; record 0 is "no provenance".
+    // Records no point refers to any more are freed and their ids reused.
+    provenance_ids: Vec<u32>,
+    provenance_records: Vec<Option<Provenance>>,
+    provenance_index: HashMap<ProvenanceKey, u32>,
+    provenance_refs: Vec<usize>,
+    free_provenance: Vec<u32>,
+    summary: SeriesSummary,
+}
+
+impl LocationSeries {
+    pub fn new(location: String, elevation: Option<f64>) -> Self {
+        Self {
+            location,
+            elevation,
+            timestamps: Vec::new(),
+            temperature: Vec::new(),
+            humidity: Vec::new(),
+            pressure: Vec::new(),
+            wind_speed: Vec::new(),
+            wind_direction: Vec::new(),
+            precipitation: Vec::new(),
+            qc_flags: Vec::new(),
+            source_ids: Vec::new(),
+            source_names: Vec::new(),
+            provenance_ids: Vec::new(),
+            provenance_records: vec![None],
+            provenance_index: HashMap::new(),
+            provenance_refs: vec![0],
+            free_provenance: Vec::new(),
+            summary: SeriesSummary::empty(elevation),
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn elevation(&self) -> Option<f64> {
+        self.elevation
+    }
+
+    pub fn set_elevation(&mut self, elevation: Option<f64>) {
+        if self.elevation != elevation {
+            self.elevation = elevation;
+            self.recompute_summary();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    pub fn timestamps(&self) -> &[i64] {
+        &self.timestamps
+    }
+
+    pub fn column(&self, variable: WeatherVariable) -> &[f64] {
+        match variable {
+            WeatherVariable::Temperature => &self.temperature,
+            WeatherVariable::Humidity => &self.humidity,
+            WeatherVariable::Pressure => &self.pressure,
+            WeatherVariable::WindSpeed => &self.wind_speed,
+            WeatherVariable::WindDirection => &self.wind_direction,
+            WeatherVariable::Precipitation => &self.precipitation,
+        }
+    }
+
+    pub fn qc_flags(&self) -> &[QcFlags] {
+        &self.qc_flags
+    }
+
+    pub fn source(&self, index: usize) -> &str {
+        &self.source_names[self.source_ids[index] as usize]
+    }
+
+    // Interned sources: one id per point indexing into `source_names`.
+    pub fn source_ids(&self) -> &[u32] {
+        &self.source_ids
+    }
+
+    pub fn source_names(&self) -> &[String] {
+        &self.source_names
+    }
+
+    pub fn provenance(&self, index: usize) -> Option<&Provenance> {
+        self.provenance_records[self.provenance_ids[index] as usize].as_ref()
+    }
+
+    pub fn provenance_ids(&self) -> &[u32] {
+        &self.provenance_ids
+    }
+
+    pub fn provenance_records(&self) -> &[Option<Provenance>] {
+        &self.provenance_records
+    }
+
+    // Rebuilds a series from raw columns, e.g. a snapshot; the columns must already be sorted and consistent.
+    pub fn from_columns(location: String, elevation: Option<f64>, columns: SeriesColumns) -> Result<Self, String> {
+        let rows = columns.timestamps.len();
+        let lengths = [
+            columns.temperature.len(),
+            columns.humidity.len(),
+            columns.pressure.len(),
+            columns.wind_speed.len(),
+            columns.wind_direction.len(),
+            columns.precipitation.len(),
+            columns.qc_flags.len(),
+            columns.source_ids.len(),
+        ];
+        if lengths.iter().any(|len| *len != rows) {
+            return Err(format!("Columns of {} have different lengths", location));
+        }
+        if columns.timestamps.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(format!("Timestamps of {} are not sorted", location));
+        }
+        if columns.source_ids.iter().any(|id| *id as usize >= columns.source_names.len()) {
+            return Err(format!("Source id out of range in {}", location));
+        }
+
+        let (provenance_ids, provenance_records) = if columns.provenance_records.is_empty() {
+            (vec![0; rows], vec![None])
+        } else {
+            (columns.provenance_ids, columns.provenance_records)
+        };
+        if provenance_ids.len() != rows || provenance_records[0].is_some() {
+            return Err(format!("Provenance columns of {} are inconsistent", location));
+        }
+        if provenance_ids.iter().any(|id| *id as usize >= provenance_records.len()) {
+            return Err(format!("Provenance id out of range in {}", location));
+        }
+        let mut provenance_records = provenance_records;
+        let mut provenance_refs = vec![0; provenance_records.len()];
+        for id in &provenance_ids {
+            provenance_refs[*id as usize] += 1;
+        }
+        let mut free_provenance = Vec::new();
+        for id in 1..provenance_records.len() {
+            if provenance_refs[id] == 0 {
+                provenance_records[id] = None;
+                free_provenance.push(id as u32);
+            }
+        }
+        let provenance_index = provenance_records
+            .iter()
+            .enumerate()
+            .filter_map(|(id, record)| Some((provenance_key(record.as_ref()?), id as u32)))
+            .collect();
+
+        let mut series = Self {
+            location,
+            elevation,
+            timestamps: columns.timestamps,
+            temperature: columns.temperature,
+            humidity: columns.humidity,
+            pressure: columns.pressure,
+            wind_speed: columns.wind_speed,
+            wind_direction: columns.wind_direction,
+            precipitation: columns.precipitation,
+            qc_flags: columns.qc_flags,
+            source_ids: columns.source_ids,
+            source_names: columns.source_names,
+            provenance_ids,
+            provenance_records,
+            provenance_index,
+            provenance_refs,
+            free_provenance,
+            summary: SeriesSummary::empty(elevation),
+        };
+        series.recompute_summary();
+        Ok(series)
+    }
+
+    // Index range of points with `start <= timestamp < end`.
+    pub fn range_between(&self, start: i64, end: i64) -> Range<usize> {
+        let from = self.timestamps.partition_point(|t| *t < start);
+        let to = self.timestamps.partition_point(|t| *t < end).max(from);
+        from..to
+    }
+
+    pub fn range(&self, window: Option<TimeWindow>) -> Range<usize> {
+        match window {
+            Some(window) => self.range_between(window.start, window.end),
+            None => 0..self.len(),
+        }
+    }
+
+    pub fn observation(&self, index: usize) -> Observation {
+        Observation {
+            timestamp: self.timestamps[index],
+            temperature: self.temperature[index],
+            humidity: self.humidity[index],
+            pressure: self.pressure[index],
+            wind_speed: self.wind_speed[index],
+            wind_direction: self.wind_direction[index],
+            precipitation: self.precipitation[index],
+            qc_flags: self.qc_flags[index],
+        }
+    }
+
+    pub fn point(&self, index: usize) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp: self.timestamps[index],
+            location: self.location.clone(),
+            temperature: self.temperature[index],
+            humidity: self.humidity[index],
+            pressure: self.pressure[index],
+            wind_speed: self.wind_speed[index],
+            wind_direction: self.wind_direction[index],
+            precipitation: self.precipitation[index],
+            qc_flags: self.qc_flags[index],
+            source: self.source(index).to_string(),
+            provenance: self.provenance(index).cloned(),
+        }
+    }
+
+    pub fn points(&self, range: Range<usize>) -> Vec<WeatherDataPoint> {
+        range.map(|i| self.point(i)).collect()
+    }
+
+    pub fn summary(&self) -> &SeriesSummary {
+        &self.summary
+    }
+
+    pub fn summarize(&self, range: Range<usize>) -> SeriesSummary {
+        if range == (0..self.len()) {
+            return self.summary;
+        }
+
+        let mut summary = SeriesSummary::empty(self.elevation);
+        for (position, i) in range.enumerate() {
+            summary.add(position, &self.observation(i), self.elevation);
+        }
+        summary
+    }
+
+    pub fn insert(&mut self, index: usize, point: &WeatherDataPoint) {
+        let source_id = self.intern_source(&point.source);
+        let provenance_id = self.intern_provenance(point.provenance.as_ref());
+        self.timestamps.insert(index, point.timestamp);
+        self.temperature.insert(index, point.temperature);
+        self.humidity.insert(index, point.humidity);
+        self.pressure.insert(index, point.pressure);
+        self.wind_speed.insert(index, point.wind_speed);
+        self.wind_direction.insert(index, point.wind_direction);
+        self.precipitation.insert(index, point.precipitation);
+        self.qc_flags.insert(index, point.qc_flags);
+        self.source_ids.insert(index, source_id);
+        self.provenance_ids.insert(index, provenance_id);
+
+        // Everything after the insert moves one position to the right.
+        self.shift_positions(index + 1..self.len(), -1, 1.0);
+        self.summary.add(index, &self.observation(index), self.elevation);
+    }
+
+    pub fn replace(&mut self, index: usize, point: &WeatherDataPoint) -> WeatherDataPoint {
+        let previous = self.point(index);
+        let exact = self.summary.remove(index, &self.observation(index), self.elevation);
+        let source_id = self.intern_source(&point.source);
+        let provenance_id = self.intern_provenance(point.provenance.as_ref());
+        self.release_provenance(self.provenance_ids[index]);
+        self.timestamps[index] = point.timestamp;
+        self.temperature[index] = point.temperature;
+        self.humidity[index] = point.humidity;
+        self.pressure[index] = point.pressure;
+        self.wind_speed[index] = point.wind_speed;
+        self.wind_direction[index] = point.wind_direction;
+        self.precipitation[index] = point.precipitation;
+        self.qc_flags[index] = point.qc_flags;
+        self.source_ids[index] = source_id;
+        self.provenance_ids[index] = provenance_id;
+        if exact {
+            self.summary.add(index, &self.observation(index), self.elevation);
+        } else {
+            self.recompute_summary();
+        }
+        previous
+    }
+
+    pub fn remove_range(&mut self, range: Range<usize>) -> Vec<WeatherDataPoint> {
+        let removed = self.points(range.clone());
+        let mut exact = true;
+        for i in range.clone() {
+            exact &= self.summary.remove(i, &self.observation(i), self.elevation);
+        }
+        if exact {
+            self.shift_positions(range.end..self.len(), 0, -(range.len() as f64));
+        }
+
+        self.timestamps.drain(range.clone());
+        self.temperature.drain(range.clone());
+        self.humidity.drain(range.clone());
+        self.pressure.drain(range.clone());
+        self.wind_speed.drain(range.clone());
+        self.wind_direction.drain(range.clone());
+        self.precipitation.drain(range.clone());
+        self.qc_flags.drain(range.clone());
+        self.source_ids.drain(range.clone());
+        for id in self.provenance_ids.drain(range).collect::<Vec<_>>() {
+            self.release_provenance(id);
+        }
+        if !exact {
+            self.recompute_summary();
+        }
+        removed
+    }
+
+    pub fn retain_timestamps<F: Fn(i64) -> bool>(&mut self, keep: F) -> usize {
+        let mask: Vec<bool> = self.timestamps.iter().map(|t| keep(*t)).collect();
+        let removed = mask.iter().filter(|k| !**k).count();
+        if removed == 0 {
+            return 0;
+        }
+
+        retain_by_mask(&mut self.timestamps, &mask);
+        retain_by_mask(&mut self.temperature, &mask);
+        retain_by_mask(&mut self.humidity, &mask);
+        retain_by_mask(&mut self.pressure, &mask);
+        retain_by_mask(&mut self.wind_speed, &mask);
+        retain_by_mask(&mut self.wind_direction, &mask);
+        retain_by_mask(&mut self.precipitation, &mask);
+        retain_by_mask(&mut self.qc_flags, &mask);
+        retain_by_mask(&mut self.source_ids, &mask);
+        let released: Vec<u32> = self.provenance_ids.iter().zip(&mask).filter(|(_, keep)| !**keep).map(|(id, _)| *id).collect();
+        for id in released {
+            self.release_provenance(id);
+        }
+        retain_by_mask(&mut self.provenance_ids, &mask);
+        self.recompute_summary();
+        removed
+    }
+
+    fn intern_source(&mut self, source: &str) -> u32 {
+        match self.source_names.iter().position(|s| s == source) {
+            Some(id) => id as u32,
+            None => {
+                self.source_names.push(source.to_string());
+                (self.source_names.len() - 1) as u32
+            }
+        }
+    }
+
+    // A hash map rather than a scan: unlike sources, every ingested payload brings a new record.
+    // A payload ingested again keeps the record, and so the arrival time, it was first stored with.
+    fn intern_provenance(&mut self, provenance: Option<&Provenance>) -> u32 {
+        let provenance = match provenance {
+            Some(provenance) => provenance,
+            None => return 0,
+        };
+        let key = provenance_key(provenance);
+        let id = match self.provenance_index.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = match self.free_provenance.pop() {
+                    Some(id) => {
+                        self.provenance_records[id as usize] = Some(provenance.clone());
+                        id
+                    }
+                    None => {
+                        self.provenance_records.push(Some(provenance.clone()));
+                        self.provenance_refs.push(0);
+                        (self.provenance_records.len() - 1) as u32
+                    }
+                };
+                self.provenance_index.insert(key, id);
+                id
+            }
+        };
+        self.provenance_refs[id as usize] += 1;
+        id
+    }
+
+    fn release_provenance(&mut self, id: u32) {
+        if id == 0 {
+            return;
+        }
+        let refs = &mut self.provenance_refs[id as usize];
+        *refs -= 1;
+        if *refs == 0 {
+            if let Some(provenance) = self.provenance_records[id as usize].take() {
+                self.provenance_index.remove(&provenance_key(&provenance));
+            }
+            self.free_provenance.push(id);
+        }
+    }
+
+    // Shifts the summary's positions of the counted temperatures in `range`, whose current position is
+    // their index plus `offset`, by `delta`.
+    fn shift_positions(&mut self, range: Range<usize>, offset: isize, delta: f64) {
+        for i in range {
+            let temperature = self.temperature[i];
+            if !self.qc_flags[i].failed() && temperature.is_finite() {
+                self.summary.shift((i as isize + offset) as usize, temperature, delta);
+            }
+        }
+    }
+
+    fn recompute_summary(&mut self) {
+        let mut summary = SeriesSummary::empty(self.elevation);
+        for i in 0..self.len() {
+            summary.add(i, &self.observation(i), self.elevation);
+        }
+        self.summary = summary;
+    }
+}
+
+type ProvenanceKey = ([u8; 32], Option<ReporterSignature>);
+
+fn provenance_key(provenance: &Provenance) -> ProvenanceKey {
+    (provenance.payload_hash, provenance.signature.clone())
+}
+
+fn retain_by_mask<T>(values: &mut Vec<T>, mask: &[bool]) {
+    let mut i = 0;
+    values.retain(|_| {
+        let keep = mask[i];
+        i += 1;
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(timestamp: i64, temperature: f64, humidity: f64, qc_flags: QcFlags) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: "KJFK".to_string(),
+            temperature,
+            humidity,
+            pressure: 1000.0 + temperature / 2.0,
+            wind_speed: (temperature / 3.0).abs(),
+            wind_direction: 90.0,
+            precipitation: if timestamp % 7 == 0 { 0.4 } else { 0.0 },
+            qc_flags,
+            source: "metar".to_string(),
+            provenance: None,
+        }
+    }
+
+    fn brute_force(series: &LocationSeries, range: Range<usize>) -> SeriesSummary {
+        SeriesSummary::from_observations(range.map(|i| series.observation(i)), series.elevation())
+    }
+
+    fn assert_same(actual: &SeriesSummary, expected: &SeriesSummary, step: &str) {
+        let counts = |s: &SeriesSummary| {
+            [s.count, s.temperature_count, s.humidity_count, s.pressure_count, s.wind_speed_count, s.dew_point_count, s.sea_level_pressure_count]
+        };
+        let extremes = |s: &SeriesSummary| {
+            [s.temperature_max, s.temperature_min, s.heat_index_max, s.wind_chill_min, s.apparent_temperature_max, s.apparent_temperature_min]
+        };
+        let sums = |s: &SeriesSummary| {
+            [
+                s.temperature_sum,
+                s.temperature_index_sum,
+                s.temperature_position_sum,
+                s.temperature_position_sq_sum,
+                s.humidity_sum,
+                s.pressure_sum,
+                s.wind_speed_sum,
+                s.precipitation_sum,
+                s.dew_point_sum,
+                s.sea_level_pressure_sum.unwrap_or(0.0),
+            ]
+        };
+
+        assert_eq!(counts(actual), counts(expected), "counts after {}", step);
+        assert_eq!(extremes(actual), extremes(expected), "extremes after {}", step);
+        for (a, e) in sums(actual).into_iter().zip(sums(expected)) {
+            assert!((a - e).abs() <= 1e-9 * e.abs().max(1.0), "sum after {}: {} != {}", step, a, e);
+        }
+        assert!((actual.temperature_trend() - expected.temperature_trend()).abs() < 1e-9, "trend after {}", step);
+    }
+
+    fn check(series: &LocationSeries, step: &str) {
+        assert_same(&series.summarize(0..series.len()), &brute_force(series, 0..series.len()), step);
+        let middle = series.len() / 4..series.len() * 3 / 4;
+        assert_same(&series.summarize(middle.clone()), &brute_force(series, middle), step);
+    }
+
+    fn insert_sorted(series: &mut LocationSeries, point: &WeatherDataPoint) {
+        let index = series.range_between(i64::MIN, point.timestamp + 1).end;
+        series.insert(index, point);
+    }
+
+    #[test]
+    fn incremental_summary_matches_a_full_recompute() {
+        let mut series = LocationSeries::new("KJFK".to_string(), Some(120.0));
+        for i in 0..40 {
+            let humidity = if i % 5 == 0 { f64::NAN } else { 40.0 + i as f64 };
+            series.insert(series.len(), &point(1_000 + i * 60, 5.0 + (i as f64 * 1.7) % 23.0, humidity, QcFlags::CHECKED));
+        }
+        check(&series, "appends");
+
+        insert_sorted(&mut series, &point(1_030, 12.5, 55.0, QcFlags::CHECKED));
+        insert_sorted(&mut series, &point(1_001, f64::NAN, 60.0, QcFlags::CHECKED));
+        insert_sorted(&mut series, &point(1_500, 99.0, 60.0, QcFlags::CHECKED | QcFlags::RANGE_FAILED));
+        check(&series, "out-of-order inserts");
+
+        series.replace(10, &point(series.timestamps()[10], 14.0, 50.0, QcFlags::CHECKED));
+        check(&series, "replacing an ordinary value");
+        let hottest = (0..series.len()).max_by(|a, b| series.column(WeatherVariable::Temperature)[*a].total_cmp(&series.column(WeatherVariable::Temperature)[*b])).unwrap();
+        series.replace(hottest, &point(series.timestamps()[hottest], 6.0, 50.0, QcFlags::CHECKED));
+        check(&series, "replacing the maximum");
+        series.replace(3, &point(series.timestamps()[3], 35.0, 80.0, QcFlags::CHECKED));
+        check(&series, "replacing with a new heat-index maximum");
+
+        series.remove_range(20..25);
+        check(&series, "removing ordinary values");
+        let coldest = (0..series.len()).min_by(|a, b| series.column(WeatherVariable::Temperature)[*a].total_cmp(&series.column(WeatherVariable::Temperature)[*b])).unwrap();
+        series.remove_range(coldest..coldest + 1);
+        check(&series, "removing the minimum");
+        series.remove_range(0..series.len());
+        check(&series, "removing everything");
+    }
+}
@@ -0,0 +1,645 @@
+use std::collections::HashSet;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+use sha3::{Digest, Sha3_256};
+use web3::types::H256;
+
+use crate::blockchain_interface::{compute_data_hash, OracleData};
+use crate::data_query::local_start_of_day;
+use crate::degree_days::{self, DegreeDayConfig, DegreeDayKind, MissingDayPolicy};
+use crate::units::{from_oracle_fixed, LengthUnit, TemperatureUnit, Unit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementVariable {
+    Temperature,
+    Humidity,
+    Precipitation,
+}
+
+impl SettlementVariable {
+    // Units of the oracle's fixed-point values.
+    pub fn canonical_unit(&self) -> Unit {
+        match self {
+            SettlementVariable::Temperature => Unit::Temperature(TemperatureUnit::Celsius),
+            SettlementVariable::Humidity => Unit::Percent,
+            SettlementVariable::Precipitation => Unit::Length(LengthUnit::Millimeters),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aggregation {
+    Max,
+    Min,
+    Mean,
+    Sum,
+    Last,
+    HeatingDegreeDays,
+    CoolingDegreeDays,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Above,
+    AtOrAbove,
+    Below,
+    AtOrBelow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    HalfUp,
+    HalfEven,
+    Floor,
+    Ceil,
+    Truncate,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RoundingRule {
+    pub decimals: u32,
+    pub mode: RoundingMode,
+}
+
+impl RoundingRule {
+    pub fn whole_units() -> Self {
+        Self { decimals: 0, mode: RoundingMode::HalfUp }
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        let factor = 10f64.powi(self.decimals as i32);
+        let scaled = value * factor;
+
+        let rounded = match self.mode {
+            // Ties go towards positive infinity, so -2.5 becomes -2 rather than f64::round's -3.
+            RoundingMode::HalfUp => {
+                let floor = scaled.floor();
+                if scaled - floor >= 0.5 { floor + 1.0 } else { floor }
+            }
+            RoundingMode::HalfEven => {
+                let floor = scaled.floor();
+                let diff = scaled - floor;
+                if (diff - 0.5).abs() < 1e-9 {
+                    if floor % 2.0 == 0.0 { floor } else { floor + 1.0 }
+                } else {
+                    scaled.round()
+                }
+            }
+            RoundingMode::Floor => scaled.floor(),
+            RoundingMode::Ceil => scaled.ceil(),
+            RoundingMode::Truncate => scaled.trunc(),
+        };
+
+        rounded / factor
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutcomeBucket {
+    pub label: String,
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+}
+
+impl OutcomeBucket {
+    fn contains(&self, value: f64) -> bool {
+        self.lower.is_none_or(|lower| value >= lower) && self.upper.is_none_or(|upper| value < upper)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ContractKind {
+    Threshold {
+        aggregation: Aggregation,
+        comparison: Comparison,
+        threshold: f64,
+    },
+    RangeBuckets {
+        aggregation: Aggregation,
+        buckets: Vec<OutcomeBucket>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherContract {
+    pub contract_id: String,
+    pub city: String,
+    pub variable: SettlementVariable,
+    pub start_date: NaiveDate,
+    pub days: u32,
+    pub timezone: Tz,
+    pub rounding: RoundingRule,
+    pub min_readings: usize,
+    pub kind: ContractKind,
+    #[serde(default)]
+    pub degree_days: Option<DegreeDayConfig>,
+    // Unit the threshold or buckets are quoted in; unset means the oracle's canonical unit.
+    #[serde(default)]
+    pub unit: Option<Unit>,
+}
+
+impl WeatherContract {
+    pub fn daily_max_above(contract_id: String, city: String, date: NaiveDate, timezone: Tz, threshold: f64) -> Self {
+        Self {
+            contract_id,
+            city,
+            variable: SettlementVariable::Temperature,
+            start_date: date,
+            days: 1,
+            timezone,
+            rounding: RoundingRule::whole_units(),
+            min_readings: 1,
+            kind: ContractKind::Threshold {
+                aggregation: Aggregation::Max,
+                comparison: Comparison::Above,
+                threshold,
+            },
+            degree_days: None,
+            unit: None,
+        }
+    }
+
+    // Precipitation is not hashed on chain: settle these with SettlementEngine::without_hash_verification.
+    pub fn weekly_rain_total(contract_id: String, city: String, start_date: NaiveDate, timezone: Tz, buckets: Vec<OutcomeBucket>) -> Self {
+        Self {
+            contract_id,
+            city,
+            variable: SettlementVariable::Precipitation,
+            start_date,
+            days: 7,
+            timezone,
+            rounding: RoundingRule { decimals: 1, mode: RoundingMode::HalfUp },
+            min_readings: 1,
+            kind: ContractKind::RangeBuckets {
+                aggregation: Aggregation::Sum,
+                buckets,
+            },
+            degree_days: None,
+            unit: None,
+        }
+    }
+
+    pub fn season_degree_days(
+        contract_id: String,
+        city: String,
+        start_date: NaiveDate,
+        days: u32,
+        kind: DegreeDayKind,
+        config: DegreeDayConfig,
+        buckets: Vec<OutcomeBucket>,
+    ) -> Self {
+        let aggregation = match kind {
+            DegreeDayKind::Heating => Aggregation::HeatingDegreeDays,
+            DegreeDayKind::Cooling => Aggregation::CoolingDegreeDays,
+        };
+
+        Self {
+            contract_id,
+            city,
+            variable: SettlementVariable::Temperature,
+            start_date,
+            days,
+            timezone: config.timezone,
+            rounding: RoundingRule { decimals: 0, mode: RoundingMode::HalfUp },
+            min_readings: 1,
+            kind: ContractKind::RangeBuckets { aggregation, buckets },
+            unit: Some(Unit::Temperature(config.unit)),
+            degree_days: Some(config),
+        }
+    }
+
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    pub fn settlement_unit(&self) -> Unit {
+        self.unit.unwrap_or_else(|| self.variable.canonical_unit())
+    }
+
+    pub fn settlement_window(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let end_date = self.start_date + Duration::days(self.days as i64);
+        let start = local_start_of_day(self.timezone, self.start_date)?;
+        let end = local_start_of_day(self.timezone, end_date)?;
+        Some((Utc.timestamp_opt(start, 0).single()?, Utc.timestamp_opt(end, 0).single()?))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OracleReading {
+    pub data: OracleData,
+    pub precipitation: Option<i64>,
+    pub tx_hash: Option<H256>,
+    pub block_number: Option<u64>,
+}
+
+impl OracleReading {
+    pub fn new(data: OracleData) -> Self {
+        Self {
+            data,
+            precipitation: None,
+            tx_hash: None,
+            block_number: None,
+        }
+    }
+
+    fn value(&self, variable: SettlementVariable) -> Option<f64> {
+        match variable {
+            SettlementVariable::Temperature => Some(from_oracle_fixed(self.data.temperature)),
+            SettlementVariable::Humidity => Some(from_oracle_fixed(self.data.humidity)),
+            SettlementVariable::Precipitation => self.precipitation.map(from_oracle_fixed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    Yes,
+    No,
+    Bucket(String),
+    Unresolved(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceReading {
+    pub timestamp: u64,
+    pub value: f64,
+    pub data_hash: [u8; 32],
+    pub tx_hash: Option<H256>,
+    pub block_number: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedReading {
+    pub timestamp: u64,
+    pub data_hash: [u8; 32],
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementEvidence {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    // Reading values, the raw value and the settled value are all in this unit.
+    pub unit: Unit,
+    pub readings: Vec<EvidenceReading>,
+    pub excluded: Vec<ExcludedReading>,
+    pub raw_value: Option<f64>,
+    pub settled_value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    pub contract_id: String,
+    pub outcome: Outcome,
+    pub evidence: SettlementEvidence,
+    pub resolution_hash: [u8; 32],
+}
+
+pub struct SettlementEngine {
+    verify_hashes: bool,
+}
+
+impl Default for SettlementEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettlementEngine {
+    pub fn new() -> Self {
+        Self { verify_hashes: true }
+    }
+
+    pub fn without_hash_verification() -> Self {
+        Self { verify_hashes: false }
+    }
+
+    pub fn settle(&self, contract: &WeatherContract, readings: &[OracleReading]) -> Result<Resolution, Box<dyn std::error::Error>> {
+        let (window_start, window_end) = contract
+            .settlement_window()
+            .ok_or("Settlement window does not exist in the contract timezone")?;
+
+        let unit = contract.settlement_unit();
+        if !unit.same_dimension(&contract.variable.canonical_unit()) {
+            return Err(format!("{} cannot settle a {:?} contract", unit.symbol(), contract.variable).into());
+        }
+
+        // Precipitation is not part of the on-chain data hash, so nothing vouches for it.
+        if self.verify_hashes && contract.variable == SettlementVariable::Precipitation {
+            return Err("Precipitation readings are not covered by the data hash and cannot be verified".into());
+        }
+
+        let start_ts = window_start.timestamp();
+        let end_ts = window_end.timestamp();
+
+        let mut ordered: Vec<&OracleReading> = readings.iter().collect();
+        ordered.sort_by(|a, b| {
+            a.data.timestamp
+                .cmp(&b.data.timestamp)
+                .then_with(|| a.data.data_hash.cmp(&b.data.data_hash))
+        });
+
+        let mut seen_hashes = HashSet::new();
+        let mut used = Vec::new();
+        let mut excluded = Vec::new();
+
+        for reading in ordered {
+            let data = &reading.data;
+            let ts = data.timestamp as i64;
+
+            if data.city != contract.city || ts < start_ts || ts >= end_ts {
+                continue;
+            }
+
+            // A forged reading reusing a genuine hash must not claim it ahead of the genuine one.
+            let exclusion = if self.verify_hashes
+                && compute_data_hash(&data.city, data.temperature, data.humidity, data.timestamp) != data.data_hash
+            {
+                Some("data hash does not match reported values")
+            } else if !seen_hashes.insert(data.data_hash) {
+                Some("duplicate data hash")
+            } else {
+                None
+            };
+
+            if let Some(reason) = exclusion {
+                excluded.push(ExcludedReading {
+                    timestamp: data.timestamp,
+                    data_hash: data.data_hash,
+                    reason: reason.to_string(),
+                });
+                continue;
+            }
+
+            match reading.value(contract.variable) {
+                Some(value) => used.push(EvidenceReading {
+                    timestamp: data.timestamp,
+                    value: unit.from_canonical(value),
+                    data_hash: data.data_hash,
+                    tx_hash: reading.tx_hash,
+                    block_number: reading.block_number,
+                }),
+                None => excluded.push(ExcludedReading {
+                    timestamp: data.timestamp,
+                    data_hash: data.data_hash,
+                    reason: format!("reading has no {:?} value", contract.variable),
+                }),
+            }
+        }
+
+        let aggregation = match &contract.kind {
+            ContractKind::Threshold { aggregation, .. } => *aggregation,
+            ContractKind::RangeBuckets { aggregation, .. } => *aggregation,
+        };
+
+        let raw_value = if used.len() < contract.min_readings.max(1) {
+            Err(format!(
+                "{} valid readings in window, {} required",
+                used.len(),
+                contract.min_readings.max(1)
+            ))
+        } else {
+            match aggregation {
+                Aggregation::HeatingDegreeDays => degree_day_index(contract, &used, DegreeDayKind::Heating),
+                Aggregation::CoolingDegreeDays => degree_day_index(contract, &used, DegreeDayKind::Cooling),
+                _ => aggregate(&used, aggregation).ok_or_else(|| "no readings to aggregate".to_string()),
+            }
+        };
+        let (raw_value, settled_value, outcome) = match raw_value {
+            Ok(value) => {
+                let settled = contract.rounding.apply(value);
+                (Some(value), Some(settled), resolve_outcome(&contract.kind, settled))
+            }
+            Err(reason) => (None, None, Outcome::Unresolved(reason)),
+        };
+
+        let evidence = SettlementEvidence {
+            window_start,
+            window_end,
+            unit,
+            readings: used,
+            excluded,
+            raw_value,
+            settled_value,
+        };
+
+        let resolution_hash = hash_resolution(&contract.contract_id, &outcome, &evidence)?;
+
+        Ok(Resolution {
+            contract_id: contract.contract_id.clone(),
+            outcome,
+            evidence,
+            resolution_hash,
+        })
+    }
+
+    pub fn settle_batch(&self, contracts: &[WeatherContract], readings: &[OracleReading]) -> Vec<Result<Resolution, String>> {
+        contracts
+            .iter()
+            .map(|contract| self.settle(contract, readings).map_err(|e| e.to_string()))
+            .collect()
+    }
+}
+
+fn aggregate(readings: &[EvidenceReading], aggregation: Aggregation) -> Option<f64> {
+    let values = readings.iter().map(|r| r.value);
+
+    match aggregation {
+        Aggregation::Max => values.reduce(f64::max),
+        Aggregation::Min => values.reduce(f64::min),
+        Aggregation::Sum => Some(values.sum()),
+        Aggregation::Mean => {
+            let n = readings.len();
+            if n == 0 { None } else { Some(values.sum::<f64>() / n as f64) }
+        }
+        Aggregation::Last => readings.last().map(|r| r.value),
+        Aggregation::HeatingDegreeDays | Aggregation::CoolingDegreeDays => None,
+    }
+}
+
+// Degree-day contracts settle only when every day in the window has an observed daily temperature;
+// interpolated days are never used for settlement, whatever the contract's missing-day policy.
+fn degree_day_index(contract: &WeatherContract, readings: &[EvidenceReading], kind: DegreeDayKind) -> Result<f64, String> {
+    if contract.days == 0 {
+        return Err("contract covers no days".to_string());
+    }
+
+    let mut config = contract
+        .degree_days
+        .clone()
+        .unwrap_or_else(|| DegreeDayConfig::fahrenheit(contract.timezone));
+    config.timezone = contract.timezone;
+    if config.missing == MissingDayPolicy::Interpolate {
+        config.missing = MissingDayPolicy::Exclude;
+    }
+
+    // compute_degree_days takes canonical temperatures and applies the config's unit itself.
+    let unit = contract.settlement_unit();
+    let samples: Vec<(i64, f64)> = readings.iter().map(|r| (r.timestamp as i64, unit.to_canonical(r.value))).collect();
+    let through = contract.start_date + Duration::days(contract.days as i64 - 1);
+    let series = degree_days::compute_degree_days(&contract.city, &samples, &config, contract.start_date, through)
+        .map_err(|e| e.to_string())?;
+
+    if !series.is_complete() || !series.interpolated_days.is_empty() {
+        let days = series.missing_days.len() + series.interpolated_days.len();
+        return Err(format!("{} days without an observed daily temperature", days));
+    }
+    Ok(series.index(kind))
+}
+
+fn resolve_outcome(kind: &ContractKind, value: f64) -> Outcome {
+    match kind {
+        ContractKind::Threshold { comparison, threshold, .. } => {
+            let hit = match comparison {
+                Comparison::Above => value > *threshold,
+                Comparison::AtOrAbove => value >= *threshold,
+                Comparison::Below => value < *threshold,
+                Comparison::AtOrBelow => value <= *threshold,
+            };
+            if hit { Outcome::Yes } else { Outcome::No }
+        }
+        ContractKind::RangeBuckets { buckets, .. } => buckets
+            .iter()
+            .find(|bucket| bucket.contains(value))
+            .map(|bucket| Outcome::Bucket(bucket.label.clone()))
+            .unwrap_or_else(|| Outcome::Unresolved(format!("value {} falls outside every bucket", value))),
+    }
+}
+
+fn hash_resolution(contract_id: &str, outcome: &Outcome, evidence: &SettlementEvidence) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    let mut hasher = Sha3_256::new();
+    hasher.update(contract_id.as_bytes());
+    hasher.update(serde_json::to_vec(outcome)?);
+    hasher.update(serde_json::to_vec(evidence)?);
+
+    let result = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&result);
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(city: &str, temperature: i64, timestamp: u64) -> OracleReading {
+        OracleReading::new(OracleData {
+            city: city.to_string(),
+            temperature,
+            humidity: 5000,
+            timestamp,
+            data_hash: compute_data_hash(city, temperature, 5000, timestamp),
+        })
+    }
+
+    fn contract() -> WeatherContract {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        WeatherContract::daily_max_above("c1".to_string(), "NYC".to_string(), date, chrono_tz::UTC, 30.0)
+    }
+
+    const NOON: u64 = 1_719_835_200;
+
+    #[test]
+    fn duplicate_hashes_settle_once() {
+        let first = reading("NYC", 3100, NOON);
+        let resolution = SettlementEngine::new().settle(&contract(), &[first.clone(), first]).unwrap();
+
+        assert_eq!(resolution.evidence.readings.len(), 1);
+        assert_eq!(resolution.evidence.excluded[0].reason, "duplicate data hash");
+        assert_eq!(resolution.outcome, Outcome::Yes);
+    }
+
+    #[test]
+    fn tampered_values_are_excluded() {
+        let mut tampered = reading("NYC", 2500, NOON);
+        tampered.data.temperature = 3500;
+        let resolution = SettlementEngine::new().settle(&contract(), &[tampered]).unwrap();
+
+        assert!(resolution.evidence.readings.is_empty());
+        assert_eq!(resolution.evidence.excluded[0].reason, "data hash does not match reported values");
+        assert!(matches!(resolution.outcome, Outcome::Unresolved(_)));
+    }
+
+    #[test]
+    fn forged_reading_does_not_shadow_genuine_hash() {
+        let genuine = reading("NYC", 3100, NOON);
+        let mut forged = genuine.clone();
+        forged.data.timestamp = NOON - 3600;
+        forged.data.temperature = 2000;
+
+        let resolution = SettlementEngine::new().settle(&contract(), &[forged, genuine]).unwrap();
+        assert_eq!(resolution.evidence.readings.len(), 1);
+        assert_eq!(resolution.evidence.readings[0].timestamp, NOON);
+        assert_eq!(resolution.outcome, Outcome::Yes);
+    }
+
+    #[test]
+    fn unverifiable_precipitation_is_refused() {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let contract = WeatherContract::weekly_rain_total("r1".to_string(), "NYC".to_string(), date, chrono_tz::UTC, Vec::new());
+        let mut rain = reading("NYC", 2000, NOON);
+        rain.precipitation = Some(1250);
+
+        assert!(SettlementEngine::new().settle(&contract, &[rain.clone()]).is_err());
+        let resolution = SettlementEngine::without_hash_verification().settle(&contract, &[rain]).unwrap();
+        assert_eq!(resolution.evidence.raw_value, Some(12.5));
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let rule = |decimals, mode| RoundingRule { decimals, mode };
+
+        assert_eq!(rule(0, RoundingMode::HalfUp).apply(2.5), 3.0);
+        assert_eq!(rule(0, RoundingMode::HalfUp).apply(-2.5), -2.0);
+        assert_eq!(rule(0, RoundingMode::HalfUp).apply(-2.6), -3.0);
+        assert_eq!(rule(1, RoundingMode::HalfUp).apply(-0.25), -0.2);
+        assert_eq!(rule(0, RoundingMode::HalfEven).apply(2.5), 2.0);
+        assert_eq!(rule(0, RoundingMode::HalfEven).apply(3.5), 4.0);
+        assert_eq!(rule(0, RoundingMode::HalfEven).apply(-2.5), -2.0);
+        assert_eq!(rule(0, RoundingMode::Floor).apply(-2.1), -3.0);
+        assert_eq!(rule(0, RoundingMode::Ceil).apply(2.1), 3.0);
+        assert_eq!(rule(0, RoundingMode::Truncate).apply(-2.9), -2.0);
+    }
+
+    #[test]
+    fn window_starts_after_skipped_midnight() {
+        // Chile moved its clocks from 00:00 to 01:00 on 2022-09-11.
+        let date = NaiveDate::from_ymd_opt(2022, 9, 11).unwrap();
+        let start = local_start_of_day(chrono_tz::America::Santiago, date).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2022, 9, 11, 4, 0, 0).unwrap().timestamp());
+    }
+
+    #[test]
+    fn degree_days_never_settle_on_interpolated_days() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut config = DegreeDayConfig::celsius(chrono_tz::UTC);
+        config.missing = MissingDayPolicy::Interpolate;
+        let contract = WeatherContract::season_degree_days(
+            "hdd".to_string(),
+            "NYC".to_string(),
+            start,
+            3,
+            DegreeDayKind::Heating,
+            config,
+            Vec::new(),
+        );
+
+        // Hourly readings on the first and last day only.
+        let midnight = 1_704_067_200;
+        let readings: Vec<EvidenceReading> = (0..72)
+            .filter(|hour| !(24..48).contains(hour))
+            .map(|hour| EvidenceReading {
+                timestamp: (midnight + hour * 3600) as u64,
+                value: 10.0,
+                data_hash: [0; 32],
+                tx_hash: None,
+                block_number: None,
+            })
+            .collect();
+
+        let error = degree_day_index(&contract, &readings, DegreeDayKind::Heating).unwrap_err();
+        assert_eq!(error, "1 days without an observed daily temperature");
+    }
+}
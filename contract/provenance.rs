@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use sha3::{Digest, Sha3_256};
+use web3::signing;
+use web3::types::Address;
+
+use crate::blockchain_interface::OracleData;
+use crate::data_processor::WeatherDataPoint;
+use crate::location_series::Observation;
+use crate::quality_control::QcFlags;
+
+// The source ID itself is WeatherDataPoint::source; this records how and when the reading arrived.
+
+// As supplied by the reporting oracle: its address and a 65-byte eth_sign signature, both hex.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReporterSignature {
+    pub reporter: String,
+    pub signature: String,
+}
+
+impl ReporterSignature {
+    // The reporter signs the SHA3-256 payload hash as an EIP-191 personal message.
+    pub fn verify(&self, payload_hash: &[u8; 32]) -> bool {
+        let reporter = match Address::from_str(self.reporter.strip_prefix("0x").unwrap_or(&self.reporter)) {
+            Ok(reporter) => reporter,
+            Err(_) => return false,
+        };
+        let signature = match decode_hex(&self.signature) {
+            Some(signature) if signature.len() == 65 => signature,
+            _ => return false,
+        };
+        // eth_sign reports the recovery id as 27 or 28; raw signatures use 0 or 1.
+        let recovery_id = match signature[64] {
+            v @ (0 | 1) => v as i32,
+            v @ (27 | 28) => v as i32 - 27,
+            _ => return false,
+        };
+        let message = signing::hash_message(payload_hash);
+        signing::recover(message.as_bytes(), &signature[..64], recovery_id).is_ok_and(|signer| signer == reporter)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Provenance {
+    pub ingested_at: i64,
+    // SHA3-256 of the upstream record exactly as received.
+    pub payload_hash: [u8; 32],
+    #[serde(default)]
+    pub signature: Option<ReporterSignature>,
+}
+
+impl Provenance {
+    pub fn new(payload: &[u8]) -> Self {
+        Self::from_hash(payload_hash(payload))
+    }
+
+    pub fn from_hash(payload_hash: [u8; 32]) -> Self {
+        Self {
+            ingested_at: Utc::now().timestamp(),
+            payload_hash,
+            signature: None,
+        }
+    }
+
+    pub fn with_signature(mut self, signature: ReporterSignature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+}
+
+pub fn payload_hash(payload: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha3_256::digest(payload));
+    hash
+}
+
+// Hash of a batch submitted as points rather than a raw payload, fed field by field; strings are
+// length-prefixed so adjacent fields cannot run together. Provenance is not part of it.
+pub fn points_hash(points: &[WeatherDataPoint]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    for point in points {
+        hasher.update(point.timestamp.to_le_bytes());
+        for text in [&point.location, &point.source] {
+            hasher.update((text.len() as u64).to_le_bytes());
+            hasher.update(text.as_bytes());
+        }
+        for value in [point.temperature, point.humidity, point.pressure, point.wind_speed, point.wind_direction, point.precipitation] {
+            hasher.update(value.to_bits().to_le_bytes());
+        }
+        hasher.update(point.qc_flags.0.to_le_bytes());
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamRecord {
+    pub payload_hash: [u8; 32],
+    pub payload: String,
+}
+
+// Stored points behind an on-chain reading: the point, its provenance and the raw upstream payload when retained.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamEvidence {
+    pub point: WeatherDataPoint,
+    pub hash_matches: bool,
+    pub upstream: Option<UpstreamRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisputeEvidence {
+    pub reading: OracleData,
+    pub candidates: Vec<UpstreamEvidence>,
+}
+
+impl DisputeEvidence {
+    pub fn matched(&self) -> Option<&UpstreamEvidence> {
+        self.candidates.iter().find(|c| c.hash_matches)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceWeights {
+    pub weights: HashMap<String, f64>,
+    // Weight of unlisted sources; 0 restricts processing to the listed ones.
+    pub default_weight: f64,
+}
+
+impl SourceWeights {
+    pub fn only(sources: &[&str]) -> Self {
+        Self {
+            weights: sources.iter().map(|s| (s.to_string(), 1.0)).collect(),
+            default_weight: 0.0,
+        }
+    }
+
+    pub fn with_weight(mut self, source: &str, weight: f64) -> Self {
+        self.weights.insert(source.to_string(), weight);
+        self
+    }
+
+    pub fn weight(&self, source: &str) -> f64 {
+        self.weights.get(source).copied().unwrap_or(self.default_weight).max(0.0)
+    }
+
+    // Weighted mean of observations sharing a timestamp; directions are averaged as vectors.
+    // Missing values are skipped per variable. QC-failed observations are left out unless nothing else
+    // is available, and the blend keeps the QC flags of the observations it used.
+    pub fn blend(&self, observations: &[(Observation, &str)]) -> Option<Observation> {
+        let weighted: Vec<(&Observation, f64)> = observations
+            .iter()
+            .map(|(observation, source)| (observation, self.weight(source)))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        let weighted = if weighted.iter().any(|(o, _)| !o.qc_flags.failed()) {
+            weighted.into_iter().filter(|(o, _)| !o.qc_flags.failed()).collect()
+        } else {
+            weighted
+        };
+        let first = weighted.first()?.0;
+
+        let mean = |value: fn(&Observation) -> f64| {
+            let (sum, total) = weighted
+                .iter()
+                .filter(|(o, _)| value(o).is_finite())
+                .fold((0.0, 0.0), |(sum, total), (o, w)| (sum + w * value(o), total + w));
+            if total > 0.0 { sum / total } else { f64::NAN }
+        };
+        let (sin, cos) = weighted
+            .iter()
+            .filter(|(o, _)| o.wind_direction.is_finite())
+            .fold((0.0, 0.0), |(sin, cos), (o, w)| {
+                let radians = o.wind_direction.to_radians();
+                (sin + w * radians.sin(), cos + w * radians.cos())
+            });
+        let wind_direction = if sin == 0.0 && cos == 0.0 { f64::NAN } else { sin.atan2(cos).to_degrees().rem_euclid(360.0) };
+
+        Some(Observation {
+            timestamp: first.timestamp,
+            temperature: mean(|o| o.temperature),
+            humidity: mean(|o| o.humidity),
+            pressure: mean(|o| o.pressure),
+            wind_speed: mean(|o| o.wind_speed),
+            wind_direction,
+            precipitation: mean(|o| o.precipitation),
+            qc_flags: QcFlags(weighted.iter().fold(0, |flags, (o, _)| flags | o.qc_flags.0)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::signing::{Key, SecretKey, SecretKeyRef};
+
+    fn sign(key: &SecretKey, payload_hash: &[u8; 32]) -> ReporterSignature {
+        let key = SecretKeyRef::new(key);
+        let signed = key.sign_message(signing::hash_message(payload_hash).as_bytes()).unwrap();
+        let mut bytes = [signed.r.as_bytes(), signed.s.as_bytes()].concat();
+        bytes.push(signed.v as u8 + 27);
+        ReporterSignature {
+            reporter: format!("{:?}", key.address()),
+            signature: format!("0x{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+        }
+    }
+
+    #[test]
+    fn signature_verifies_only_for_its_payload_and_reporter() {
+        let key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let hash = payload_hash(b"KJFK 121851Z 31015KT");
+        let signature = sign(&key, &hash);
+        assert!(signature.verify(&hash));
+        assert!(!signature.verify(&payload_hash(b"KJFK 121851Z 31016KT")));
+
+        let other = SecretKey::from_slice(&[8u8; 32]).unwrap();
+        let forged = ReporterSignature { reporter: sign(&other, &hash).reporter, ..signature.clone() };
+        assert!(!forged.verify(&hash));
+        assert!(!ReporterSignature { signature: "0x12".to_string(), ..signature }.verify(&hash));
+    }
+
+    #[test]
+    fn failed_source_does_not_taint_blend() {
+        let observation = |temperature: f64, qc_flags: QcFlags| Observation {
+            timestamp: 0,
+            temperature,
+            humidity: 50.0,
+            pressure: 1000.0,
+            wind_speed: 1.0,
+            wind_direction: 90.0,
+            precipitation: 0.0,
+            qc_flags,
+        };
+        let weights = SourceWeights { weights: HashMap::new(), default_weight: 1.0 };
+
+        let blended = weights
+            .blend(&[(observation(20.0, QcFlags::CHECKED), "a"), (observation(80.0, QcFlags::CHECKED | QcFlags::RANGE_FAILED), "b")])
+            .unwrap();
+        assert_eq!(blended.temperature, 20.0);
+        assert!(!blended.qc_flags.failed());
+
+        let blended = weights.blend(&[(observation(80.0, QcFlags::RANGE_FAILED), "b")]).unwrap();
+        assert!(blended.qc_flags.failed());
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+use crate::location_series::LocationSeries;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QcFlags(pub u32);
+
+impl QcFlags {
+    pub const CHECKED: QcFlags = QcFlags(1 << 0);
+    pub const RANGE_FAILED: QcFlags = QcFlags(1 << 1);
+    pub const STEP_FAILED: QcFlags = QcFlags(1 << 2);
+    pub const PERSISTENCE_FAILED: QcFlags = QcFlags(1 << 3);
+    pub const CONSISTENCY_FAILED: QcFlags = QcFlags(1 << 4);
+    pub const SPATIAL_FAILED: QcFlags = QcFlags(1 << 5);
+    pub const INTERPOLATED: QcFlags = QcFlags(1 << 6);
+
+    const FAILURES: u32 = Self::RANGE_FAILED.0
+        | Self::STEP_FAILED.0
+        | Self::PERSISTENCE_FAILED.0
+        | Self::CONSISTENCY_FAILED.0
+        | Self::SPATIAL_FAILED.0;
+
+    pub fn empty() -> Self {
+        QcFlags(0)
+    }
+
+    pub fn contains(&self, other: QcFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: QcFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.contains(Self::CHECKED)
+    }
+
+    pub fn failed(&self) -> bool {
+        self.0 & Self::FAILURES != 0
+    }
+
+    pub fn is_interpolated(&self) -> bool {
+        self.contains(Self::INTERPOLATED)
+    }
+
+    // Points that never went through QC are not trusted for settlement.
+    pub fn settlement_eligible(&self) -> bool {
+        self.is_checked() && !self.failed() && !self.is_interpolated()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let all = [
+            (Self::CHECKED, "checked"),
+            (Self::RANGE_FAILED, "range"),
+            (Self::STEP_FAILED, "step"),
+            (Self::PERSISTENCE_FAILED, "persistence"),
+            (Self::CONSISTENCY_FAILED, "consistency"),
+            (Self::SPATIAL_FAILED, "spatial"),
+            (Self::INTERPOLATED, "interpolated"),
+        ];
+        all.iter().filter(|(flag, _)| self.contains(*flag)).map(|(_, name)| *name).collect()
+    }
+}
+
+impl std::ops::BitOr for QcFlags {
+    type Output = QcFlags;
+
+    fn bitor(self, rhs: QcFlags) -> QcFlags {
+        QcFlags(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StepLimit {
+    pub max_change: f64,
+    pub window_secs: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PersistenceLimit {
+    pub window_secs: i64,
+    pub min_variation: f64,
+    pub min_points: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpatialConfig {
+    pub neighbors: HashMap<String, Vec<String>>,
+    pub max_deviation: HashMap<WeatherVariable, f64>,
+    pub time_tolerance_secs: i64,
+    pub min_neighbors: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QcConfig {
+    pub ranges: HashMap<WeatherVariable, (f64, f64)>,
+    // A missing (non-finite) value fails range QC only for these; other variables are checked when present.
+    #[serde(default = "default_required")]
+    pub required: Vec<WeatherVariable>,
+    pub steps: HashMap<WeatherVariable, StepLimit>,
+    pub persistence: HashMap<WeatherVariable, PersistenceLimit>,
+    pub min_humidity_with_precipitation: f64,
+    pub spatial: Option<SpatialConfig>,
+}
+
+impl Default for QcConfig {
+    fn default() -> Self {
+        let mut ranges = HashMap::new();
+        ranges.insert(WeatherVariable::Temperature, (-90.0, 60.0));
+        ranges.insert(WeatherVariable::Humidity, (0.0, 100.0));
+        ranges.insert(WeatherVariable::Pressure, (500.0, 1090.0));
+        ranges.insert(WeatherVariable::WindSpeed, (0.0, 113.0));
+        ranges.insert(WeatherVariable::WindDirection, (0.0, 360.0));
+        ranges.insert(WeatherVariable::Precipitation, (0.0, 500.0));
+
+        let mut steps = HashMap::new();
+        steps.insert(WeatherVariable::Temperature, StepLimit { max_change: 10.0, window_secs: 3600 });
+        steps.insert(WeatherVariable::Humidity, StepLimit { max_change: 50.0, window_secs: 3600 });
+        steps.insert(WeatherVariable::Pressure, StepLimit { max_change: 15.0, window_secs: 3 * 3600 });
+        steps.insert(WeatherVariable::WindSpeed, StepLimit { max_change: 25.0, window_secs: 3600 });
+
+        let mut persistence = HashMap::new();
+        persistence.insert(WeatherVariable::Temperature, PersistenceLimit { window_secs: 6 * 3600, min_variation: 0.1, min_points: 6 });
+        persistence.insert(WeatherVariable::Pressure, PersistenceLimit { window_secs: 6 * 3600, min_variation: 0.1, min_points: 6 });
+        persistence.insert(WeatherVariable::Humidity, PersistenceLimit { window_secs: 12 * 3600, min_variation: 0.1, min_points: 12 });
+
+        Self {
+            ranges,
+            required: default_required(),
+            steps,
+            persistence,
+            min_humidity_with_precipitation: 20.0,
+            spatial: None,
+        }
+    }
+}
+
+// The variables every oracle submission carries.
+fn default_required() -> Vec<WeatherVariable> {
+    vec![WeatherVariable::Temperature, WeatherVariable::Humidity]
+}
+
+pub struct QcContext<'a> {
+    pub history: Option<&'a LocationSeries>,
+    pub neighbors: Vec<&'a LocationSeries>,
+}
+
+pub trait QcCheck: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn check(&self, point: &WeatherDataPoint, context: &QcContext) -> QcFlags;
+}
+
+pub struct RangeCheck {
+    ranges: HashMap<WeatherVariable, (f64, f64)>,
+    required: Vec<WeatherVariable>,
+}
+
+impl QcCheck for RangeCheck {
+    fn name(&self) -> &'static str {
+        "range"
+    }
+
+    fn check(&self, point: &WeatherDataPoint, _context: &QcContext) -> QcFlags {
+        let out_of_range = self.ranges.iter().any(|(variable, (min, max))| {
+            let value = variable.value(point);
+            if !value.is_finite() {
+                return self.required.contains(variable);
+            }
+            value < *min || value > *max
+        });
+
+        if out_of_range { QcFlags::RANGE_FAILED } else { QcFlags::empty() }
+    }
+}
+
+pub struct StepCheck {
+    limits: HashMap<WeatherVariable, StepLimit>,
+}
+
+impl QcCheck for StepCheck {
+    fn name(&self) -> &'static str {
+        "step"
+    }
+
+    fn check(&self, point: &WeatherDataPoint, context: &QcContext) -> QcFlags {
+        let history = match context.history {
+            Some(history) => history,
+            None => return QcFlags::empty(),
+        };
+
+        let before = history.range_between(i64::MIN, point.timestamp);
+        let previous = before.rev().find(|i| !history.qc_flags()[*i].failed());
+
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return QcFlags::empty(),
+        };
+
+        let dt = point.timestamp - history.timestamps()[previous];
+        let jumped = self.limits.iter().any(|(variable, limit)| {
+            dt <= limit.window_secs && (variable.value(point) - history.column(*variable)[previous]).abs() > limit.max_change
+        });
+
+        if jumped { QcFlags::STEP_FAILED } else { QcFlags::empty() }
+    }
+}
+
+pub struct PersistenceCheck {
+    limits: HashMap<WeatherVariable, PersistenceLimit>,
+}
+
+impl QcCheck for PersistenceCheck {
+    fn name(&self) -> &'static str {
+        "persistence"
+    }
+
+    fn check(&self, point: &WeatherDataPoint, context: &QcContext) -> QcFlags {
+        let history = match context.history {
+            Some(history) => history,
+            None => return QcFlags::empty(),
+        };
+
+        let stuck = self.limits.iter().any(|(variable, limit)| {
+            let window = history.range_between(point.timestamp - limit.window_secs, point.timestamp);
+            let values: Vec<f64> = history.column(*variable)[window]
+                .iter()
+                .copied()
+                .chain(std::iter::once(variable.value(point)))
+                .filter(|value| value.is_finite())
+                .collect();
+
+            if values.len() < limit.min_points {
+                return false;
+            }
+
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            max - min < limit.min_variation
+        });
+
+        if stuck { QcFlags::PERSISTENCE_FAILED } else { QcFlags::empty() }
+    }
+}
+
+pub struct ConsistencyCheck {
+    min_humidity_with_precipitation: f64,
+}
+
+impl QcCheck for ConsistencyCheck {
+    fn name(&self) -> &'static str {
+        "consistency"
+    }
+
+    fn check(&self, point: &WeatherDataPoint, _context: &QcContext) -> QcFlags {
+        let rain_without_moisture = point.precipitation > 0.0 && point.humidity < self.min_humidity_with_precipitation;
+
+        if rain_without_moisture {
+            QcFlags::CONSISTENCY_FAILED
+        } else {
+            QcFlags::empty()
+        }
+    }
+}
+
+pub struct SpatialCheck {
+    config: SpatialConfig,
+}
+
+impl QcCheck for SpatialCheck {
+    fn name(&self) -> &'static str {
+        "spatial"
+    }
+
+    fn check(&self, point: &WeatherDataPoint, context: &QcContext) -> QcFlags {
+        let inconsistent = self.config.max_deviation.iter().any(|(variable, max_deviation)| {
+            let mut neighbor_values: Vec<f64> = context.neighbors
+                .iter()
+                .filter_map(|series| {
+                    let tolerance = self.config.time_tolerance_secs;
+                    series
+                        .range_between(point.timestamp - tolerance, point.timestamp + tolerance + 1)
+                        .filter(|i| !series.qc_flags()[*i].failed())
+                        .min_by_key(|i| (series.timestamps()[*i] - point.timestamp).abs())
+                        .map(|i| series.column(*variable)[i])
+                })
+                .filter(|value| value.is_finite())
+                .collect();
+
+            if neighbor_values.len() < self.config.min_neighbors.max(1) {
+                return false;
+            }
+
+            neighbor_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let n = neighbor_values.len();
+            let median = if n.is_multiple_of(2) {
+                (neighbor_values[n / 2 - 1] + neighbor_values[n / 2]) / 2.0
+            } else {
+                neighbor_values[n / 2]
+            };
+
+            (variable.value(point) - median).abs() > *max_deviation
+        });
+
+        if inconsistent { QcFlags::SPATIAL_FAILED } else { QcFlags::empty() }
+    }
+}
+
+pub struct QcPipeline {
+    checks: Vec<Box<dyn QcCheck>>,
+    neighbors: HashMap<String, Vec<String>>,
+}
+
+impl QcPipeline {
+    pub fn empty() -> Self {
+        Self {
+            checks: Vec::new(),
+            neighbors: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: QcConfig) -> Self {
+        let mut pipeline = Self::empty();
+        pipeline.add_check(Box::new(RangeCheck { ranges: config.ranges, required: config.required }));
+        pipeline.add_check(Box::new(StepCheck { limits: config.steps }));
+        pipeline.add_check(Box::new(PersistenceCheck { limits: config.persistence }));
+        pipeline.add_check(Box::new(ConsistencyCheck {
+            min_humidity_with_precipitation: config.min_humidity_with_precipitation,
+        }));
+
+        if let Some(spatial) = config.spatial {
+            pipeline.neighbors = spatial.neighbors.clone();
+            pipeline.add_check(Box::new(SpatialCheck { config: spatial }));
+        }
+
+        pipeline
+    }
+
+    pub fn add_check(&mut self, check: Box<dyn QcCheck>) {
+        self.checks.push(check);
+    }
+
+    pub fn set_neighbors(&mut self, location: String, neighbors: Vec<String>) {
+        self.neighbors.insert(location, neighbors);
+    }
+
+    pub fn neighbors_of(&self, location: &str) -> &[String] {
+        self.neighbors.get(location).map(|n| n.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn check_names(&self) -> Vec<&'static str> {
+        self.checks.iter().map(|c| c.name()).collect()
+    }
+
+    pub fn evaluate(&self, point: &WeatherDataPoint, context: &QcContext) -> QcFlags {
+        self.checks
+            .iter()
+            .fold(QcFlags::CHECKED, |flags, check| flags | check.check(point, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_checked_measured_points_are_settlement_eligible() {
+        assert!(!QcFlags::empty().settlement_eligible());
+        assert!(QcFlags::CHECKED.settlement_eligible());
+        assert!(!(QcFlags::CHECKED | QcFlags::STEP_FAILED).settlement_eligible());
+        assert!(!(QcFlags::CHECKED | QcFlags::INTERPOLATED).settlement_eligible());
+    }
+
+    #[tokio::test]
+    async fn batches_are_checked_without_a_configured_pipeline() {
+        let processor = crate::data_processor::DataProcessor::new();
+        let point = WeatherDataPoint {
+            timestamp: 1_700_000_000,
+            location: "KJFK".to_string(),
+            temperature: 20.0,
+            humidity: 60.0,
+            pressure: 1013.0,
+            wind_speed: 3.0,
+            wind_direction: 180.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::empty(),
+            source: "metar".to_string(),
+            provenance: None,
+        };
+        processor.add_data_point(point).await.unwrap();
+
+        let window = crate::data_query::TimeWindow::new(0, i64::MAX);
+        let stored = processor.query_points("KJFK", window).await;
+        assert!(stored[0].qc_flags.settlement_eligible());
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use web3::types::{Address, U256};
+
+use crate::blockchain_interface::{BlockchainInterface, OracleData};
+use crate::cost_accounting::wei_to_eth;
+use crate::provenance::SourceWeights;
+use crate::units::from_oracle_fixed;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReporterObservation {
+    pub reporter: Address,
+    pub data: OracleData,
+    pub submitted_at: u64,
+    // Dispute counts as read from chain, with the time of each read, so a score as of an
+    // earlier time does not see disputes raised after it.
+    #[serde(default)]
+    pub disputes: Vec<(u64, u64)>,
+}
+
+impl ReporterObservation {
+    pub fn dispute_count_at(&self, as_of: u64) -> u64 {
+        latest_at(&self.disputes, as_of).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationConfig {
+    pub half_life_secs: f64,
+    pub consensus_bucket_secs: u64,
+    pub latency_scale_secs: f64,
+    // Deviations from consensus (°C and % humidity) at which accuracy drops to 1/e.
+    pub deviation_scale: f64,
+    #[serde(default = "default_humidity_deviation_scale")]
+    pub humidity_deviation_scale: f64,
+    pub stake_half_eth: f64,
+    pub prior_score: f64,
+    pub prior_weight: f64,
+    pub weights: ReputationWeights,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationWeights {
+    pub disputes: f64,
+    pub latency: f64,
+    pub accuracy: f64,
+    pub stake: f64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            half_life_secs: 7.0 * 86400.0,
+            consensus_bucket_secs: 3600,
+            latency_scale_secs: 600.0,
+            deviation_scale: 1.0,
+            humidity_deviation_scale: default_humidity_deviation_scale(),
+            stake_half_eth: 1.0,
+            prior_score: 0.5,
+            prior_weight: 5.0,
+            weights: ReputationWeights {
+                disputes: 0.35,
+                latency: 0.15,
+                accuracy: 0.35,
+                stake: 0.15,
+            },
+        }
+    }
+}
+
+fn default_humidity_deviation_scale() -> f64 {
+    5.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreComponents {
+    pub disputes: f64,
+    pub latency: f64,
+    pub accuracy: f64,
+    pub stake: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationScore {
+    pub reporter: Address,
+    pub score: f64,
+    pub components: ScoreComponents,
+    pub submissions: usize,
+    pub disputes: u64,
+    pub effective_samples: f64,
+    pub as_of: u64,
+}
+
+pub struct ReputationTracker {
+    config: ReputationConfig,
+    observations: Vec<ReporterObservation>,
+    known_hashes: HashSet<[u8; 32]>,
+    stakes: HashMap<Address, Vec<(u64, U256)>>,
+    history: HashMap<Address, Vec<(u64, f64)>>,
+}
+
+impl ReputationTracker {
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            observations: Vec::new(),
+            known_hashes: HashSet::new(),
+            stakes: HashMap::new(),
+            history: HashMap::new(),
+        }
+    }
+
+    pub fn record_observation(&mut self, reporter: Address, data: OracleData, submitted_at: u64) {
+        if !self.known_hashes.insert(data.data_hash) {
+            return;
+        }
+
+        self.observations.push(ReporterObservation {
+            reporter,
+            data,
+            submitted_at,
+            disputes: Vec::new(),
+        });
+    }
+
+    // Records the reporter's stake from `since` on; earlier scores keep the stake they saw.
+    pub fn set_stake(&mut self, reporter: Address, stake: U256, since: u64) {
+        record_change(self.stakes.entry(reporter).or_default(), since, stake);
+    }
+
+    // `observed_at` is when the chain was read; scores before it keep the earlier counts.
+    pub async fn refresh_from_chain(&mut self, interface: &BlockchainInterface, observed_at: u64) -> Result<(), Box<dyn std::error::Error>> {
+        for observation in self.observations.iter_mut() {
+            let count = interface.get_dispute_count(observation.data.data_hash).await?.low_u64();
+            record_change(&mut observation.disputes, observed_at, count);
+        }
+
+        let mut reporters: Vec<Address> = self.observations.iter().map(|o| o.reporter).collect();
+        reporters.sort();
+        reporters.dedup();
+
+        for reporter in reporters {
+            let stake = interface.get_stake_balance(reporter).await?;
+            self.set_stake(reporter, stake, observed_at);
+        }
+
+        Ok(())
+    }
+
+    pub fn score(&self, reporter: Address, as_of: u64) -> Option<ReputationScore> {
+        let deviations = self.consensus_deviations(as_of);
+
+        let mut weight_sum = 0.0;
+        let mut dispute_rate = 0.0;
+        let mut latency = 0.0;
+        let mut deviation = 0.0;
+        let mut deviation_weight = 0.0;
+        let mut submissions = 0;
+        let mut disputes = 0;
+
+        for (index, observation) in self.observations.iter().enumerate() {
+            if observation.reporter != reporter || observation.submitted_at > as_of {
+                continue;
+            }
+
+            let age = (as_of - observation.submitted_at) as f64;
+            let weight = 0.5f64.powf(age / self.config.half_life_secs);
+
+            let dispute_count = observation.dispute_count_at(as_of);
+            submissions += 1;
+            disputes += dispute_count;
+            weight_sum += weight;
+            dispute_rate += weight * dispute_count as f64;
+            latency += weight * observation.submitted_at.saturating_sub(observation.data.timestamp) as f64;
+
+            if let Some(d) = deviations.get(&index) {
+                deviation += weight * d;
+                deviation_weight += weight;
+            }
+        }
+
+        if submissions == 0 {
+            return None;
+        }
+
+        let components = ScoreComponents {
+            disputes: 1.0 / (1.0 + dispute_rate / weight_sum),
+            latency: (-(latency / weight_sum) / self.config.latency_scale_secs).exp(),
+            accuracy: if deviation_weight > 0.0 {
+                (-(deviation / deviation_weight)).exp()
+            } else {
+                self.config.prior_score
+            },
+            stake: self.stake_component(reporter, as_of),
+        };
+
+        let weights = &self.config.weights;
+        let total_weight = weights.disputes + weights.latency + weights.accuracy + weights.stake;
+        let raw = (weights.disputes * components.disputes
+            + weights.latency * components.latency
+            + weights.accuracy * components.accuracy
+            + weights.stake * components.stake)
+            / total_weight;
+
+        // Shrink towards the prior until a reporter has enough recent history.
+        let confidence = weight_sum / (weight_sum + self.config.prior_weight);
+        let score = confidence * raw + (1.0 - confidence) * self.config.prior_score;
+
+        Some(ReputationScore {
+            reporter,
+            score,
+            components,
+            submissions,
+            disputes,
+            effective_samples: weight_sum,
+            as_of,
+        })
+    }
+
+    pub fn score_all(&self, as_of: u64) -> Vec<ReputationScore> {
+        let mut reporters: Vec<Address> = self.observations.iter().map(|o| o.reporter).collect();
+        reporters.sort();
+        reporters.dedup();
+
+        let mut scores: Vec<ReputationScore> = reporters
+            .into_iter()
+            .filter_map(|reporter| self.score(reporter, as_of))
+            .collect();
+        scores.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        scores
+    }
+
+    pub fn snapshot(&mut self, as_of: u64) -> Vec<ReputationScore> {
+        let scores = self.score_all(as_of);
+        for score in &scores {
+            self.history.entry(score.reporter).or_default().push((as_of, score.score));
+        }
+        scores
+    }
+
+    pub fn history(&self, reporter: Address) -> &[(u64, f64)] {
+        self.history.get(&reporter).map(|h| h.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn source_weights(&self, reporters: &[Address], as_of: u64) -> HashMap<Address, f64> {
+        let scores: Vec<(Address, f64)> = reporters
+            .iter()
+            .map(|reporter| (*reporter, self.reporter_score(*reporter, as_of)))
+            .collect();
+
+        let total: f64 = scores.iter().map(|(_, s)| s).sum();
+        scores
+            .into_iter()
+            .map(|(reporter, score)| {
+                let weight = if total > 0.0 { score / total } else { 1.0 / reporters.len() as f64 };
+                (reporter, weight)
+            })
+            .collect()
+    }
+
+    // Weights for DataProcessor::process_by_source, given which reporter stands behind each source.
+    // Blending normalises weights, so raw scores are used; unlisted sources get the prior score.
+    pub fn processing_weights(&self, sources: &HashMap<String, Address>, as_of: u64) -> SourceWeights {
+        SourceWeights {
+            weights: sources
+                .iter()
+                .map(|(source, reporter)| (source.clone(), self.reporter_score(*reporter, as_of)))
+                .collect(),
+            default_weight: self.config.prior_score,
+        }
+    }
+
+    pub fn weighted_consensus(&self, readings: &[(Address, f64)], as_of: u64) -> Option<f64> {
+        if readings.is_empty() {
+            return None;
+        }
+
+        let reporters: Vec<Address> = readings.iter().map(|(reporter, _)| *reporter).collect();
+        let weights = self.source_weights(&reporters, as_of);
+
+        let mut weighted_sum = 0.0;
+        let mut weight_total = 0.0;
+        for (reporter, value) in readings {
+            let weight = weights.get(reporter).copied().unwrap_or(0.0);
+            weighted_sum += weight * value;
+            weight_total += weight;
+        }
+
+        if weight_total > 0.0 { Some(weighted_sum / weight_total) } else { None }
+    }
+
+    fn reporter_score(&self, reporter: Address, as_of: u64) -> f64 {
+        self.score(reporter, as_of).map(|s| s.score).unwrap_or(self.config.prior_score)
+    }
+
+    fn stake_component(&self, reporter: Address, as_of: u64) -> f64 {
+        let stake_eth = self.stakes
+            .get(&reporter)
+            .and_then(|stakes| latest_at(stakes, as_of))
+            .map(|stake| wei_to_eth(*stake))
+            .unwrap_or(0.0);
+        stake_eth / (stake_eth + self.config.stake_half_eth)
+    }
+
+    // Only submissions known by `as_of` form the consensus, so past scores never see later data.
+    // Deviations are scaled per variable and averaged over temperature and humidity.
+    fn consensus_deviations(&self, as_of: u64) -> HashMap<usize, f64> {
+        let mut buckets: HashMap<(String, u64), Vec<usize>> = HashMap::new();
+        let bucket_size = self.config.consensus_bucket_secs.max(1);
+
+        for (index, observation) in self.observations.iter().enumerate() {
+            if observation.submitted_at > as_of {
+                continue;
+            }
+            let bucket = observation.data.timestamp / bucket_size;
+            buckets.entry((observation.data.city.clone(), bucket)).or_default().push(index);
+        }
+
+        let mut deviations = HashMap::new();
+        for indices in buckets.values() {
+            let mut reporters: Vec<Address> = indices.iter().map(|&i| self.observations[i].reporter).collect();
+            reporters.sort();
+            reporters.dedup();
+            if reporters.len() < 2 {
+                continue;
+            }
+
+            let variables: [(FixedValue, f64); 2] = [
+                (|data| data.temperature, self.config.deviation_scale),
+                (|data| data.humidity, self.config.humidity_deviation_scale),
+            ];
+            for (value_of, scale) in variables {
+                let mut values: Vec<f64> = indices.iter().map(|&i| from_oracle_fixed(value_of(&self.observations[i].data))).collect();
+                values.sort_by(|a, b| a.total_cmp(b));
+                let median = median_of_sorted(&values);
+
+                for &i in indices {
+                    let value = from_oracle_fixed(value_of(&self.observations[i].data));
+                    *deviations.entry(i).or_insert(0.0) += (value - median).abs() / scale.max(f64::EPSILON) / variables.len() as f64;
+                }
+            }
+        }
+
+        deviations
+    }
+}
+
+type FixedValue = fn(&OracleData) -> i64;
+
+// Histories stay sorted by time so reads can binary search; repeats of the value before are skipped.
+fn record_change<T: PartialEq>(history: &mut Vec<(u64, T)>, at: u64, value: T) {
+    let pos = history.partition_point(|(time, _)| *time <= at);
+    if pos == 0 || history[pos - 1].1 != value {
+        history.insert(pos, (at, value));
+    }
+}
+
+fn latest_at<T>(history: &[(u64, T)], as_of: u64) -> Option<&T> {
+    let end = history.partition_point(|(time, _)| *time <= as_of);
+    end.checked_sub(1).map(|i| &history[i].1)
+}
+
+fn median_of_sorted(values: &[f64]) -> f64 {
+    let n = values.len();
+    if n.is_multiple_of(2) {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    } else {
+        values[n / 2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain_interface::compute_data_hash;
+
+    fn data(temperature: i64, timestamp: u64) -> OracleData {
+        OracleData {
+            city: "NYC".to_string(),
+            temperature,
+            humidity: 5000,
+            timestamp,
+            data_hash: compute_data_hash("NYC", temperature, 5000, timestamp),
+        }
+    }
+
+    #[test]
+    fn scores_ignore_submissions_after_as_of() {
+        let (a, b) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+        let mut tracker = ReputationTracker::new(ReputationConfig::default());
+        tracker.record_observation(a, data(2000, 1000), 1010);
+        tracker.record_observation(a, data(2000, 1000), 1010);
+        let before = tracker.score(a, 2000).unwrap();
+
+        // A later, wildly different reading from b lands in the same consensus bucket.
+        tracker.record_observation(b, data(4000, 1001), 5000);
+        let after = tracker.score(a, 2000).unwrap();
+
+        assert_eq!(before.submissions, 1);
+        assert_eq!(before.components.accuracy, after.components.accuracy);
+        assert!(tracker.score(a, 6000).unwrap().components.accuracy < after.components.accuracy);
+    }
+
+    #[test]
+    fn later_disputes_and_stake_changes_leave_past_scores_alone() {
+        let a = Address::from_low_u64_be(1);
+        let mut tracker = ReputationTracker::new(ReputationConfig::default());
+        tracker.record_observation(a, data(2000, 1000), 1010);
+        tracker.set_stake(a, U256::exp10(18), 1000);
+        let before = tracker.score(a, 2000).unwrap();
+
+        tracker.observations[0].disputes.push((3000, 2));
+        tracker.set_stake(a, U256::zero(), 3000);
+        let replayed = tracker.score(a, 2000).unwrap();
+        let now = tracker.score(a, 4000).unwrap();
+
+        assert_eq!(replayed.score, before.score);
+        assert_eq!(replayed.disputes, 0);
+        assert_eq!(replayed.components.stake, 0.5);
+        assert_eq!(now.disputes, 2);
+        assert_eq!(now.components.stake, 0.0);
+    }
+
+    #[test]
+    fn histories_stay_ordered_when_recorded_out_of_order() {
+        let mut history = Vec::new();
+        record_change(&mut history, 300, 3);
+        record_change(&mut history, 100, 1);
+        record_change(&mut history, 200, 1);
+
+        assert_eq!(history, vec![(100, 1), (300, 3)]);
+        assert_eq!(latest_at(&history, 99), None);
+        assert_eq!(latest_at(&history, 250), Some(&1));
+        assert_eq!(latest_at(&history, 300), Some(&3));
+    }
+}
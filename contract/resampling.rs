@@ -0,0 +1,296 @@
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::{local_start_of_day, WeatherVariable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BucketInterval {
+    Fixed(i64),
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BucketAggregation {
+    Mean,
+    Max,
+    Min,
+    Sum,
+    First,
+    Last,
+    VectorMean,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResampleConfig {
+    pub interval: BucketInterval,
+    pub timezone: Tz,
+    pub aggregations: Vec<(WeatherVariable, BucketAggregation)>,
+    pub expected_interval_secs: Option<i64>,
+    pub min_coverage: f64,
+    // QC-failed points are never aggregated; gap-filled ones only when asked for.
+    #[serde(default)]
+    pub include_interpolated: bool,
+}
+
+impl ResampleConfig {
+    pub fn new(interval: BucketInterval, timezone: Tz) -> Self {
+        Self {
+            interval,
+            timezone,
+            aggregations: vec![
+                (WeatherVariable::Temperature, BucketAggregation::Mean),
+                (WeatherVariable::Temperature, BucketAggregation::Max),
+                (WeatherVariable::Temperature, BucketAggregation::Min),
+                (WeatherVariable::Humidity, BucketAggregation::Mean),
+                (WeatherVariable::Pressure, BucketAggregation::Mean),
+                (WeatherVariable::WindSpeed, BucketAggregation::VectorMean),
+                (WeatherVariable::WindDirection, BucketAggregation::VectorMean),
+                (WeatherVariable::Precipitation, BucketAggregation::Sum),
+            ],
+            expected_interval_secs: None,
+            min_coverage: 0.9,
+            include_interpolated: false,
+        }
+    }
+
+    pub fn including_interpolated(mut self) -> Self {
+        self.include_interpolated = true;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let BucketInterval::Fixed(seconds) = self.interval {
+            if seconds <= 0 {
+                return Err(format!("Fixed bucket interval must be positive, got {}s", seconds).into());
+            }
+        }
+        if self.expected_interval_secs.is_some_and(|interval| interval <= 0) {
+            return Err("Expected interval must be positive".into());
+        }
+        Ok(())
+    }
+
+    pub fn hourly(timezone: Tz) -> Self {
+        Self::new(BucketInterval::Hourly, timezone)
+    }
+
+    pub fn daily(timezone: Tz) -> Self {
+        Self::new(BucketInterval::Daily, timezone)
+    }
+
+    pub fn monthly(timezone: Tz) -> Self {
+        Self::new(BucketInterval::Monthly, timezone)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketValue {
+    pub variable: WeatherVariable,
+    pub aggregation: BucketAggregation,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResampledBucket {
+    pub start: i64,
+    pub end: i64,
+    pub count: usize,
+    pub expected: Option<usize>,
+    pub complete: bool,
+    pub values: Vec<BucketValue>,
+}
+
+impl ResampledBucket {
+    pub fn get(&self, variable: WeatherVariable, aggregation: BucketAggregation) -> Option<f64> {
+        self.values
+            .iter()
+            .find(|v| v.variable == variable && v.aggregation == aggregation)
+            .map(|v| v.value)
+    }
+
+    pub fn local_date(&self, timezone: Tz) -> Option<NaiveDate> {
+        Some(timezone.timestamp_opt(self.start, 0).single()?.date_naive())
+    }
+}
+
+pub fn resample(points: &[WeatherDataPoint], config: &ResampleConfig) -> Result<Vec<ResampledBucket>, Box<dyn std::error::Error>> {
+    config.validate()?;
+
+    let mut sorted: Vec<&WeatherDataPoint> = points
+        .iter()
+        .filter(|p| !p.qc_flags.failed() && (config.include_interpolated || !p.qc_flags.is_interpolated()))
+        .collect();
+    if sorted.is_empty() {
+        return Ok(Vec::new());
+    }
+    sorted.sort_by_key(|p| p.timestamp);
+
+    let expected_interval = config.expected_interval_secs.or_else(|| median_spacing(&sorted));
+
+    let mut buckets = Vec::new();
+    let out_of_range = |timestamp: i64| format!("Timestamp {} is outside the supported range", timestamp);
+    let (mut start, mut end) = bucket_bounds(sorted[0].timestamp, config).ok_or_else(|| out_of_range(sorted[0].timestamp))?;
+    let last_timestamp = sorted[sorted.len() - 1].timestamp;
+    let mut cursor = 0;
+
+    loop {
+        let begin = cursor;
+        while cursor < sorted.len() && sorted[cursor].timestamp < end {
+            cursor += 1;
+        }
+
+        buckets.push(build_bucket(start, end, &sorted[begin..cursor], config, expected_interval));
+
+        if end > last_timestamp {
+            break;
+        }
+        (start, end) = bucket_bounds(end, config).ok_or_else(|| out_of_range(end))?;
+    }
+
+    Ok(buckets)
+}
+
+fn build_bucket(
+    start: i64,
+    end: i64,
+    points: &[&WeatherDataPoint],
+    config: &ResampleConfig,
+    expected_interval: Option<i64>,
+) -> ResampledBucket {
+    let expected = expected_interval
+        .filter(|interval| *interval > 0)
+        .map(|interval| ((end - start) / interval).max(1) as usize);
+
+    let complete = match expected {
+        Some(expected) => points.len() as f64 >= (expected as f64 * config.min_coverage).ceil(),
+        None => !points.is_empty(),
+    };
+
+    let values = if points.is_empty() {
+        Vec::new()
+    } else {
+        config.aggregations
+            .iter()
+            .filter_map(|(variable, aggregation)| {
+                aggregate(points, *variable, *aggregation).map(|value| BucketValue {
+                    variable: *variable,
+                    aggregation: *aggregation,
+                    value,
+                })
+            })
+            .collect()
+    };
+
+    ResampledBucket {
+        start,
+        end,
+        count: points.len(),
+        expected,
+        complete,
+        values,
+    }
+}
+
+// Missing (non-finite) values are skipped; a bucket with none left has no value for the variable.
+fn aggregate(points: &[&WeatherDataPoint], variable: WeatherVariable, aggregation: BucketAggregation) -> Option<f64> {
+    let values: Vec<f64> = points.iter().map(|p| variable.value(p)).filter(|v| v.is_finite()).collect();
+    let mean = || (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+
+    match aggregation {
+        BucketAggregation::Mean => mean(),
+        BucketAggregation::Max => values.iter().copied().reduce(f64::max),
+        BucketAggregation::Min => values.iter().copied().reduce(f64::min),
+        BucketAggregation::Sum => (!values.is_empty()).then(|| values.iter().sum()),
+        BucketAggregation::First => values.first().copied(),
+        BucketAggregation::Last => values.last().copied(),
+        BucketAggregation::VectorMean => match variable {
+            WeatherVariable::WindSpeed | WeatherVariable::WindDirection => {
+                let (u, v) = mean_wind_vector(points)?;
+                Some(if variable == WeatherVariable::WindSpeed { (u * u + v * v).sqrt() } else { vector_direction(u, v) })
+            }
+            _ => mean(),
+        },
+    }
+}
+
+// Meteorological convention: direction is where the wind blows from, clockwise from north.
+pub fn wind_components(speed: f64, direction_deg: f64) -> (f64, f64) {
+    let radians = direction_deg.to_radians();
+    (-speed * radians.sin(), -speed * radians.cos())
+}
+
+pub fn vector_direction(u: f64, v: f64) -> f64 {
+    if u == 0.0 && v == 0.0 {
+        return 0.0;
+    }
+    (u.atan2(v).to_degrees() + 180.0).rem_euclid(360.0)
+}
+
+fn mean_wind_vector(points: &[&WeatherDataPoint]) -> Option<(f64, f64)> {
+    let (u_sum, v_sum, n) = points
+        .iter()
+        .filter(|p| p.wind_speed.is_finite() && p.wind_direction.is_finite())
+        .fold((0.0, 0.0, 0), |(u_acc, v_acc, n), p| {
+            let (u, v) = wind_components(p.wind_speed, p.wind_direction);
+            (u_acc + u, v_acc + v, n + 1)
+        });
+    (n > 0).then(|| (u_sum / n as f64, v_sum / n as f64))
+}
+
+fn median_spacing(points: &[&WeatherDataPoint]) -> Option<i64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let mut gaps: Vec<i64> = points
+        .windows(2)
+        .map(|w| w[1].timestamp - w[0].timestamp)
+        .filter(|gap| *gap > 0)
+        .collect();
+    if gaps.is_empty() {
+        return None;
+    }
+
+    gaps.sort_unstable();
+    Some(gaps[gaps.len() / 2])
+}
+
+// None for an invalid interval or a timestamp chrono cannot represent.
+pub fn bucket_bounds(timestamp: i64, config: &ResampleConfig) -> Option<(i64, i64)> {
+    let tz = config.timezone;
+
+    match config.interval {
+        BucketInterval::Fixed(seconds) => {
+            if seconds <= 0 {
+                return None;
+            }
+            let start = timestamp.div_euclid(seconds).checked_mul(seconds)?;
+            Some((start, start.checked_add(seconds)?))
+        }
+        BucketInterval::Hourly => {
+            let local = tz.timestamp_opt(timestamp, 0).single()?;
+            let offset = local.naive_local().and_utc().timestamp() - timestamp;
+            let local_start = local.naive_local().date().and_hms_opt(local.hour(), 0, 0)?;
+            let start = local_start.and_utc().timestamp() - offset;
+            Some((start, start + 3600))
+        }
+        BucketInterval::Daily => {
+            let date = tz.timestamp_opt(timestamp, 0).single()?.date_naive();
+            Some((local_start_of_day(tz, date)?, local_start_of_day(tz, date.checked_add_signed(Duration::days(1))?)?))
+        }
+        BucketInterval::Monthly => {
+            let date = tz.timestamp_opt(timestamp, 0).single()?.date_naive();
+            let first = NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?;
+            let next = if date.month() == 12 {
+                NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)?
+            };
+            Some((local_start_of_day(tz, first)?, local_start_of_day(tz, next)?))
+        }
+    }
+}
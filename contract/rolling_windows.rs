@@ -0,0 +1,377 @@
+use std::collections::{HashMap, VecDeque};
+use serde::{Serialize, Deserialize};
+use tokio::sync::broadcast;
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+use crate::location_series::LocationSeries;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RollingWindowSpec {
+    pub variable: WeatherVariable,
+    pub duration_secs: i64,
+}
+
+impl RollingWindowSpec {
+    pub fn hours(variable: WeatherVariable, hours: i64) -> Self {
+        Self { variable, duration_secs: hours * 3600 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RollingStat {
+    Mean,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingThreshold {
+    pub location: Option<String>,
+    pub spec: RollingWindowSpec,
+    pub stat: RollingStat,
+    pub level: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingConfig {
+    pub windows: Vec<RollingWindowSpec>,
+    pub thresholds: Vec<RollingThreshold>,
+    pub channel_capacity: usize,
+}
+
+impl RollingConfig {
+    pub fn standard(variables: &[WeatherVariable]) -> Self {
+        let windows = variables
+            .iter()
+            .flat_map(|variable| [1, 24, 24 * 7].map(|hours| RollingWindowSpec::hours(*variable, hours)))
+            .collect();
+
+        Self {
+            windows,
+            thresholds: Vec::new(),
+            channel_capacity: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RollingValue {
+    pub spec: RollingWindowSpec,
+    pub end: i64,
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl RollingValue {
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { f64::NAN } else { self.sum / self.count as f64 }
+    }
+
+    pub fn get(&self, stat: RollingStat) -> f64 {
+        match stat {
+            RollingStat::Mean => self.mean(),
+            RollingStat::Min => self.min,
+            RollingStat::Max => self.max,
+            RollingStat::Sum => self.sum,
+            RollingStat::Count => self.count as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossingDirection {
+    Upward,
+    Downward,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RollingEvent {
+    Changed {
+        location: String,
+        value: RollingValue,
+    },
+    ThresholdCrossed {
+        location: String,
+        threshold: RollingThreshold,
+        direction: CrossingDirection,
+        previous: f64,
+        value: RollingValue,
+    },
+}
+
+// Window covering (newest - duration, newest]; monotonic deques keep min and max amortised O(1).
+// Only finite values are pushed, so a missing reading cannot poison the running sum.
+#[derive(Debug, Default)]
+struct WindowState {
+    samples: VecDeque<(i64, f64)>,
+    sum: f64,
+    max_candidates: VecDeque<(i64, f64)>,
+    min_candidates: VecDeque<(i64, f64)>,
+    last: Option<RollingValue>,
+}
+
+impl WindowState {
+    fn newest(&self) -> Option<i64> {
+        self.samples.back().map(|(ts, _)| *ts)
+    }
+
+    fn push(&mut self, timestamp: i64, value: f64, duration_secs: i64) {
+        self.samples.push_back((timestamp, value));
+        self.sum += value;
+
+        while self.max_candidates.back().is_some_and(|(_, v)| *v <= value) {
+            self.max_candidates.pop_back();
+        }
+        self.max_candidates.push_back((timestamp, value));
+        while self.min_candidates.back().is_some_and(|(_, v)| *v >= value) {
+            self.min_candidates.pop_back();
+        }
+        self.min_candidates.push_back((timestamp, value));
+
+        let cutoff = timestamp - duration_secs;
+        while self.samples.front().is_some_and(|(ts, _)| *ts <= cutoff) {
+            let (_, old) = self.samples.pop_front().unwrap();
+            self.sum -= old;
+        }
+        while self.max_candidates.front().is_some_and(|(ts, _)| *ts <= cutoff) {
+            self.max_candidates.pop_front();
+        }
+        while self.min_candidates.front().is_some_and(|(ts, _)| *ts <= cutoff) {
+            self.min_candidates.pop_front();
+        }
+    }
+
+    fn rebuild(&mut self, series: &LocationSeries, spec: &RollingWindowSpec) {
+        let last = self.last.take();
+        *self = WindowState { last, ..WindowState::default() };
+
+        let end = match series.timestamps().last() {
+            Some(end) => *end,
+            None => return,
+        };
+        let values = series.column(spec.variable);
+        for i in series.range_between(end - spec.duration_secs + 1, end + 1) {
+            if !series.qc_flags()[i].failed() && values[i].is_finite() {
+                self.push(series.timestamps()[i], values[i], spec.duration_secs);
+            }
+        }
+    }
+
+    fn value(&self, spec: RollingWindowSpec) -> Option<RollingValue> {
+        Some(RollingValue {
+            spec,
+            end: self.newest()?,
+            count: self.samples.len(),
+            sum: self.sum,
+            min: self.min_candidates.front()?.1,
+            max: self.max_candidates.front()?.1,
+        })
+    }
+}
+
+pub struct RollingWindows {
+    windows: Vec<RollingWindowSpec>,
+    thresholds: Vec<RollingThreshold>,
+    states: HashMap<(String, RollingWindowSpec), WindowState>,
+    sender: broadcast::Sender<RollingEvent>,
+}
+
+impl RollingWindows {
+    pub fn new(config: RollingConfig) -> Self {
+        let (sender, _) = broadcast::channel(config.channel_capacity.max(1));
+        Self {
+            windows: config.windows,
+            thresholds: config.thresholds,
+            states: HashMap::new(),
+            sender,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RollingEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn add_threshold(&mut self, threshold: RollingThreshold) {
+        self.thresholds.push(threshold);
+    }
+
+    pub fn values(&self, location: &str) -> Vec<RollingValue> {
+        self.windows
+            .iter()
+            .filter_map(|spec| self.states.get(&(location.to_string(), *spec)))
+            .filter_map(|state| state.last)
+            .collect()
+    }
+
+    // Called once per location after a batch has been applied to `series`, with the batch's points for that
+    // location in order; `replaced` is set for points that overwrote earlier data. In-order points are pushed
+    // one at a time; otherwise the series already holds the whole batch, so the window is rebuilt once.
+    pub fn observe(&mut self, series: &LocationSeries, points: &[(&WeatherDataPoint, bool)]) {
+        let location = series.location();
+        for spec in self.windows.clone() {
+            let state = self.states.entry((location.to_string(), spec)).or_default();
+
+            let mut newest = state.newest();
+            let in_order = points.iter().all(|(point, replaced)| {
+                let in_order = !replaced && newest.is_none_or(|newest| point.timestamp >= newest);
+                newest = newest.max(Some(point.timestamp));
+                in_order
+            });
+
+            if in_order {
+                for (point, _) in points {
+                    let value = spec.variable.value(point);
+                    if !point.qc_flags.failed() && value.is_finite() {
+                        state.push(point.timestamp, value, spec.duration_secs);
+                        publish(&self.sender, &self.thresholds, location, spec, state);
+                    }
+                }
+            } else {
+                state.rebuild(series, &spec);
+                publish(&self.sender, &self.thresholds, location, spec, state);
+            }
+        }
+    }
+}
+
+// Sends the window's value if it changed, and any threshold it crossed doing so.
+fn publish(
+    sender: &broadcast::Sender<RollingEvent>,
+    thresholds: &[RollingThreshold],
+    location: &str,
+    spec: RollingWindowSpec,
+    state: &mut WindowState,
+) {
+    let current = match state.value(spec) {
+        Some(current) => current,
+        None => return,
+    };
+    let previous = state.last.replace(current);
+    if previous == Some(current) {
+        return;
+    }
+
+    // Send errors only mean nobody is subscribed right now.
+    let _ = sender.send(RollingEvent::Changed {
+        location: location.to_string(),
+        value: current,
+    });
+
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return,
+    };
+    for threshold in thresholds {
+        let applies = threshold.spec == spec && threshold.location.as_deref().is_none_or(|l| l == location);
+        if !applies {
+            continue;
+        }
+
+        let (before, after) = (previous.get(threshold.stat), current.get(threshold.stat));
+        let direction = if before < threshold.level && after >= threshold.level {
+            CrossingDirection::Upward
+        } else if before >= threshold.level && after < threshold.level {
+            CrossingDirection::Downward
+        } else {
+            continue;
+        };
+
+        let _ = sender.send(RollingEvent::ThresholdCrossed {
+            location: location.to_string(),
+            threshold: threshold.clone(),
+            direction,
+            previous: before,
+            value: current,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality_control::QcFlags;
+
+    const T: i64 = 1_700_000_000;
+
+    fn point(timestamp: i64, temperature: f64) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: "KJFK".to_string(),
+            temperature,
+            humidity: 50.0,
+            pressure: 1013.0,
+            wind_speed: 3.0,
+            wind_direction: 180.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::default(),
+            source: "metar".to_string(),
+            provenance: None,
+        }
+    }
+
+    fn windows() -> RollingWindows {
+        RollingWindows::new(RollingConfig {
+            windows: vec![RollingWindowSpec::hours(WeatherVariable::Temperature, 24)],
+            thresholds: Vec::new(),
+            channel_capacity: 64,
+        })
+    }
+
+    // Applies a batch the way DataProcessor does: every point first, then one observe call.
+    fn apply(rolling: &mut RollingWindows, series: &mut LocationSeries, batch: &[WeatherDataPoint]) {
+        for point in batch {
+            let at = series.range_between(point.timestamp, point.timestamp + 1).end;
+            series.insert(at, point);
+        }
+        let observed: Vec<(&WeatherDataPoint, bool)> = batch.iter().map(|p| (p, false)).collect();
+        rolling.observe(series, &observed);
+    }
+
+    #[test]
+    fn late_point_in_batch_does_not_double_count_newer_ones() {
+        let mut rolling = windows();
+        let mut series = LocationSeries::new("KJFK".to_string(), None);
+        apply(&mut rolling, &mut series, &[point(T + 5 * 3600, 10.0)]);
+        apply(&mut rolling, &mut series, &[point(T, 20.0), point(T + 6 * 3600, 30.0)]);
+
+        let value = rolling.values("KJFK")[0];
+        assert_eq!(value.count, 3);
+        assert_eq!(value.sum, 60.0);
+        assert_eq!((value.min, value.max, value.end), (10.0, 30.0, T + 6 * 3600));
+    }
+
+    #[test]
+    fn in_order_points_slide_the_window_and_skip_missing_values() {
+        let mut rolling = windows();
+        let mut series = LocationSeries::new("KJFK".to_string(), None);
+        apply(&mut rolling, &mut series, &[point(T, 10.0), point(T + 3600, f64::NAN), point(T + 7200, 12.0)]);
+        apply(&mut rolling, &mut series, &[point(T + 25 * 3600, 14.0)]);
+
+        let value = rolling.values("KJFK")[0];
+        assert_eq!((value.count, value.sum), (2, 26.0));
+        assert_eq!(value.mean(), 13.0);
+    }
+
+    #[test]
+    fn threshold_crossing_is_published_once() {
+        let mut rolling = windows();
+        let spec = RollingWindowSpec::hours(WeatherVariable::Temperature, 24);
+        rolling.add_threshold(RollingThreshold { location: None, spec, stat: RollingStat::Max, level: 30.0 });
+        let mut events = rolling.subscribe();
+        let mut series = LocationSeries::new("KJFK".to_string(), None);
+
+        apply(&mut rolling, &mut series, &[point(T, 20.0), point(T + 3600, 31.0), point(T + 7200, 32.0)]);
+        let crossings: Vec<CrossingDirection> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|event| match event {
+                RollingEvent::ThresholdCrossed { direction, .. } => Some(direction),
+                RollingEvent::Changed { .. } => None,
+            })
+            .collect();
+        assert_eq!(crossings, vec![CrossingDirection::Upward]);
+    }
+}
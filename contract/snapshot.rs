@@ -0,0 +1,363 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Serialize, Deserialize};
+
+use crate::climatology::ClimateNormals;
+use crate::data_processor::ProcessingStats;
+use crate::data_query::WeatherVariable;
+use crate::location_series::{LocationSeries, SeriesColumns};
+use crate::provenance::{Provenance, UpstreamRecord};
+use crate::quality_control::QcFlags;
+use crate::station_registry::StationRegistry;
+
+// Layout: magic, little-endian u32 version, then one gzip stream of sections.
+// Each section is a u64 length, a CRC32 and the payload; the first is the JSON manifest,
+// then one binary section per location (JSON header followed by fixed-width columns).
+// Version 2 added the provenance id column and the upstream payloads.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"WXSNAP\0\0";
+pub const SNAPSHOT_VERSION: u32 = 2;
+const SECTION_HEADER_LEN: usize = 12;
+
+// Timestamp, six measurements, QC flags, source id and, from version 2, provenance id.
+fn bytes_per_row(version: u32) -> usize {
+    let v1 = 8 + 6 * 8 + 4 + 4;
+    if version >= 2 { v1 + 4 } else { v1 }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub version: u32,
+    pub created_at: i64,
+    pub locations: usize,
+    pub points: usize,
+}
+
+// Rolling windows and their subscribers are not captured; they refill from new ingestion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub info: SnapshotInfo,
+    pub stats: ProcessingStats,
+    pub stations: StationRegistry,
+    pub normals: Vec<ClimateNormals>,
+    // Written as binary sections after the manifest rather than inside it.
+    #[serde(skip)]
+    pub series: Vec<LocationSeries>,
+    #[serde(default)]
+    pub upstream: Vec<UpstreamRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SeriesHeader {
+    location: String,
+    elevation: Option<f64>,
+    rows: usize,
+    sources: Vec<String>,
+    #[serde(default)]
+    provenance: Vec<Option<Provenance>>,
+}
+
+// Written to a temporary file and renamed, so a crash never leaves a truncated snapshot at `path`.
+pub fn write_snapshot(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    write_snapshot_series(path, snapshot, &snapshot.series)
+}
+
+// As write_snapshot, with the series passed separately so callers can write them without copying;
+// `snapshot.series` is ignored.
+pub fn write_snapshot_series<'a, I>(path: &Path, snapshot: &Snapshot, series: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a LocationSeries>,
+{
+    let manifest = serde_json::to_vec(snapshot).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let tmp_path = tmp_path(path);
+    let mut file = BufWriter::new(File::create(&tmp_path)?);
+    file.write_all(SNAPSHOT_MAGIC)?;
+    file.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    write_section(&mut encoder, &manifest)?;
+    for series in series {
+        write_section(&mut encoder, &encode_series(series)?)?;
+    }
+
+    let file = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn read_snapshot(path: &Path) -> io::Result<Snapshot> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+    if &header[..8] != SNAPSHOT_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{:?} is not a snapshot", path)));
+    }
+
+    let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    match version {
+        1 | 2 => read_sections(GzDecoder::new(file), version),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Snapshot version {} is not supported (newest is {})", version, SNAPSHOT_VERSION),
+        )),
+    }
+}
+
+fn read_sections<R: Read>(mut reader: R, version: u32) -> io::Result<Snapshot> {
+    let manifest = read_section(&mut reader)?.ok_or_else(|| invalid("Snapshot has no manifest"))?;
+    let mut snapshot: Snapshot = serde_json::from_slice(&manifest).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    while let Some(payload) = read_section(&mut reader)? {
+        snapshot.series.push(decode_series(&payload, version)?);
+    }
+
+    let points: usize = snapshot.series.iter().map(|s| s.len()).sum();
+    if snapshot.series.len() != snapshot.info.locations || points != snapshot.info.points {
+        return Err(invalid("Snapshot is incomplete"));
+    }
+    Ok(snapshot)
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_section<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+    writer.write_all(&crc32fast::hash(payload).to_le_bytes())?;
+    writer.write_all(payload)
+}
+
+// Ok(None) at a clean end of the stream.
+fn read_section<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; SECTION_HEADER_LEN];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated section header")),
+            n => filled += n,
+        }
+    }
+
+    let len = u64::from_le_bytes(header[..8].try_into().unwrap());
+    let checksum = u32::from_le_bytes(header[8..].try_into().unwrap());
+
+    // Read through `take` so a corrupt length cannot allocate more than the stream holds.
+    let mut payload = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut payload)?;
+    if payload.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated section"));
+    }
+    if crc32fast::hash(&payload) != checksum {
+        return Err(invalid("Section checksum mismatch"));
+    }
+    Ok(Some(payload))
+}
+
+fn encode_series(series: &LocationSeries) -> io::Result<Vec<u8>> {
+    let header = SeriesHeader {
+        location: series.location().to_string(),
+        elevation: series.elevation(),
+        rows: series.len(),
+        sources: series.source_names().to_vec(),
+        provenance: series.provenance_records().to_vec(),
+    };
+    let header = serde_json::to_vec(&header).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut payload = Vec::with_capacity(4 + header.len() + series.len() * bytes_per_row(SNAPSHOT_VERSION));
+    payload.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&header);
+    // Raw bit patterns keep NaN markers for missing values, which JSON cannot represent.
+    for timestamp in series.timestamps() {
+        payload.extend_from_slice(&timestamp.to_le_bytes());
+    }
+    for variable in WeatherVariable::ALL {
+        for value in series.column(variable) {
+            payload.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for flags in series.qc_flags() {
+        payload.extend_from_slice(&flags.0.to_le_bytes());
+    }
+    for id in series.source_ids().iter().chain(series.provenance_ids()) {
+        payload.extend_from_slice(&id.to_le_bytes());
+    }
+    Ok(payload)
+}
+
+fn decode_series(payload: &[u8], version: u32) -> io::Result<LocationSeries> {
+    let header_len = u32::from_le_bytes(payload.get(..4).ok_or_else(|| invalid("Truncated series header"))?.try_into().unwrap()) as usize;
+    let header = payload.get(4..4 + header_len).ok_or_else(|| invalid("Truncated series header"))?;
+    let header: SeriesHeader = serde_json::from_slice(header).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let body = &payload[4 + header_len..];
+    let rows = header.rows;
+    if rows.checked_mul(bytes_per_row(version)) != Some(body.len()) {
+        return Err(invalid("Series columns do not match the row count"));
+    }
+
+    let mut offset = 0;
+    let mut take = |width: usize| {
+        let bytes = &body[offset..offset + rows * width];
+        offset += rows * width;
+        bytes.chunks_exact(width)
+    };
+    let timestamps = take(8).map(|b| i64::from_le_bytes(b.try_into().unwrap())).collect();
+    let mut floats = || take(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect::<Vec<f64>>();
+    let (temperature, humidity, pressure) = (floats(), floats(), floats());
+    let (wind_speed, wind_direction, precipitation) = (floats(), floats(), floats());
+    let qc_flags = take(4).map(|b| QcFlags(u32::from_le_bytes(b.try_into().unwrap()))).collect();
+    let source_ids = take(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+    // Version 1 has no provenance column; empty columns mean no provenance for every point.
+    let provenance_ids = if version >= 2 {
+        take(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect()
+    } else {
+        Vec::new()
+    };
+
+    let columns = SeriesColumns {
+        timestamps,
+        temperature,
+        humidity,
+        pressure,
+        wind_speed,
+        wind_direction,
+        precipitation,
+        qc_flags,
+        source_ids,
+        source_names: header.sources,
+        provenance_ids,
+        provenance_records: header.provenance,
+    };
+    LocationSeries::from_columns(header.location, header.elevation, columns).map_err(|e| invalid(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::WeatherDataPoint;
+
+    fn point(timestamp: i64, temperature: f64, provenance: Option<Provenance>) -> WeatherDataPoint {
+        WeatherDataPoint {
+            timestamp,
+            location: "KJFK".to_string(),
+            temperature,
+            humidity: f64::NAN,
+            pressure: 1013.0,
+            wind_speed: 3.0,
+            wind_direction: 180.0,
+            precipitation: 0.0,
+            qc_flags: QcFlags::CHECKED,
+            source: "metar".to_string(),
+            provenance,
+        }
+    }
+
+    fn snapshot(provenance: bool) -> Snapshot {
+        let mut series = LocationSeries::new("KJFK".to_string(), Some(4.0));
+        for i in 0..3 {
+            let record = provenance.then(|| Provenance::new(format!("payload {}", i).as_bytes()));
+            series.insert(i, &point(1_700_000_000 + i as i64 * 3600, 20.0 + i as f64, record));
+        }
+        Snapshot {
+            info: SnapshotInfo { version: SNAPSHOT_VERSION, created_at: 1_700_000_000, locations: 1, points: 3 },
+            stats: ProcessingStats::default(),
+            stations: StationRegistry::new(),
+            normals: Vec::new(),
+            series: vec![series],
+            upstream: vec![UpstreamRecord { payload_hash: [1; 32], payload: "payload".to_string() }],
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("weather-snapshot-{}-{}", std::process::id(), name))
+    }
+
+    fn same_points(a: &LocationSeries, b: &LocationSeries) -> bool {
+        let bits = |p: WeatherDataPoint| {
+            (p.timestamp, p.temperature.to_bits(), p.humidity.to_bits(), p.qc_flags, p.source, p.provenance)
+        };
+        a.points(0..a.len()).into_iter().map(bits).eq(b.points(0..b.len()).into_iter().map(bits))
+    }
+
+    #[test]
+    fn round_trip_keeps_points_and_missing_values() {
+        let path = temp_path("round-trip");
+        let written = snapshot(true);
+        write_snapshot(&path, &written).unwrap();
+        let read = read_snapshot(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.info.points, 3);
+        assert_eq!(read.series[0].elevation(), Some(4.0));
+        assert!(same_points(&read.series[0], &written.series[0]));
+        assert_eq!(read.upstream[0].payload, "payload");
+    }
+
+    #[test]
+    fn version_1_files_read_without_provenance() {
+        let written = snapshot(false);
+        let series = &written.series[0];
+        let header = SeriesHeader {
+            location: series.location().to_string(),
+            elevation: series.elevation(),
+            rows: series.len(),
+            sources: series.source_names().to_vec(),
+            provenance: Vec::new(),
+        };
+        let header = serde_json::to_vec(&header).unwrap();
+        let body = encode_series(series).unwrap();
+        let columns = &body[body.len() - series.len() * bytes_per_row(SNAPSHOT_VERSION)..body.len() - series.len() * 4];
+        let mut section = (header.len() as u32).to_le_bytes().to_vec();
+        section.extend_from_slice(&header);
+        section.extend_from_slice(columns);
+
+        let path = temp_path("v1");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(SNAPSHOT_MAGIC).unwrap();
+        file.write_all(&1u32.to_le_bytes()).unwrap();
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        write_section(&mut encoder, &serde_json::to_vec(&written).unwrap()).unwrap();
+        write_section(&mut encoder, &section).unwrap();
+        encoder.finish().unwrap();
+
+        let read = read_snapshot(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(same_points(&read.series[0], series));
+        assert!(read.series[0].provenance(0).is_none());
+    }
+
+    #[test]
+    fn truncated_and_corrupt_files_are_rejected() {
+        let path = temp_path("damaged");
+        write_snapshot(&path, &snapshot(true)).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        for cut in [bytes.len() - 1, bytes.len() / 2, 20, 4] {
+            fs::write(&path, &bytes[..cut]).unwrap();
+            assert!(read_snapshot(&path).is_err(), "truncated to {} bytes", cut);
+        }
+
+        let mut corrupt = bytes.clone();
+        let middle = corrupt.len() / 2;
+        corrupt[middle] ^= 0xff;
+        fs::write(&path, &corrupt).unwrap();
+        assert!(read_snapshot(&path).is_err());
+
+        let mut future = bytes;
+        future[8..12].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        fs::write(&path, &future).unwrap();
+        assert!(read_snapshot(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
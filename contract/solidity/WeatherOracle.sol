@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+contract WeatherOracle {
+    struct WeatherData {
+        string city;
+        int64 temperature;
+        int64 humidity;
+        uint64 timestamp;
+        bytes32 dataHash;
+        address reporter;
+    }
+
+    uint256 public immutable minStake;
+    uint256 public immutable rewardPerSubmission;
+    // Minimum seconds of block time between two rewarded submissions from one reporter.
+    uint256 public immutable rewardInterval;
+
+    // Rewards are paid from this pool only, never from staked deposits.
+    uint256 public rewardPool;
+    // Accrued but unclaimed rewards; accrual stops once they would exceed the pool.
+    uint256 public outstandingRewards;
+
+    mapping(string => WeatherData) private latestData;
+    mapping(address => uint256) private stakes;
+    mapping(address => uint256) private rewards;
+    mapping(bytes32 => uint256) private disputeCounts;
+    mapping(bytes32 => mapping(address => bool)) private disputed;
+    mapping(bytes32 => address) private hashReporter;
+    mapping(address => uint256) private lastRewardedAt;
+
+    event DataSubmitted(string city, address indexed reporter, int64 temperature, int64 humidity, uint64 timestamp, bytes32 dataHash);
+    event Staked(address indexed reporter, uint256 amount);
+    event Unstaked(address indexed reporter, uint256 amount);
+    event DisputeSubmitted(bytes32 indexed dataHash, address indexed disputer, string reason);
+    event RewardsClaimed(address indexed reporter, uint256 amount);
+    event RewardsFunded(address indexed funder, uint256 amount);
+
+    constructor(uint256 _minStake, uint256 _rewardPerSubmission, uint256 _rewardInterval) payable {
+        minStake = _minStake;
+        rewardPerSubmission = _rewardPerSubmission;
+        rewardInterval = _rewardInterval;
+        rewardPool = msg.value;
+    }
+
+    receive() external payable {
+        fundRewards();
+    }
+
+    function fundRewards() public payable {
+        rewardPool += msg.value;
+        emit RewardsFunded(msg.sender, msg.value);
+    }
+
+    function submitData(string calldata city, int64 temperature, int64 humidity, uint64 timestamp, bytes32 dataHash) external {
+        require(stakes[msg.sender] >= minStake, "insufficient stake");
+        require(hashReporter[dataHash] == address(0), "duplicate data hash");
+        require(timestamp > latestData[city].timestamp, "stale timestamp");
+
+        latestData[city] = WeatherData(city, temperature, humidity, timestamp, dataHash, msg.sender);
+        hashReporter[dataHash] = msg.sender;
+
+        // The reading is accepted either way; only the reward is rate limited and capped by the pool.
+        bool intervalElapsed = lastRewardedAt[msg.sender] == 0 || block.timestamp >= lastRewardedAt[msg.sender] + rewardInterval;
+        if (intervalElapsed && outstandingRewards + rewardPerSubmission <= rewardPool) {
+            lastRewardedAt[msg.sender] = block.timestamp;
+            rewards[msg.sender] += rewardPerSubmission;
+            outstandingRewards += rewardPerSubmission;
+        }
+
+        emit DataSubmitted(city, msg.sender, temperature, humidity, timestamp, dataHash);
+    }
+
+    function getLatestData(string calldata city) external view returns (string memory, int64, int64, uint64, bytes32) {
+        WeatherData storage data = latestData[city];
+        require(data.timestamp != 0, "no data for city");
+        return (data.city, data.temperature, data.humidity, data.timestamp, data.dataHash);
+    }
+
+    function stake(uint256 amount) external payable {
+        require(amount > 0, "zero stake");
+        require(msg.value == amount, "value does not match amount");
+        stakes[msg.sender] += amount;
+        emit Staked(msg.sender, amount);
+    }
+
+    function unstake(uint256 amount) external {
+        require(amount > 0, "zero unstake");
+        require(stakes[msg.sender] >= amount, "amount exceeds stake");
+        stakes[msg.sender] -= amount;
+        (bool ok, ) = payable(msg.sender).call{value: amount}("");
+        require(ok, "transfer failed");
+        emit Unstaked(msg.sender, amount);
+    }
+
+    function getStake(address reporter) external view returns (uint256) {
+        return stakes[reporter];
+    }
+
+    function submitDispute(bytes32 dataHash, string calldata reason) external {
+        require(stakes[msg.sender] >= minStake, "insufficient stake");
+        require(hashReporter[dataHash] != address(0), "unknown data hash");
+        require(hashReporter[dataHash] != msg.sender, "cannot dispute own data");
+        require(!disputed[dataHash][msg.sender], "already disputed");
+
+        disputed[dataHash][msg.sender] = true;
+        disputeCounts[dataHash] += 1;
+        emit DisputeSubmitted(dataHash, msg.sender, reason);
+    }
+
+    function getDisputeCount(bytes32 dataHash) external view returns (uint256) {
+        return disputeCounts[dataHash];
+    }
+
+    function claimRewards() external {
+        uint256 amount = rewards[msg.sender];
+        require(amount > 0, "no rewards");
+        require(rewardPool >= amount, "reward pool exhausted");
+        rewards[msg.sender] = 0;
+        rewardPool -= amount;
+        outstandingRewards -= amount;
+        (bool ok, ) = payable(msg.sender).call{value: amount}("");
+        require(ok, "transfer failed");
+        emit RewardsClaimed(msg.sender, amount);
+    }
+
+    function getRewardBalance(address reporter) external view returns (uint256) {
+        return rewards[reporter];
+    }
+}
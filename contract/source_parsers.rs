@@ -0,0 +1,517 @@
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::derived_variables::{relative_humidity, station_pressure};
+use crate::quality_control::QcFlags;
+use crate::station_registry::StationRegistry;
+use crate::units::{LengthUnit, PressureUnit, SpeedUnit, TemperatureUnit};
+
+pub const METAR_SOURCE: &str = "metar";
+pub const OPENWEATHERMAP_SOURCE: &str = "openweathermap";
+pub const NOAA_ISD_SOURCE: &str = "noaa-isd";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OwmUnits {
+    Standard,
+    Metric,
+    Imperial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RawFormat {
+    // METAR times carry only day and time of day; the month is taken from the reference.
+    Metar { reference_timestamp: i64 },
+    OpenWeatherMapCurrent { units: OwmUnits },
+    OpenWeatherMapForecast { units: OwmUnits },
+    NoaaIsd,
+}
+
+// The registry supplies elevations for reducing sea-level or altimeter pressure to station pressure.
+// Without one the pressure is stored as missing: `pressure` only ever holds station pressure.
+pub fn parse(format: RawFormat, payload: &str, stations: &StationRegistry) -> Result<Vec<WeatherDataPoint>, Box<dyn std::error::Error>> {
+    match format {
+        RawFormat::Metar { reference_timestamp } => parse_lines(payload, |line| parse_metar(line, reference_timestamp, stations)),
+        RawFormat::OpenWeatherMapCurrent { units } => Ok(vec![parse_openweathermap_current(payload, units, stations)?]),
+        RawFormat::OpenWeatherMapForecast { units } => parse_openweathermap_forecast(payload, units, stations),
+        RawFormat::NoaaIsd => parse_lines(payload, |line| parse_isd_record(line, stations)),
+    }
+}
+
+fn parse_lines<F>(payload: &str, parse_line: F) -> Result<Vec<WeatherDataPoint>, Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> Result<WeatherDataPoint, Box<dyn std::error::Error>>,
+{
+    payload
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| parse_line(line).map_err(|e| format!("Line {}: {}", number + 1, e).into()))
+        .collect()
+}
+
+// NaN marks a missing element for that variable only (QC rejects the point only when a required variable is missing);
+// absent precipitation groups mean none was reported.
+pub fn parse_metar(raw: &str, reference_timestamp: i64, stations: &StationRegistry) -> Result<WeatherDataPoint, Box<dyn std::error::Error>> {
+    let mut tokens = raw.split_whitespace().filter(|t| !matches!(*t, "METAR" | "SPECI" | "COR" | "AUTO")).peekable();
+
+    let station = tokens.next().ok_or("Empty METAR")?;
+    if station.len() != 4 || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid METAR station {}", station).into());
+    }
+    let time = tokens.next().ok_or("METAR has no observation time")?;
+    let timestamp = metar_timestamp(time, reference_timestamp)?;
+
+    let (mut wind_speed, mut wind_direction) = (f64::NAN, f64::NAN);
+    let (mut temperature, mut dew_point) = (f64::NAN, f64::NAN);
+    let mut reported_pressure = None;
+    let mut precipitation = 0.0;
+
+    let mut in_remarks = false;
+    for token in tokens {
+        if token == "RMK" {
+            in_remarks = true;
+            continue;
+        }
+
+        if in_remarks {
+            if let Some((t, d)) = metar_precise_temperatures(token) {
+                temperature = t;
+                dew_point = d;
+            } else if let Some(hundredths) = token.strip_prefix('P').filter(|d| d.len() == 4 && d.chars().all(|c| c.is_ascii_digit())) {
+                precipitation = LengthUnit::Inches.to_canonical(hundredths.parse::<f64>()? / 100.0);
+            }
+        } else if let Some((direction, speed)) = metar_wind(token) {
+            wind_direction = direction;
+            wind_speed = speed;
+        } else if let Some((t, d)) = metar_temperatures(token) {
+            temperature = t;
+            dew_point = d;
+        } else if token.len() == 5 && token.get(1..).is_some_and(|d| d.chars().all(|c| c.is_ascii_digit())) {
+            if let Some(inches) = token.strip_prefix('A') {
+                reported_pressure = Some(PressureUnit::InchesOfMercury.to_canonical(inches.parse::<f64>()? / 100.0));
+            } else if let Some(hpa) = token.strip_prefix('Q') {
+                reported_pressure = Some(hpa.parse::<f64>()?);
+            }
+        }
+    }
+
+    let elevation = stations.resolve(station).and_then(|s| s.elevation_m);
+    let pressure = match (reported_pressure, elevation) {
+        (Some(altimeter), Some(elevation)) => altimeter_to_station_pressure(altimeter, elevation),
+        _ => f64::NAN,
+    };
+
+    Ok(WeatherDataPoint {
+        timestamp,
+        location: station.to_string(),
+        temperature,
+        humidity: humidity_from_dew_point(temperature, dew_point),
+        pressure,
+        wind_speed,
+        wind_direction,
+        precipitation,
+        qc_flags: QcFlags::default(),
+        source: METAR_SOURCE.to_string(),
+        // Stamped with the raw payload by DataProcessor::ingest_raw.
+        provenance: None,
+    })
+}
+
+fn metar_timestamp(token: &str, reference_timestamp: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let digits = token.strip_suffix('Z').filter(|d| d.len() == 6 && d.chars().all(|c| c.is_ascii_digit()));
+    let digits = digits.ok_or_else(|| format!("Invalid METAR time {}", token))?;
+    let (day, hour, minute): (u32, u32, u32) = (digits[0..2].parse()?, digits[2..4].parse()?, digits[4..6].parse()?);
+
+    let reference = Utc.timestamp_opt(reference_timestamp, 0).single().ok_or("Invalid reference timestamp")?;
+    let mut month_start = NaiveDate::from_ymd_opt(reference.year(), reference.month(), 1).unwrap();
+    // Reports from the following day are tolerated for clock skew; anything later belongs to an earlier month.
+    for _ in 0..3 {
+        let candidate = NaiveDate::from_ymd_opt(month_start.year(), month_start.month(), day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .map(|time| time.and_utc().timestamp());
+        if let Some(candidate) = candidate {
+            if candidate <= reference_timestamp + 86_400 {
+                return Ok(candidate);
+            }
+        }
+        month_start = (month_start - Duration::days(1)).with_day(1).unwrap();
+    }
+    Err(format!("METAR time {} does not fit near the reference time", token).into())
+}
+
+fn metar_wind(token: &str) -> Option<(f64, f64)> {
+    let (body, unit) = if let Some(body) = token.strip_suffix("KT") {
+        (body, SpeedUnit::Knots)
+    } else if let Some(body) = token.strip_suffix("MPS") {
+        (body, SpeedUnit::MetersPerSecond)
+    } else if let Some(body) = token.strip_suffix("KMH") {
+        (body, SpeedUnit::KilometersPerHour)
+    } else {
+        return None;
+    };
+    if body.len() < 5 || !body.is_ascii() {
+        return None;
+    }
+
+    let (direction, rest) = body.split_at(3);
+    let sustained = rest.split('G').next()?;
+    let speed = unit.to_canonical(sustained.parse::<f64>().ok()?);
+    let direction = match direction {
+        "VRB" => f64::NAN,
+        _ => direction.parse::<f64>().ok()?,
+    };
+    Some((direction, speed))
+}
+
+fn metar_temperatures(token: &str) -> Option<(f64, f64)> {
+    let (temperature, dew_point) = token.split_once('/')?;
+    let temperature = metar_celsius(temperature)?;
+    let dew_point = if dew_point.is_empty() { f64::NAN } else { metar_celsius(dew_point)? };
+    Some((temperature, dew_point))
+}
+
+fn metar_celsius(value: &str) -> Option<f64> {
+    let (sign, digits) = match value.strip_prefix('M') {
+        Some(digits) => (-1.0, digits),
+        None => (1.0, value),
+    };
+    if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(sign * digits.parse::<f64>().ok()?)
+}
+
+// Remark group TsTTTsDDD: tenths of a degree with 1 as the negative sign.
+fn metar_precise_temperatures(token: &str) -> Option<(f64, f64)> {
+    let digits = token.strip_prefix('T')?;
+    if digits.len() != 8 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let tenths = |group: &str| -> Option<f64> {
+        let sign = match &group[0..1] {
+            "0" => 1.0,
+            "1" => -1.0,
+            _ => return None,
+        };
+        Some(sign * group[1..].parse::<f64>().ok()? / 10.0)
+    };
+    Some((tenths(&digits[0..4])?, tenths(&digits[4..8])?))
+}
+
+// NWS altimeter-setting relation; QNH is treated the same way.
+fn altimeter_to_station_pressure(altimeter_hpa: f64, elevation_m: f64) -> f64 {
+    const N: f64 = 0.190284;
+    (altimeter_hpa.powf(N) - 8.42288e-5 * elevation_m).powf(1.0 / N)
+}
+
+fn humidity_from_dew_point(temperature: f64, dew_point: f64) -> f64 {
+    if temperature.is_finite() && dew_point.is_finite() {
+        relative_humidity(temperature, dew_point)
+    } else {
+        f64::NAN
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OwmMain {
+    temp: f64,
+    humidity: Option<f64>,
+    pressure: Option<f64>,
+    grnd_level: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OwmWind {
+    speed: Option<f64>,
+    deg: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OwmPrecipitation {
+    #[serde(rename = "1h")]
+    one_hour: Option<f64>,
+    #[serde(rename = "3h")]
+    three_hours: Option<f64>,
+}
+
+impl OwmPrecipitation {
+    fn amount(&self) -> f64 {
+        self.one_hour.or(self.three_hours).unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OwmEntry {
+    dt: i64,
+    main: OwmMain,
+    #[serde(default)]
+    wind: OwmWind,
+    #[serde(default)]
+    rain: OwmPrecipitation,
+    #[serde(default)]
+    snow: OwmPrecipitation,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OwmCity {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OwmForecast {
+    list: Vec<OwmEntry>,
+    city: OwmCity,
+}
+
+pub fn parse_openweathermap_current(json: &str, units: OwmUnits, stations: &StationRegistry) -> Result<WeatherDataPoint, Box<dyn std::error::Error>> {
+    let entry: OwmEntry = serde_json::from_str(json)?;
+    if entry.name.is_empty() {
+        return Err("OpenWeatherMap response has no location name".into());
+    }
+    let location = entry.name.clone();
+    Ok(owm_point(entry, &location, units, stations))
+}
+
+pub fn parse_openweathermap_forecast(json: &str, units: OwmUnits, stations: &StationRegistry) -> Result<Vec<WeatherDataPoint>, Box<dyn std::error::Error>> {
+    let forecast: OwmForecast = serde_json::from_str(json)?;
+    let location = forecast.city.name;
+    Ok(forecast.list.into_iter().map(|entry| owm_point(entry, &location, units, stations)).collect())
+}
+
+// OWM omits rain and snow when there is none; snow is reported as water equivalent.
+fn owm_point(entry: OwmEntry, location: &str, units: OwmUnits, stations: &StationRegistry) -> WeatherDataPoint {
+    let (temperature_unit, speed_unit) = match units {
+        OwmUnits::Standard => (TemperatureUnit::Kelvin, SpeedUnit::MetersPerSecond),
+        OwmUnits::Metric => (TemperatureUnit::Celsius, SpeedUnit::MetersPerSecond),
+        OwmUnits::Imperial => (TemperatureUnit::Fahrenheit, SpeedUnit::MilesPerHour),
+    };
+    let temperature = temperature_unit.to_canonical(entry.main.temp);
+
+    // `pressure` is sea-level; `grnd_level` is only present in some responses.
+    let elevation = stations.resolve(location).and_then(|s| s.elevation_m);
+    let pressure = match (entry.main.grnd_level, entry.main.pressure, elevation) {
+        (Some(ground), _, _) => ground,
+        (None, Some(sea_level), Some(elevation)) => station_pressure(sea_level, temperature, elevation),
+        _ => f64::NAN,
+    };
+
+    WeatherDataPoint {
+        timestamp: entry.dt,
+        location: location.to_string(),
+        temperature,
+        humidity: entry.main.humidity.unwrap_or(f64::NAN),
+        pressure,
+        wind_speed: entry.wind.speed.map_or(f64::NAN, |s| speed_unit.to_canonical(s)),
+        wind_direction: entry.wind.deg.unwrap_or(f64::NAN),
+        precipitation: entry.rain.amount() + entry.snow.amount(),
+        qc_flags: QcFlags::default(),
+        source: OPENWEATHERMAP_SOURCE.to_string(),
+        provenance: None,
+    }
+}
+
+// Integrated Surface Database record: mandatory section at fixed columns, then tagged additional groups.
+pub fn parse_isd_record(line: &str, stations: &StationRegistry) -> Result<WeatherDataPoint, Box<dyn std::error::Error>> {
+    if line.len() < 105 || !line.is_ascii() {
+        return Err(format!("ISD record is {} characters, expected at least 105", line.len()).into());
+    }
+    // 1-based inclusive column ranges, as in the ISD format document.
+    let field = |start: usize, end: usize| &line[start - 1..end];
+
+    let location = format!("{}-{}", field(5, 10), field(11, 15));
+    let date = NaiveDate::parse_from_str(field(16, 23), "%Y%m%d")?;
+    let (hour, minute): (u32, u32) = (field(24, 25).parse()?, field(26, 27).parse()?);
+    let timestamp = date.and_hms_opt(hour, minute, 0).ok_or("Invalid ISD observation time")?.and_utc().timestamp();
+
+    let record_elevation = isd_value(field(47, 51), "+9999", "1", 1.0)?;
+    let wind_direction = match (field(61, 63), field(65, 65)) {
+        ("999", "C") => 0.0,
+        (direction, _) => isd_value(direction, "999", field(64, 64), 1.0)?.unwrap_or(f64::NAN),
+    };
+    let wind_speed = isd_value(field(66, 69), "9999", field(70, 70), 10.0)?.unwrap_or(f64::NAN);
+    let temperature = isd_value(field(88, 92), "+9999", field(93, 93), 10.0)?.unwrap_or(f64::NAN);
+    let dew_point = isd_value(field(94, 98), "+9999", field(99, 99), 10.0)?.unwrap_or(f64::NAN);
+    let sea_level_pressure = isd_value(field(100, 104), "99999", field(105, 105), 10.0)?;
+
+    let additional = &line[105..];
+    let measured_station_pressure = match isd_group(additional, "MA1", 12) {
+        Some(group) => isd_value(&group[6..11], "99999", &group[11..12], 10.0)?,
+        None => None,
+    };
+    // AA1: period hours, depth in tenths of mm, condition, quality.
+    let precipitation = match isd_group(additional, "AA1", 8) {
+        Some(group) => isd_value(&group[2..6], "9999", &group[7..8], 10.0)?.unwrap_or(f64::NAN),
+        None => 0.0,
+    };
+
+    let elevation = record_elevation.or_else(|| stations.resolve(&location).and_then(|s| s.elevation_m));
+    let pressure = match (measured_station_pressure, sea_level_pressure, elevation) {
+        (Some(pressure), _, _) => pressure,
+        (None, Some(sea_level), Some(elevation)) if temperature.is_finite() => station_pressure(sea_level, temperature, elevation),
+        _ => f64::NAN,
+    };
+
+    Ok(WeatherDataPoint {
+        timestamp,
+        location,
+        temperature,
+        humidity: humidity_from_dew_point(temperature, dew_point),
+        pressure,
+        wind_speed,
+        wind_direction,
+        precipitation,
+        qc_flags: QcFlags::default(),
+        source: NOAA_ISD_SOURCE.to_string(),
+        provenance: None,
+    })
+}
+
+// Quality codes 3 and 7 mark values ISD itself judged erroneous.
+fn isd_value(raw: &str, missing: &str, quality: &str, scale: f64) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    if raw == missing || matches!(quality, "3" | "7") {
+        return Ok(None);
+    }
+    let value: f64 = raw.trim_start_matches('+').parse().map_err(|_| format!("Invalid ISD value {}", raw))?;
+    Ok(Some(value / scale))
+}
+
+// Additional groups have tag-specific lengths, so the tag is located rather than walking every group.
+fn isd_group<'a>(additional: &'a str, tag: &str, length: usize) -> Option<&'a str> {
+    let body = additional.strip_prefix("ADD")?;
+    let start = body.find(tag)? + tag.len();
+    body.get(start..start + length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::station_registry::Station;
+
+    // The files under samples/ are compared with values decoded by hand.
+    const SAMPLE_REFERENCE_TIMESTAMP: i64 = 1_710_273_600;
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!((actual - expected).abs() < 1e-6, "{}: {} != {}", what, actual, expected);
+    }
+
+    #[test]
+    fn metar_sample() {
+        let format = RawFormat::Metar { reference_timestamp: SAMPLE_REFERENCE_TIMESTAMP };
+        let points = parse(format, include_str!("samples/metar.txt"), &StationRegistry::new()).unwrap();
+        assert_eq!(points.len(), 4);
+
+        let jfk = &points[0];
+        assert_eq!((jfk.location.as_str(), jfk.source.as_str()), ("KJFK", METAR_SOURCE));
+        assert_eq!(jfk.timestamp, 1_710_269_460);
+        assert_close(jfk.temperature, 22.2, "KJFK temperature from T group");
+        assert_close(jfk.wind_speed, SpeedUnit::Knots.to_canonical(15.0), "KJFK wind speed");
+        assert_close(jfk.wind_direction, 310.0, "KJFK wind direction");
+        assert!(jfk.pressure.is_nan(), "KJFK altimeter without an elevation");
+        assert_close(jfk.humidity, relative_humidity(22.2, 9.4), "KJFK humidity from dew point");
+
+        let heathrow = &points[1];
+        assert!(heathrow.pressure.is_nan(), "EGLL QNH without an elevation");
+        assert_close(heathrow.temperature, 14.0, "EGLL temperature");
+
+        let ohare = &points[2];
+        assert!(ohare.wind_direction.is_nan(), "KORD variable wind direction");
+        assert_close(ohare.wind_speed, SpeedUnit::Knots.to_canonical(3.0), "KORD variable wind speed");
+        assert_close(ohare.temperature, -2.2, "KORD temperature");
+        assert_close(ohare.precipitation, 0.762, "KORD precipitation");
+
+        assert_close(points[3].wind_speed, 0.0, "KDEN calm wind");
+        assert_close(points[3].temperature, 17.8, "KDEN temperature");
+    }
+
+    #[test]
+    fn metar_altimeter_is_reduced_with_a_registered_elevation() {
+        let mut stations = StationRegistry::new();
+        stations
+            .register(Station {
+                id: "KDEN".to_string(),
+                name: "Denver".to_string(),
+                latitude: 39.86,
+                longitude: -104.67,
+                elevation_m: Some(1656.0),
+                timezone: chrono_tz::America::Denver,
+                aliases: Vec::new(),
+            })
+            .unwrap();
+
+        let point = parse_metar("KDEN 121853Z 00000KT 18/M02 A3002", SAMPLE_REFERENCE_TIMESTAMP, &stations).unwrap();
+        let altimeter = PressureUnit::InchesOfMercury.to_canonical(30.02);
+        assert_close(point.pressure, altimeter_to_station_pressure(altimeter, 1656.0), "KDEN station pressure");
+        assert!(point.pressure < 850.0, "reduced well below the altimeter setting");
+    }
+
+    #[test]
+    fn metar_rejects_or_skips_non_ascii_groups() {
+        assert!(parse_metar("KJFK 121851Z 12é34KT 22/09 A3002", SAMPLE_REFERENCE_TIMESTAMP, &StationRegistry::new()).is_ok());
+        assert!(parse_metar("KJFK 121851Z é123 22/09 RMK Pé123", SAMPLE_REFERENCE_TIMESTAMP, &StationRegistry::new()).is_ok());
+        assert!(parse_metar("Ké 121851Z", SAMPLE_REFERENCE_TIMESTAMP, &StationRegistry::new()).is_err());
+    }
+
+    #[test]
+    fn openweathermap_current_sample() {
+        let json = include_str!("samples/openweathermap_current.json");
+        let point = parse_openweathermap_current(json, OwmUnits::Standard, &StationRegistry::new()).unwrap();
+
+        assert_eq!((point.location.as_str(), point.source.as_str()), ("London", OPENWEATHERMAP_SOURCE));
+        assert_eq!(point.timestamp, 1_710_269_400);
+        assert_close(point.temperature, 14.0, "Kelvin converted to Celsius");
+        assert_close(point.pressure, 1004.0, "ground-level pressure preferred");
+        assert_close(point.humidity, 82.0, "humidity");
+        assert_close(point.wind_speed, 4.1, "wind speed");
+        assert_close(point.wind_direction, 240.0, "wind direction");
+        assert_close(point.precipitation, 0.42, "one-hour rain");
+    }
+
+    #[test]
+    fn openweathermap_forecast_sample() {
+        let json = include_str!("samples/openweathermap_forecast.json");
+        let points = parse_openweathermap_forecast(json, OwmUnits::Imperial, &StationRegistry::new()).unwrap();
+        assert_eq!(points.len(), 3);
+
+        assert_close(points[0].temperature, TemperatureUnit::Fahrenheit.to_canonical(54.5), "Fahrenheit converted to Celsius");
+        assert_close(points[0].wind_speed, SpeedUnit::MilesPerHour.to_canonical(10.3), "mph converted to m/s");
+        assert_close(points[0].precipitation, 1.27, "three-hour rain");
+        assert_close(points[1].precipitation, 0.0, "dry entry");
+        assert!(points[1].pressure.is_nan(), "sea-level pressure without ground level or elevation");
+        assert_close(points[2].precipitation, 0.3, "snow water equivalent");
+    }
+
+    #[test]
+    fn noaa_isd_sample() {
+        let points = parse(RawFormat::NoaaIsd, include_str!("samples/noaa_isd.txt"), &StationRegistry::new()).unwrap();
+        assert_eq!(points.len(), 4);
+
+        let laguardia = &points[0];
+        assert_eq!((laguardia.location.as_str(), laguardia.source.as_str()), ("725030-14732", NOAA_ISD_SOURCE));
+        assert_eq!(laguardia.timestamp, 1_710_269_460);
+        assert_close(laguardia.temperature, 22.2, "temperature");
+        assert_close(laguardia.wind_speed, 7.7, "wind speed");
+        assert_close(laguardia.pressure, 1016.5, "MA1 station pressure");
+
+        assert_close(points[1].wind_direction, 0.0, "calm wind");
+        assert_close(points[1].precipitation, 0.3, "AA1 precipitation");
+        assert_close(points[2].pressure, 838.0, "Denver station pressure");
+        assert!(points[2].wind_direction.is_nan(), "Denver variable wind");
+        assert!(points[3].temperature.is_nan() && points[3].humidity.is_nan(), "missing temperature");
+        assert!(points[3].pressure.is_nan(), "sea-level pressure that cannot be reduced");
+    }
+
+    // A variable wind leaves only the direction missing, so QC still accepts the point.
+    #[test]
+    fn variable_wind_passes_range_check() {
+        use crate::quality_control::{QcConfig, QcContext, QcPipeline};
+
+        let format = RawFormat::Metar { reference_timestamp: SAMPLE_REFERENCE_TIMESTAMP };
+        let points = parse(format, include_str!("samples/metar.txt"), &StationRegistry::new()).unwrap();
+        let context = QcContext { history: None, neighbors: Vec::new() };
+        let flags = QcPipeline::from_config(QcConfig::default()).evaluate(&points[2], &context);
+        assert!(!flags.failed());
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use chrono_tz::Tz;
+use serde::{Serialize, Deserialize};
+
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Station {
+    pub id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation_m: Option<f64>,
+    pub timezone: Tz,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationDistance {
+    pub station: Station,
+    pub distance_km: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpatialMethod {
+    InverseDistance { power: f64 },
+    // Ordinary kriging with an exponential variogram fixed by the caller rather than fitted.
+    KrigingLite { range_km: f64, sill: f64, nugget: f64 },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EstimateConfig {
+    pub radius_km: f64,
+    pub tolerance_secs: i64,
+    pub method: SpatialMethod,
+}
+
+impl Default for EstimateConfig {
+    fn default() -> Self {
+        Self {
+            radius_km: 50.0,
+            tolerance_secs: 1800,
+            method: SpatialMethod::InverseDistance { power: 2.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpatialEstimate {
+    pub value: f64,
+    pub weights: Vec<(String, f64)>,
+    pub variance: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StationRegistry {
+    stations: HashMap<String, Station>,
+    aliases: HashMap<String, String>,
+}
+
+impl StationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, station: Station) -> Result<(), Box<dyn std::error::Error>> {
+        if !(-90.0..=90.0).contains(&station.latitude) || !(-180.0..=180.0).contains(&station.longitude) {
+            return Err(format!("Station {} has invalid coordinates", station.id).into());
+        }
+
+        let names = std::iter::once(&station.id).chain(station.aliases.iter());
+        for name in names {
+            if let Some(owner) = self.aliases.get(&normalize(name)) {
+                if owner != &station.id {
+                    return Err(format!("Alias {} already belongs to station {}", name, owner).into());
+                }
+            }
+        }
+
+        if let Some(previous) = self.stations.remove(&station.id) {
+            for alias in &previous.aliases {
+                self.aliases.remove(&normalize(alias));
+            }
+        }
+
+        self.aliases.insert(normalize(&station.id), station.id.clone());
+        for alias in &station.aliases {
+            self.aliases.insert(normalize(alias), station.id.clone());
+        }
+        self.stations.insert(station.id.clone(), station);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Station> {
+        self.stations.get(id)
+    }
+
+    // Accepts a station ID or any alias, case-insensitively.
+    pub fn resolve(&self, name: &str) -> Option<&Station> {
+        self.aliases.get(&normalize(name)).and_then(|id| self.stations.get(id))
+    }
+
+    pub fn stations(&self) -> impl Iterator<Item = &Station> {
+        self.stations.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    pub fn nearest(&self, latitude: f64, longitude: f64, count: usize) -> Vec<StationDistance> {
+        let mut all = self.with_distances(latitude, longitude);
+        all.truncate(count);
+        all
+    }
+
+    pub fn within_radius(&self, latitude: f64, longitude: f64, radius_km: f64) -> Vec<StationDistance> {
+        self.with_distances(latitude, longitude)
+            .into_iter()
+            .take_while(|s| s.distance_km <= radius_km)
+            .collect()
+    }
+
+    // Neighbour lists in the shape QC spatial checks expect.
+    pub fn neighbor_map(&self, radius_km: f64) -> HashMap<String, Vec<String>> {
+        self.stations
+            .values()
+            .map(|station| {
+                let neighbors = self
+                    .within_radius(station.latitude, station.longitude, radius_km)
+                    .into_iter()
+                    .filter(|n| n.station.id != station.id)
+                    .map(|n| n.station.id)
+                    .collect();
+                (station.id.clone(), neighbors)
+            })
+            .collect()
+    }
+
+    fn with_distances(&self, latitude: f64, longitude: f64) -> Vec<StationDistance> {
+        let mut all: Vec<StationDistance> = self.stations
+            .values()
+            .map(|station| StationDistance {
+                station: station.clone(),
+                distance_km: haversine_km(latitude, longitude, station.latitude, station.longitude),
+            })
+            .collect();
+        all.sort_by(|a, b| {
+            a.distance_km
+                .partial_cmp(&b.distance_km)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.station.id.cmp(&b.station.id))
+        });
+        all
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+pub fn interpolate(samples: &[(&Station, f64)], latitude: f64, longitude: f64, method: SpatialMethod) -> Option<SpatialEstimate> {
+    let samples: Vec<&(&Station, f64)> = samples.iter().filter(|(_, v)| v.is_finite()).collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    let distances: Vec<f64> = samples
+        .iter()
+        .map(|(s, _)| haversine_km(latitude, longitude, s.latitude, s.longitude))
+        .collect();
+
+    // A station at the target location is the answer under either method.
+    if let Some(i) = distances.iter().position(|d| *d < 1e-6) {
+        return Some(SpatialEstimate {
+            value: samples[i].1,
+            weights: vec![(samples[i].0.id.clone(), 1.0)],
+            variance: Some(0.0),
+        });
+    }
+
+    let (weights, variance) = match method {
+        SpatialMethod::InverseDistance { power } => {
+            let raw: Vec<f64> = distances.iter().map(|d| 1.0 / d.powf(power)).collect();
+            let total: f64 = raw.iter().sum();
+            (raw.iter().map(|w| w / total).collect::<Vec<f64>>(), None)
+        }
+        SpatialMethod::KrigingLite { range_km, sill, nugget } => {
+            let variogram = |h: f64| if h <= 0.0 { 0.0 } else { nugget + (sill - nugget) * (1.0 - (-3.0 * h / range_km).exp()) };
+            kriging_weights(&samples, &distances, variogram)?
+        }
+    };
+
+    Some(SpatialEstimate {
+        value: samples.iter().zip(weights.iter()).map(|((_, v), w)| v * w).sum(),
+        weights: samples.iter().zip(weights.iter()).map(|((s, _), w)| (s.id.clone(), *w)).collect(),
+        variance,
+    })
+}
+
+fn kriging_weights<F: Fn(f64) -> f64>(samples: &[&(&Station, f64)], distances: &[f64], variogram: F) -> Option<(Vec<f64>, Option<f64>)> {
+    let n = samples.len();
+    let size = n + 1;
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+
+    for i in 0..n {
+        for j in 0..n {
+            let (a, b) = (samples[i].0, samples[j].0);
+            matrix[i][j] = variogram(haversine_km(a.latitude, a.longitude, b.latitude, b.longitude));
+        }
+        matrix[i][n] = 1.0;
+        matrix[n][i] = 1.0;
+        matrix[i][size] = variogram(distances[i]);
+    }
+    matrix[n][size] = 1.0;
+
+    let solution = solve_linear(matrix)?;
+    let weights = solution[..n].to_vec();
+    let lagrange = solution[n];
+    let variance = weights.iter().zip(distances.iter()).map(|(w, d)| w * variogram(*d)).sum::<f64>() + lagrange;
+
+    Some((weights, Some(variance.max(0.0))))
+}
+
+// Gaussian elimination with partial pivoting on an augmented matrix.
+pub(crate) fn solve_linear(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = matrix.len();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|a, b| matrix[*a][col].abs().partial_cmp(&matrix[*b][col].abs()).unwrap_or(std::cmp::Ordering::Equal))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+
+        let (upper, lower) = matrix.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower.iter_mut() {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot;
+            }
+        }
+    }
+
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (matrix[row][n] - tail) / matrix[row][row];
+    }
+    Some(solution)
+}
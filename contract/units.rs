@@ -0,0 +1,365 @@
+use serde::{Serialize, Deserialize};
+
+use crate::data_processor::WeatherDataPoint;
+use crate::data_query::WeatherVariable;
+use crate::derived_variables::DerivedVariable;
+
+// Everything inside the processor is canonical: °C, %, hPa, m/s, mm and degrees.
+// Other units exist only at the ingestion and export boundaries.
+
+// On-chain values are fixed-point integers in canonical units.
+pub const ORACLE_SCALE: f64 = 100.0;
+
+pub fn to_oracle_fixed(value: f64) -> i64 {
+    (value * ORACLE_SCALE).round() as i64
+}
+
+pub fn from_oracle_fixed(value: i64) -> f64 {
+    value as f64 / ORACLE_SCALE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => value,
+            TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            TemperatureUnit::Kelvin => value - 273.15,
+        }
+    }
+
+    pub fn from_canonical(&self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "degC",
+            TemperatureUnit::Fahrenheit => "degF",
+            TemperatureUnit::Kelvin => "K",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PressureUnit {
+    Hectopascal,
+    Kilopascal,
+    InchesOfMercury,
+    MillimetersOfMercury,
+}
+
+impl PressureUnit {
+    fn hpa_per_unit(&self) -> f64 {
+        match self {
+            PressureUnit::Hectopascal => 1.0,
+            PressureUnit::Kilopascal => 10.0,
+            PressureUnit::InchesOfMercury => 33.8639,
+            PressureUnit::MillimetersOfMercury => 1.333224,
+        }
+    }
+
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        value * self.hpa_per_unit()
+    }
+
+    pub fn from_canonical(&self, hpa: f64) -> f64 {
+        hpa / self.hpa_per_unit()
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PressureUnit::Hectopascal => "hPa",
+            PressureUnit::Kilopascal => "kPa",
+            PressureUnit::InchesOfMercury => "inHg",
+            PressureUnit::MillimetersOfMercury => "mmHg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpeedUnit {
+    MetersPerSecond,
+    KilometersPerHour,
+    MilesPerHour,
+    Knots,
+}
+
+impl SpeedUnit {
+    fn ms_per_unit(&self) -> f64 {
+        match self {
+            SpeedUnit::MetersPerSecond => 1.0,
+            SpeedUnit::KilometersPerHour => 1.0 / 3.6,
+            SpeedUnit::MilesPerHour => 0.44704,
+            SpeedUnit::Knots => 0.514444,
+        }
+    }
+
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        value * self.ms_per_unit()
+    }
+
+    pub fn from_canonical(&self, ms: f64) -> f64 {
+        ms / self.ms_per_unit()
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            SpeedUnit::MetersPerSecond => "m/s",
+            SpeedUnit::KilometersPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::Knots => "kn",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LengthUnit {
+    Millimeters,
+    Centimeters,
+    Inches,
+}
+
+impl LengthUnit {
+    fn mm_per_unit(&self) -> f64 {
+        match self {
+            LengthUnit::Millimeters => 1.0,
+            LengthUnit::Centimeters => 10.0,
+            LengthUnit::Inches => 25.4,
+        }
+    }
+
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        value * self.mm_per_unit()
+    }
+
+    pub fn from_canonical(&self, mm: f64) -> f64 {
+        mm / self.mm_per_unit()
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            LengthUnit::Millimeters => "mm",
+            LengthUnit::Centimeters => "cm",
+            LengthUnit::Inches => "in",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AngleUnit {
+    Degrees,
+    Radians,
+}
+
+impl AngleUnit {
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => value,
+            AngleUnit::Radians => value.to_degrees(),
+        }
+    }
+
+    pub fn from_canonical(&self, degrees: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => degrees,
+            AngleUnit::Radians => degrees.to_radians(),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AngleUnit::Degrees => "deg",
+            AngleUnit::Radians => "rad",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Unit {
+    Temperature(TemperatureUnit),
+    Pressure(PressureUnit),
+    Speed(SpeedUnit),
+    Length(LengthUnit),
+    Angle(AngleUnit),
+    Percent,
+    GramsPerCubicMeter,
+}
+
+impl Unit {
+    const ALL: [Unit; 18] = [
+        Unit::Temperature(TemperatureUnit::Celsius),
+        Unit::Temperature(TemperatureUnit::Fahrenheit),
+        Unit::Temperature(TemperatureUnit::Kelvin),
+        Unit::Pressure(PressureUnit::Hectopascal),
+        Unit::Pressure(PressureUnit::Kilopascal),
+        Unit::Pressure(PressureUnit::InchesOfMercury),
+        Unit::Pressure(PressureUnit::MillimetersOfMercury),
+        Unit::Speed(SpeedUnit::MetersPerSecond),
+        Unit::Speed(SpeedUnit::KilometersPerHour),
+        Unit::Speed(SpeedUnit::MilesPerHour),
+        Unit::Speed(SpeedUnit::Knots),
+        Unit::Length(LengthUnit::Millimeters),
+        Unit::Length(LengthUnit::Centimeters),
+        Unit::Length(LengthUnit::Inches),
+        Unit::Angle(AngleUnit::Degrees),
+        Unit::Angle(AngleUnit::Radians),
+        Unit::Percent,
+        Unit::GramsPerCubicMeter,
+    ];
+
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        match self {
+            Unit::Temperature(unit) => unit.to_canonical(value),
+            Unit::Pressure(unit) => unit.to_canonical(value),
+            Unit::Speed(unit) => unit.to_canonical(value),
+            Unit::Length(unit) => unit.to_canonical(value),
+            Unit::Angle(unit) => unit.to_canonical(value),
+            Unit::Percent | Unit::GramsPerCubicMeter => value,
+        }
+    }
+
+    pub fn from_canonical(&self, value: f64) -> f64 {
+        match self {
+            Unit::Temperature(unit) => unit.from_canonical(value),
+            Unit::Pressure(unit) => unit.from_canonical(value),
+            Unit::Speed(unit) => unit.from_canonical(value),
+            Unit::Length(unit) => unit.from_canonical(value),
+            Unit::Angle(unit) => unit.from_canonical(value),
+            Unit::Percent | Unit::GramsPerCubicMeter => value,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Temperature(unit) => unit.symbol(),
+            Unit::Pressure(unit) => unit.symbol(),
+            Unit::Speed(unit) => unit.symbol(),
+            Unit::Length(unit) => unit.symbol(),
+            Unit::Angle(unit) => unit.symbol(),
+            Unit::Percent => "%",
+            Unit::GramsPerCubicMeter => "g/m3",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Unit> {
+        let symbol = symbol.trim();
+        // hPa and mbar are the same unit.
+        if symbol.eq_ignore_ascii_case("mbar") {
+            return Some(Unit::Pressure(PressureUnit::Hectopascal));
+        }
+        Unit::ALL.iter().copied().find(|unit| unit.symbol() == symbol)
+    }
+
+    // Both units measure the same kind of quantity, so converting between them is meaningful.
+    pub fn same_dimension(&self, other: &Unit) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitSystem {
+    pub temperature: TemperatureUnit,
+    pub pressure: PressureUnit,
+    pub speed: SpeedUnit,
+    pub precipitation: LengthUnit,
+    pub direction: AngleUnit,
+}
+
+impl Default for UnitSystem {
+    fn default() -> Self {
+        Self::metric()
+    }
+}
+
+impl UnitSystem {
+    // The canonical units.
+    pub fn metric() -> Self {
+        Self {
+            temperature: TemperatureUnit::Celsius,
+            pressure: PressureUnit::Hectopascal,
+            speed: SpeedUnit::MetersPerSecond,
+            precipitation: LengthUnit::Millimeters,
+            direction: AngleUnit::Degrees,
+        }
+    }
+
+    // What US markets settle in.
+    pub fn us_customary() -> Self {
+        Self {
+            temperature: TemperatureUnit::Fahrenheit,
+            pressure: PressureUnit::InchesOfMercury,
+            speed: SpeedUnit::MilesPerHour,
+            precipitation: LengthUnit::Inches,
+            direction: AngleUnit::Degrees,
+        }
+    }
+
+    pub fn unit(&self, variable: WeatherVariable) -> Unit {
+        match variable {
+            WeatherVariable::Temperature => Unit::Temperature(self.temperature),
+            WeatherVariable::Humidity => Unit::Percent,
+            WeatherVariable::Pressure => Unit::Pressure(self.pressure),
+            WeatherVariable::WindSpeed => Unit::Speed(self.speed),
+            WeatherVariable::WindDirection => Unit::Angle(self.direction),
+            WeatherVariable::Precipitation => Unit::Length(self.precipitation),
+        }
+    }
+
+    pub fn derived_unit(&self, variable: DerivedVariable) -> Unit {
+        match variable {
+            DerivedVariable::DewPoint
+            | DerivedVariable::HeatIndex
+            | DerivedVariable::WindChill
+            | DerivedVariable::ApparentTemperature => Unit::Temperature(self.temperature),
+            DerivedVariable::AbsoluteHumidity => Unit::GramsPerCubicMeter,
+            DerivedVariable::SeaLevelPressure => Unit::Pressure(self.pressure),
+            DerivedVariable::WindU | DerivedVariable::WindV => Unit::Speed(self.speed),
+        }
+    }
+
+    pub fn to_canonical(&self, point: &WeatherDataPoint) -> WeatherDataPoint {
+        self.map_point(point, |unit, value| unit.to_canonical(value))
+    }
+
+    pub fn from_canonical(&self, point: &WeatherDataPoint) -> WeatherDataPoint {
+        self.map_point(point, |unit, value| unit.from_canonical(value))
+    }
+
+    fn map_point<F: Fn(Unit, f64) -> f64>(&self, point: &WeatherDataPoint, convert: F) -> WeatherDataPoint {
+        let value = |variable: WeatherVariable| convert(self.unit(variable), variable.value(point));
+        WeatherDataPoint {
+            temperature: value(WeatherVariable::Temperature),
+            humidity: value(WeatherVariable::Humidity),
+            pressure: value(WeatherVariable::Pressure),
+            wind_speed: value(WeatherVariable::WindSpeed),
+            wind_direction: value(WeatherVariable::WindDirection),
+            precipitation: value(WeatherVariable::Precipitation),
+            ..point.clone()
+        }
+    }
+}
+
+// Column header with its unit, e.g. `temperature[degF]`.
+pub fn annotated_name(name: &str, unit: Unit) -> String {
+    format!("{}[{}]", name, unit.symbol())
+}
+
+// Splits `temperature[degF]` into the name and its unit; unannotated names have no unit.
+pub fn parse_annotated_name(header: &str) -> (&str, Option<&str>) {
+    let header = header.trim();
+    match header.strip_suffix(']').and_then(|rest| rest.split_once('[')) {
+        Some((name, unit)) => (name.trim(), Some(unit)),
+        None => (header, None),
+    }
+}